@@ -1,5 +1,25 @@
-use std::collections::BTreeMap;
+use alloc::{collections::BTreeMap, string::String, vec::Vec};
+use core::{
+    fmt::{self, Write},
+    ops::Index,
+    str::Utf8Error,
+};
 
+// Binary bytestrings longer than this are summarised by `to_pretty_string`
+const PRETTY_HEX_LIMIT: usize = 20;
+// Leading bytes shown for summarised bytestrings, e.g. the start of the first piece hash
+const PRETTY_PREFIX_LEN: usize = 4;
+
+/// A decoded bencode value.
+///
+/// Values can be built from plain Rust types:
+///
+/// ```
+/// use bencoding::BencodeType;
+///
+/// let list = BencodeType::from(vec![BencodeType::from("spam"), 42.into()]);
+/// assert_eq!(bencoding::encode(list), b"l4:spami42ee");
+/// ```
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
 pub enum BencodeType {
     ByteString(Vec<u8>),
@@ -7,3 +27,841 @@ pub enum BencodeType {
     List(Vec<BencodeType>),
     Dictionary(BTreeMap<Vec<u8>, BencodeType>),
 }
+
+/// One step into a nested value, see `BencodeType::lookup`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PathSegment<'a> {
+    Key(&'a [u8]),
+    Index(usize),
+}
+
+/// Counts of each kind of value in a tree, see `BencodeType::stats`.
+/// Dictionary keys are not counted as bytestrings.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct BencodeStats {
+    pub integers: usize,
+    pub byte_strings: usize,
+    pub lists: usize,
+    pub dictionaries: usize,
+    /// Total length of all bytestring values
+    pub byte_string_bytes: usize,
+    /// Deepest nesting of lists and dictionaries, 0 for a lone scalar
+    pub max_depth: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StrError {
+    NotByteString,
+    InvalidUtf8(Utf8Error),
+}
+
+impl BencodeType {
+    pub fn as_integer(&self) -> Option<i64> {
+        match self {
+            Self::Integer(i) => Some(*i),
+            _ => None,
+        }
+    }
+
+    pub fn as_bytes(&self) -> Option<&[u8]> {
+        match self {
+            Self::ByteString(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn as_list(&self) -> Option<&[BencodeType]> {
+        match self {
+            Self::List(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    pub fn as_dict(&self) -> Option<&BTreeMap<Vec<u8>, BencodeType>> {
+        match self {
+            Self::Dictionary(d) => Some(d),
+            _ => None,
+        }
+    }
+
+    pub fn iter_dict(&self) -> Option<impl Iterator<Item = (&[u8], &BencodeType)>> {
+        Some(self.as_dict()?.iter().map(|(k, v)| (k.as_slice(), v)))
+    }
+
+    pub fn iter_list(&self) -> Option<impl Iterator<Item = &BencodeType>> {
+        Some(self.as_list()?.iter())
+    }
+
+    /// Byte length of a bytestring or element count of a container, `None` for integers.
+    pub fn len(&self) -> Option<usize> {
+        match self {
+            Self::ByteString(s) => Some(s.len()),
+            Self::Integer(_) => None,
+            Self::List(v) => Some(v.len()),
+            Self::Dictionary(d) => Some(d.len()),
+        }
+    }
+
+    pub fn is_empty(&self) -> Option<bool> {
+        self.len().map(|len| len == 0)
+    }
+
+    /// Lowercase name of the variant, for error messages.
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Self::ByteString(_) => "bytestring",
+            Self::Integer(_) => "integer",
+            Self::List(_) => "list",
+            Self::Dictionary(_) => "dictionary",
+        }
+    }
+
+    /// Borrows the bytestring as text, `None` if it isn't one or isn't valid UTF-8.
+    pub fn as_str(&self) -> Option<&str> {
+        self.try_as_str().ok()
+    }
+
+    pub fn try_as_str(&self) -> Result<&str, StrError> {
+        let bytes = self.as_bytes().ok_or(StrError::NotByteString)?;
+        core::str::from_utf8(bytes).map_err(StrError::InvalidUtf8)
+    }
+
+    /// Looks up `key` when this is a dictionary.
+    pub fn get(&self, key: impl AsRef<[u8]>) -> Option<&BencodeType> {
+        self.as_dict()?.get(key.as_ref())
+    }
+
+    /// Follows a chain of dictionary keys, e.g. `&[b"info", b"name"]`.
+    pub fn path(&self, segments: &[&[u8]]) -> Option<&BencodeType> {
+        segments
+            .iter()
+            .try_fold(self, |value, segment| value.get(segment))
+    }
+
+    /// Like `path`, but can also step into lists by index.
+    pub fn lookup(&self, segments: &[PathSegment]) -> Option<&BencodeType> {
+        segments
+            .iter()
+            .try_fold(self, |value, segment| match segment {
+                PathSegment::Key(key) => value.get(key),
+                PathSegment::Index(index) => value.as_list()?.get(*index),
+            })
+    }
+
+    pub fn stats(&self) -> BencodeStats {
+        let mut stats = BencodeStats::default();
+        self.collect_stats(&mut stats, 0);
+        stats
+    }
+
+    fn collect_stats(&self, stats: &mut BencodeStats, depth: usize) {
+        match self {
+            Self::ByteString(s) => {
+                stats.byte_strings += 1;
+                stats.byte_string_bytes += s.len();
+            }
+            Self::Integer(_) => stats.integers += 1,
+            Self::List(v) => {
+                stats.lists += 1;
+                stats.max_depth = stats.max_depth.max(depth + 1);
+                for item in v {
+                    item.collect_stats(stats, depth + 1);
+                }
+            }
+            Self::Dictionary(d) => {
+                stats.dictionaries += 1;
+                stats.max_depth = stats.max_depth.max(depth + 1);
+                for value in d.values() {
+                    value.collect_stats(stats, depth + 1);
+                }
+            }
+        }
+    }
+
+    /// Like `Display`, but cuts text bytestrings longer than `max` bytes short.
+    pub fn display_truncated(&self, max: usize) -> impl fmt::Display + '_ {
+        DisplayTruncated { value: self, max }
+    }
+
+    fn fmt_with(&self, f: &mut fmt::Formatter<'_>, max: Option<usize>) -> fmt::Result {
+        match self {
+            Self::ByteString(s) => fmt_bytes(s, f, max),
+            Self::Integer(i) => write!(f, "{i}"),
+            Self::List(v) => {
+                write!(f, "[")?;
+                for (i, item) in v.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    item.fmt_with(f, max)?;
+                }
+                write!(f, "]")
+            }
+            Self::Dictionary(d) => {
+                write!(f, "{{")?;
+                for (i, (k, v)) in d.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    fmt_bytes(k, f, max)?;
+                    write!(f, ": ")?;
+                    v.fmt_with(f, max)?;
+                }
+                write!(f, "}}")
+            }
+        }
+    }
+
+    /// Multi-line rendering for human inspection. Text bytestrings are quoted,
+    /// short binary ones are shown as hex and long ones as `<N bytes, 0a1b2c3d…>`.
+    pub fn to_pretty_string(&self) -> String {
+        let mut out = String::new();
+        self.write_pretty(&mut out, 0);
+        out
+    }
+
+    fn write_pretty(&self, out: &mut String, indent: usize) {
+        match self {
+            Self::ByteString(s) => write_pretty_bytes(out, s),
+            Self::Integer(i) => {
+                let _ = write!(out, "{i}");
+            }
+            Self::List(v) if v.is_empty() => out.push_str("[]"),
+            Self::List(v) => {
+                out.push_str("[\n");
+                for (i, item) in v.iter().enumerate() {
+                    push_indent(out, indent + 1);
+                    item.write_pretty(out, indent + 1);
+                    out.push_str(if i + 1 < v.len() { ",\n" } else { "\n" });
+                }
+                push_indent(out, indent);
+                out.push(']');
+            }
+            Self::Dictionary(d) if d.is_empty() => out.push_str("{}"),
+            Self::Dictionary(d) => {
+                out.push_str("{\n");
+                for (i, (k, v)) in d.iter().enumerate() {
+                    push_indent(out, indent + 1);
+                    write_pretty_bytes(out, k);
+                    out.push_str(": ");
+                    v.write_pretty(out, indent + 1);
+                    out.push_str(if i + 1 < d.len() { ",\n" } else { "\n" });
+                }
+                push_indent(out, indent);
+                out.push('}');
+            }
+        }
+    }
+
+    pub fn into_integer(self) -> Result<i64, BencodeType> {
+        match self {
+            Self::Integer(i) => Ok(i),
+            other => Err(other),
+        }
+    }
+
+    pub fn into_bytes(self) -> Result<Vec<u8>, BencodeType> {
+        match self {
+            Self::ByteString(s) => Ok(s),
+            other => Err(other),
+        }
+    }
+
+    pub fn into_list(self) -> Result<Vec<BencodeType>, BencodeType> {
+        match self {
+            Self::List(v) => Ok(v),
+            other => Err(other),
+        }
+    }
+
+    pub fn into_dict(self) -> Result<BTreeMap<Vec<u8>, BencodeType>, BencodeType> {
+        match self {
+            Self::Dictionary(d) => Ok(d),
+            other => Err(other),
+        }
+    }
+}
+
+impl From<i64> for BencodeType {
+    fn from(value: i64) -> Self {
+        Self::Integer(value)
+    }
+}
+
+impl From<&str> for BencodeType {
+    fn from(value: &str) -> Self {
+        Self::ByteString(value.as_bytes().to_vec())
+    }
+}
+
+impl From<String> for BencodeType {
+    fn from(value: String) -> Self {
+        Self::ByteString(value.into_bytes())
+    }
+}
+
+impl From<Vec<u8>> for BencodeType {
+    fn from(value: Vec<u8>) -> Self {
+        Self::ByteString(value)
+    }
+}
+
+impl From<Vec<BencodeType>> for BencodeType {
+    fn from(value: Vec<BencodeType>) -> Self {
+        Self::List(value)
+    }
+}
+
+impl From<BTreeMap<Vec<u8>, BencodeType>> for BencodeType {
+    fn from(value: BTreeMap<Vec<u8>, BencodeType>) -> Self {
+        Self::Dictionary(value)
+    }
+}
+
+impl FromIterator<BencodeType> for BencodeType {
+    fn from_iter<T: IntoIterator<Item = BencodeType>>(iter: T) -> Self {
+        Self::List(iter.into_iter().collect())
+    }
+}
+
+impl FromIterator<(Vec<u8>, BencodeType)> for BencodeType {
+    fn from_iter<T: IntoIterator<Item = (Vec<u8>, BencodeType)>>(iter: T) -> Self {
+        Self::Dictionary(iter.into_iter().collect())
+    }
+}
+
+fn fmt_bytes(bytes: &[u8], f: &mut fmt::Formatter<'_>, max: Option<usize>) -> fmt::Result {
+    let Ok(text) = core::str::from_utf8(bytes) else {
+        return write!(f, "<{} bytes>", bytes.len());
+    };
+
+    match max {
+        Some(max) if text.len() > max => {
+            let mut end = max;
+            while !text.is_char_boundary(end) {
+                end -= 1;
+            }
+            write!(f, "{:?}... ({} bytes)", &text[..end], text.len())
+        }
+        _ => write!(f, "{text:?}"),
+    }
+}
+
+fn write_pretty_bytes(out: &mut String, bytes: &[u8]) {
+    if let Ok(text) = core::str::from_utf8(bytes) {
+        let _ = write!(out, "{text:?}");
+        return;
+    }
+
+    if bytes.len() <= PRETTY_HEX_LIMIT {
+        out.push('<');
+        push_hex(out, bytes);
+        out.push('>');
+    } else {
+        let _ = write!(out, "<{} bytes, ", bytes.len());
+        push_hex(out, &bytes[..PRETTY_PREFIX_LEN]);
+        out.push_str("…>");
+    }
+}
+
+fn push_hex(out: &mut String, bytes: &[u8]) {
+    for byte in bytes {
+        let _ = write!(out, "{byte:02x}");
+    }
+}
+
+fn push_indent(out: &mut String, indent: usize) {
+    for _ in 0..indent {
+        out.push_str("  ");
+    }
+}
+
+impl fmt::Display for BencodeType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.fmt_with(f, None)
+    }
+}
+
+struct DisplayTruncated<'a> {
+    value: &'a BencodeType,
+    max: usize,
+}
+
+impl fmt::Display for DisplayTruncated<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.value.fmt_with(f, Some(self.max))
+    }
+}
+
+impl Index<&[u8]> for BencodeType {
+    type Output = BencodeType;
+
+    fn index(&self, key: &[u8]) -> &Self::Output {
+        self.get(key).expect("key not present in dictionary")
+    }
+}
+
+/// Only bytestrings compare equal, other variants are never equal to text.
+impl PartialEq<&str> for BencodeType {
+    fn eq(&self, other: &&str) -> bool {
+        self.as_bytes() == Some(other.as_bytes())
+    }
+}
+
+impl PartialEq<&[u8]> for BencodeType {
+    fn eq(&self, other: &&[u8]) -> bool {
+        self.as_bytes() == Some(*other)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn as_integer() {
+        assert_eq!(BencodeType::Integer(42).as_integer(), Some(42));
+        assert_eq!(BencodeType::ByteString(b"42".to_vec()).as_integer(), None);
+    }
+
+    #[test]
+    fn as_bytes() {
+        let input = BencodeType::ByteString(b"spam".to_vec());
+        assert_eq!(input.as_bytes(), Some(b"spam".as_slice()));
+        assert_eq!(BencodeType::Integer(3).as_bytes(), None);
+    }
+
+    #[test]
+    fn as_list() {
+        let input = BencodeType::List(vec![BencodeType::Integer(1), BencodeType::Integer(2)]);
+        assert_eq!(
+            input.as_list(),
+            Some([BencodeType::Integer(1), BencodeType::Integer(2)].as_slice())
+        );
+        assert_eq!(BencodeType::Dictionary(BTreeMap::new()).as_list(), None);
+    }
+
+    #[test]
+    fn as_dict() {
+        let mut dict = BTreeMap::new();
+        dict.insert(b"cow".to_vec(), BencodeType::ByteString(b"moo".to_vec()));
+        let input = BencodeType::Dictionary(dict.clone());
+        assert_eq!(input.as_dict(), Some(&dict));
+        assert_eq!(BencodeType::List(vec![]).as_dict(), None);
+    }
+
+    #[test]
+    fn into_integer() {
+        assert_eq!(BencodeType::Integer(42).into_integer(), Ok(42));
+
+        let input = BencodeType::ByteString(b"42".to_vec());
+        assert_eq!(input.clone().into_integer(), Err(input));
+    }
+
+    #[test]
+    fn into_bytes() {
+        let input = BencodeType::ByteString(b"spam".to_vec());
+        assert_eq!(input.into_bytes(), Ok(b"spam".to_vec()));
+
+        let input = BencodeType::Integer(3);
+        assert_eq!(input.clone().into_bytes(), Err(input));
+    }
+
+    #[test]
+    fn into_list() {
+        let input = BencodeType::List(vec![BencodeType::Integer(1)]);
+        assert_eq!(input.into_list(), Ok(vec![BencodeType::Integer(1)]));
+
+        let input = BencodeType::Dictionary(BTreeMap::new());
+        assert_eq!(input.clone().into_list(), Err(input));
+    }
+
+    #[test]
+    fn into_dict() {
+        let mut dict = BTreeMap::new();
+        dict.insert(b"cow".to_vec(), BencodeType::ByteString(b"moo".to_vec()));
+        let input = BencodeType::Dictionary(dict.clone());
+        assert_eq!(input.into_dict(), Ok(dict));
+
+        let input = BencodeType::List(vec![BencodeType::Integer(1)]);
+        assert_eq!(input.clone().into_dict(), Err(input));
+    }
+
+    #[test]
+    fn get_present_key() {
+        let mut dict = BTreeMap::new();
+        dict.insert(
+            b"announce".to_vec(),
+            BencodeType::ByteString(b"url".to_vec()),
+        );
+        let input = BencodeType::Dictionary(dict);
+        assert_eq!(
+            input.get("announce"),
+            Some(&BencodeType::ByteString(b"url".to_vec()))
+        );
+        assert_eq!(
+            input.get(b"announce"),
+            Some(&BencodeType::ByteString(b"url".to_vec()))
+        );
+    }
+
+    #[test]
+    fn get_absent_key() {
+        let input = BencodeType::Dictionary(BTreeMap::new());
+        assert_eq!(input.get("announce"), None);
+    }
+
+    #[test]
+    fn get_non_dictionary() {
+        let input = BencodeType::List(vec![BencodeType::ByteString(b"announce".to_vec())]);
+        assert_eq!(input.get("announce"), None);
+    }
+
+    #[test]
+    fn index_present_key() {
+        let mut dict = BTreeMap::new();
+        dict.insert(b"length".to_vec(), BencodeType::Integer(3));
+        let input = BencodeType::Dictionary(dict);
+        assert_eq!(input[b"length".as_slice()], BencodeType::Integer(3));
+    }
+
+    #[test]
+    #[should_panic(expected = "key not present")]
+    fn index_absent_key() {
+        let input = BencodeType::Dictionary(BTreeMap::new());
+        let _ = &input[b"length".as_slice()];
+    }
+
+    fn nested_torrent() -> BencodeType {
+        let mut file = BTreeMap::new();
+        file.insert(b"length".to_vec(), BencodeType::Integer(7));
+        file.insert(
+            b"path".to_vec(),
+            BencodeType::List(vec![
+                BencodeType::ByteString(b"dir".to_vec()),
+                BencodeType::ByteString(b"file.txt".to_vec()),
+            ]),
+        );
+
+        let mut info = BTreeMap::new();
+        info.insert(
+            b"files".to_vec(),
+            BencodeType::List(vec![BencodeType::Dictionary(file)]),
+        );
+        info.insert(b"name".to_vec(), BencodeType::ByteString(b"root".to_vec()));
+
+        let mut torrent = BTreeMap::new();
+        torrent.insert(b"info".to_vec(), BencodeType::Dictionary(info));
+        BencodeType::Dictionary(torrent)
+    }
+
+    #[test]
+    fn path_nested_keys() {
+        let torrent = nested_torrent();
+        assert_eq!(
+            torrent.path(&[b"info", b"name"]),
+            Some(&BencodeType::ByteString(b"root".to_vec()))
+        );
+        assert_eq!(torrent.path(&[]), Some(&torrent));
+    }
+
+    #[test]
+    fn path_missing_segment() {
+        let torrent = nested_torrent();
+        assert_eq!(torrent.path(&[b"info", b"length"]), None);
+        assert_eq!(torrent.path(&[b"info", b"name", b"value"]), None);
+    }
+
+    #[test]
+    fn lookup_through_list() {
+        let torrent = nested_torrent();
+        let segments = [
+            PathSegment::Key(b"info"),
+            PathSegment::Key(b"files"),
+            PathSegment::Index(0),
+            PathSegment::Key(b"path"),
+            PathSegment::Index(1),
+        ];
+        assert_eq!(
+            torrent.lookup(&segments),
+            Some(&BencodeType::ByteString(b"file.txt".to_vec()))
+        );
+    }
+
+    #[test]
+    fn lookup_index_out_of_range() {
+        let torrent = nested_torrent();
+        let segments = [
+            PathSegment::Key(b"info"),
+            PathSegment::Key(b"files"),
+            PathSegment::Index(1),
+        ];
+        assert_eq!(torrent.lookup(&segments), None);
+        assert_eq!(torrent.lookup(&[PathSegment::Index(0)]), None);
+    }
+
+    fn mixed_structure() -> BencodeType {
+        let mut dict = BTreeMap::new();
+        dict.insert(
+            b"comment".to_vec(),
+            BencodeType::ByteString(b"a fairly long comment".to_vec()),
+        );
+        dict.insert(b"length".to_vec(), BencodeType::Integer(-12));
+        dict.insert(
+            b"list".to_vec(),
+            BencodeType::List(vec![
+                BencodeType::Integer(1),
+                BencodeType::ByteString(b"say \"hi\"".to_vec()),
+                BencodeType::List(vec![]),
+            ]),
+        );
+        dict.insert(
+            b"pieces".to_vec(),
+            BencodeType::ByteString(vec![0xff, 0xfe, 0x00, 0x01]),
+        );
+        BencodeType::Dictionary(dict)
+    }
+
+    #[test]
+    fn display_mixed() {
+        assert_eq!(
+            mixed_structure().to_string(),
+            r#"{"comment": "a fairly long comment", "length": -12, "list": [1, "say \"hi\"", []], "pieces": <4 bytes>}"#
+        );
+    }
+
+    #[test]
+    fn display_truncated() {
+        assert_eq!(
+            mixed_structure().display_truncated(8).to_string(),
+            r#"{"comment": "a fairly"... (21 bytes), "length": -12, "list": [1, "say \"hi\"", []], "pieces": <4 bytes>}"#
+        );
+    }
+
+    #[test]
+    fn stats_of_nested_value() {
+        let value =
+            crate::decode(b"d4:infod5:filesld6:lengthi5eee4:name3:fooe4:tagsl1:a2:bcli1eeee")
+                .unwrap();
+
+        assert_eq!(
+            value.stats(),
+            BencodeStats {
+                integers: 2,
+                byte_strings: 3,
+                lists: 3,
+                dictionaries: 3,
+                byte_string_bytes: 6,
+                max_depth: 4,
+            }
+        );
+        assert_eq!(BencodeType::Integer(1).stats().max_depth, 0);
+    }
+
+    #[test]
+    fn to_pretty_string() {
+        let mut info = BTreeMap::new();
+        info.insert(b"name".to_vec(), BencodeType::from("debian.iso"));
+        info.insert(b"piece length".to_vec(), BencodeType::Integer(262144));
+        let mut pieces = vec![0xde, 0xad, 0xbe, 0xef];
+        pieces.resize(60, 0xff);
+        info.insert(b"pieces".to_vec(), BencodeType::ByteString(pieces));
+
+        let mut root = BTreeMap::new();
+        root.insert(
+            b"announce-list".to_vec(),
+            BencodeType::List(vec![BencodeType::List(vec!["udp://t".into()])]),
+        );
+        root.insert(b"info".to_vec(), BencodeType::Dictionary(info));
+        root.insert(b"nodes".to_vec(), BencodeType::List(vec![]));
+        root.insert(b"raw".to_vec(), BencodeType::ByteString(vec![0xff, 0x00]));
+
+        let expected = r#"{
+  "announce-list": [
+    [
+      "udp://t"
+    ]
+  ],
+  "info": {
+    "name": "debian.iso",
+    "piece length": 262144,
+    "pieces": <60 bytes, deadbeef…>
+  },
+  "nodes": [],
+  "raw": <ff00>
+}"#;
+        assert_eq!(BencodeType::Dictionary(root).to_pretty_string(), expected);
+    }
+
+    #[test]
+    fn display_truncated_char_boundary() {
+        let input = BencodeType::ByteString("héllo".as_bytes().to_vec());
+        assert_eq!(
+            input.display_truncated(2).to_string(),
+            r#""h"... (6 bytes)"#
+        );
+    }
+
+    #[test]
+    fn from_conversions() {
+        assert_eq!(BencodeType::from(42), BencodeType::Integer(42));
+        assert_eq!(
+            BencodeType::from("spam"),
+            BencodeType::ByteString(b"spam".to_vec())
+        );
+        assert_eq!(
+            BencodeType::from(String::from("eggs")),
+            BencodeType::ByteString(b"eggs".to_vec())
+        );
+        assert_eq!(
+            BencodeType::from(vec![0xffu8, 0x00]),
+            BencodeType::ByteString(vec![0xff, 0x00])
+        );
+        assert_eq!(
+            BencodeType::from(vec![BencodeType::Integer(1)]),
+            BencodeType::List(vec![BencodeType::Integer(1)])
+        );
+        assert_eq!(
+            BencodeType::from(BTreeMap::new()),
+            BencodeType::Dictionary(BTreeMap::new())
+        );
+    }
+
+    #[test]
+    fn into_conversion() {
+        let value: BencodeType = "spam".into();
+        assert_eq!(value, BencodeType::ByteString(b"spam".to_vec()));
+    }
+
+    #[test]
+    fn collect_list() {
+        let list: BencodeType = (1..=3).map(BencodeType::from).collect();
+        assert_eq!(
+            list,
+            BencodeType::List(vec![
+                BencodeType::Integer(1),
+                BencodeType::Integer(2),
+                BencodeType::Integer(3),
+            ])
+        );
+    }
+
+    #[test]
+    fn collect_dictionary() {
+        let pairs = vec![
+            (b"spam".to_vec(), BencodeType::from("eggs")),
+            (b"cow".to_vec(), BencodeType::from("moo")),
+        ];
+        let dict: BencodeType = pairs.into_iter().collect();
+
+        let mut expected_dict = BTreeMap::new();
+        expected_dict.insert(b"cow".to_vec(), BencodeType::ByteString(b"moo".to_vec()));
+        expected_dict.insert(b"spam".to_vec(), BencodeType::ByteString(b"eggs".to_vec()));
+
+        assert_eq!(dict, BencodeType::Dictionary(expected_dict));
+    }
+
+    #[test]
+    fn as_str_ascii() {
+        let input = BencodeType::ByteString(b"announce".to_vec());
+        assert_eq!(input.as_str(), Some("announce"));
+        assert_eq!(input.try_as_str(), Ok("announce"));
+    }
+
+    #[test]
+    fn as_str_invalid_utf8() {
+        let input = BencodeType::ByteString(vec![0xff, 0xfe]);
+        assert_eq!(input.as_str(), None);
+        assert!(matches!(input.try_as_str(), Err(StrError::InvalidUtf8(_))));
+    }
+
+    #[test]
+    fn as_str_integer() {
+        let input = BencodeType::Integer(3);
+        assert_eq!(input.as_str(), None);
+        assert_eq!(input.try_as_str(), Err(StrError::NotByteString));
+    }
+
+    #[test]
+    fn len_bytestring() {
+        let input = BencodeType::ByteString(b"spam".to_vec());
+        assert_eq!(input.len(), Some(4));
+        assert_eq!(input.is_empty(), Some(false));
+        assert_eq!(BencodeType::ByteString(vec![]).is_empty(), Some(true));
+    }
+
+    #[test]
+    fn len_integer() {
+        let input = BencodeType::Integer(1234);
+        assert_eq!(input.len(), None);
+        assert_eq!(input.is_empty(), None);
+    }
+
+    #[test]
+    fn len_list() {
+        let input = BencodeType::List(vec![BencodeType::Integer(1), BencodeType::Integer(2)]);
+        assert_eq!(input.len(), Some(2));
+        assert_eq!(BencodeType::List(vec![]).is_empty(), Some(true));
+    }
+
+    #[test]
+    fn len_dictionary() {
+        let mut dict = BTreeMap::new();
+        dict.insert(b"cow".to_vec(), BencodeType::ByteString(b"moo".to_vec()));
+        let input = BencodeType::Dictionary(dict);
+        assert_eq!(input.len(), Some(1));
+        assert_eq!(input.is_empty(), Some(false));
+        assert_eq!(
+            BencodeType::Dictionary(BTreeMap::new()).is_empty(),
+            Some(true)
+        );
+    }
+
+    #[test]
+    fn iter_dict_keys() {
+        let dict = BencodeType::dict()
+            .insert("spam", "eggs")
+            .insert("cow", "moo")
+            .build();
+        let keys: Vec<&[u8]> = dict.iter_dict().unwrap().map(|(k, _)| k).collect();
+        assert_eq!(keys, vec![b"cow".as_slice(), b"spam".as_slice()]);
+        assert!(BencodeType::Integer(1).iter_dict().is_none());
+    }
+
+    #[test]
+    fn iter_list_values() {
+        let list = BencodeType::List(vec![BencodeType::Integer(1), BencodeType::Integer(2)]);
+        let values: Vec<i64> = list
+            .iter_list()
+            .unwrap()
+            .filter_map(BencodeType::as_integer)
+            .collect();
+        assert_eq!(values, vec![1, 2]);
+        assert!(BencodeType::Dictionary(BTreeMap::new())
+            .iter_list()
+            .is_none());
+    }
+
+    #[test]
+    fn type_name_each_variant() {
+        assert_eq!(BencodeType::from("a").type_name(), "bytestring");
+        assert_eq!(BencodeType::Integer(1).type_name(), "integer");
+        assert_eq!(BencodeType::List(Vec::new()).type_name(), "list");
+        assert_eq!(
+            BencodeType::Dictionary(BTreeMap::new()).type_name(),
+            "dictionary"
+        );
+    }
+
+    #[test]
+    fn eq_str_and_bytes() {
+        let value = BencodeType::from("announce");
+        assert!(value == "announce");
+        assert!(value != "info");
+        assert!(value == b"announce".as_slice());
+        assert!(value != b"info".as_slice());
+
+        assert!(BencodeType::Integer(1) != "1");
+        assert!(BencodeType::List(Vec::new()) != b"".as_slice());
+    }
+}
@@ -0,0 +1,144 @@
+use crate::bencode::BencodeType;
+use alloc::{
+    collections::{BTreeMap, BTreeSet},
+    sync::Arc,
+    vec::Vec,
+};
+
+/// A decoded value whose dictionary keys come from a `KeyInterner`, so equal
+/// keys across many decoded values share one allocation. See
+/// `decode_interned`.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum BencodeInterned {
+    ByteString(Vec<u8>),
+    Integer(i64),
+    List(Vec<BencodeInterned>),
+    Dictionary(BTreeMap<Arc<[u8]>, BencodeInterned>),
+}
+
+/// Shared storage for dictionary keys, meant to be reused across many calls
+/// to `decode_interned`.
+///
+/// ```
+/// let mut interner = bencoding::KeyInterner::new();
+/// bencoding::decode_interned(b"d4:spami1ee", &mut interner).unwrap();
+/// bencoding::decode_interned(b"d4:spami2ee", &mut interner).unwrap();
+///
+/// assert_eq!(interner.len(), 1);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct KeyInterner {
+    keys: BTreeSet<Arc<[u8]>>,
+}
+
+impl KeyInterner {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The shared copy of `key`, allocating it on first sight.
+    pub fn intern(&mut self, key: &[u8]) -> Arc<[u8]> {
+        if let Some(existing) = self.keys.get(key) {
+            return existing.clone();
+        }
+        let key: Arc<[u8]> = key.into();
+        self.keys.insert(key.clone());
+        key
+    }
+
+    pub fn contains(&self, key: &[u8]) -> bool {
+        self.keys.contains(key)
+    }
+
+    /// Number of distinct keys seen so far.
+    pub fn len(&self) -> usize {
+        self.keys.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+}
+
+impl BencodeInterned {
+    pub fn get(&self, key: impl AsRef<[u8]>) -> Option<&BencodeInterned> {
+        match self {
+            Self::Dictionary(d) => d.get(key.as_ref()),
+            _ => None,
+        }
+    }
+
+    pub fn iter_dict(&self) -> Option<impl Iterator<Item = (&Arc<[u8]>, &BencodeInterned)>> {
+        match self {
+            Self::Dictionary(d) => Some(d.iter()),
+            _ => None,
+        }
+    }
+
+    /// Copies the keys out into an owned `BencodeType`.
+    pub fn to_owned(&self) -> BencodeType {
+        match self {
+            Self::ByteString(s) => BencodeType::ByteString(s.clone()),
+            Self::Integer(i) => BencodeType::Integer(*i),
+            Self::List(v) => BencodeType::List(v.iter().map(Self::to_owned).collect()),
+            Self::Dictionary(d) => {
+                BencodeType::Dictionary(d.iter().map(|(k, v)| (k.to_vec(), v.to_owned())).collect())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::decode::decode_interned;
+
+    #[test]
+    fn many_torrents_share_keys() {
+        let torrents: Vec<Vec<u8>> = (0..50)
+            .map(|i| {
+                crate::encode(
+                    BencodeType::dict()
+                        .insert("announce", "http://tracker/announce")
+                        .insert(
+                            "info",
+                            BencodeType::dict()
+                                .insert("length", i)
+                                .insert("name", "file")
+                                .insert("piece length", 16384)
+                                .insert("pieces", vec![0; 20])
+                                .build(),
+                        )
+                        .build(),
+                )
+            })
+            .collect();
+
+        let mut interner = KeyInterner::new();
+        let decoded: Vec<_> = torrents
+            .iter()
+            .map(|torrent| decode_interned(torrent, &mut interner).unwrap())
+            .collect();
+
+        // Six distinct keys allocated once, not 300 times
+        assert_eq!(interner.len(), 6);
+        let info_key = interner.intern(b"info");
+        assert_eq!(Arc::strong_count(&info_key), 2 + decoded.len());
+
+        assert_eq!(
+            decoded[7].get("info").unwrap().get("length"),
+            Some(&BencodeInterned::Integer(7))
+        );
+        assert_eq!(decoded[7].to_owned(), crate::decode(&torrents[7]).unwrap());
+    }
+
+    #[test]
+    fn intern_returns_shared_copy() {
+        let mut interner = KeyInterner::new();
+        assert!(interner.is_empty());
+        let a = interner.intern(b"pieces");
+        let b = interner.intern(b"pieces");
+        assert!(Arc::ptr_eq(&a, &b));
+        assert_eq!(interner.len(), 1);
+    }
+}
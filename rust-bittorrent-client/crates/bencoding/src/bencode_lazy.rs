@@ -0,0 +1,64 @@
+use crate::bencode::BencodeType;
+use alloc::{collections::BTreeMap, vec::Vec};
+use core::ops::Range;
+
+/// A decoded value whose bytestrings are byte ranges into the input rather
+/// than copies, see `decode_lazy`. Dictionary keys are still copied so that
+/// values can be looked up by key.
+///
+/// ```
+/// let input = b"d4:name3:foo6:pieces20:aaaaaaaaaaaaaaaaaaaae";
+/// let value = bencoding::decode_lazy(input).unwrap();
+///
+/// assert_eq!(value.get("pieces").unwrap().as_range(), Some(&(23..43)));
+/// assert_eq!(value.get("name").unwrap().bytes(input), Some(&b"foo"[..]));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BencodeLazy {
+    ByteString(Range<usize>),
+    Integer(i64),
+    List(Vec<BencodeLazy>),
+    Dictionary(BTreeMap<Vec<u8>, BencodeLazy>),
+}
+
+impl BencodeLazy {
+    pub fn get(&self, key: impl AsRef<[u8]>) -> Option<&BencodeLazy> {
+        match self {
+            Self::Dictionary(d) => d.get(key.as_ref()),
+            _ => None,
+        }
+    }
+
+    pub fn as_range(&self) -> Option<&Range<usize>> {
+        match self {
+            Self::ByteString(range) => Some(range),
+            _ => None,
+        }
+    }
+
+    /// The bytestring's contents within `original`, the buffer this value
+    /// was decoded from.
+    pub fn bytes<'a>(&self, original: &'a [u8]) -> Option<&'a [u8]> {
+        original.get(self.as_range()?.clone())
+    }
+
+    /// Copies every bytestring out of `original` into an owned `BencodeType`.
+    ///
+    /// # Panics
+    ///
+    /// If `original` is shorter than the buffer this value was decoded from.
+    pub fn materialize(&self, original: &[u8]) -> BencodeType {
+        match self {
+            Self::ByteString(range) => BencodeType::ByteString(original[range.clone()].to_vec()),
+            Self::Integer(i) => BencodeType::Integer(*i),
+            Self::List(v) => {
+                BencodeType::List(v.iter().map(|item| item.materialize(original)).collect())
+            }
+            Self::Dictionary(d) => BencodeType::Dictionary(
+                d.iter()
+                    .map(|(k, v)| (k.clone(), v.materialize(original)))
+                    .collect(),
+            ),
+        }
+    }
+}
@@ -0,0 +1,49 @@
+use crate::bencode::BencodeType;
+use alloc::{collections::BTreeMap, vec::Vec};
+
+/// A decoded value that borrows its bytestrings from the input buffer.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum BencodeRef<'a> {
+    ByteString(&'a [u8]),
+    Integer(i64),
+    List(Vec<BencodeRef<'a>>),
+    Dictionary(BTreeMap<&'a [u8], BencodeRef<'a>>),
+}
+
+impl BencodeRef<'_> {
+    /// Copies the borrowed data into an owned `BencodeType`.
+    pub fn to_owned(&self) -> BencodeType {
+        match self {
+            Self::ByteString(s) => BencodeType::ByteString(s.to_vec()),
+            Self::Integer(i) => BencodeType::Integer(*i),
+            Self::List(v) => BencodeType::List(v.iter().map(BencodeRef::to_owned).collect()),
+            Self::Dictionary(d) => {
+                BencodeType::Dictionary(d.iter().map(|(k, v)| (k.to_vec(), v.to_owned())).collect())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn to_owned_nested() {
+        let mut dict = BTreeMap::new();
+        dict.insert(
+            b"spam".as_slice(),
+            BencodeRef::List(vec![BencodeRef::ByteString(b"a"), BencodeRef::Integer(1)]),
+        );
+        let input = BencodeRef::Dictionary(dict);
+
+        let expected = BencodeType::dict()
+            .insert(
+                "spam",
+                BencodeType::from(vec![BencodeType::from("a"), 1.into()]),
+            )
+            .build();
+
+        assert_eq!(input.to_owned(), expected);
+    }
+}
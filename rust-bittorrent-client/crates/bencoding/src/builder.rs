@@ -0,0 +1,91 @@
+use crate::bencode::BencodeType;
+use alloc::{collections::BTreeMap, vec::Vec};
+
+/// Chained construction of a `BencodeType::Dictionary`.
+///
+/// ```
+/// use bencoding::BencodeType;
+///
+/// let dict = BencodeType::dict().insert("cow", "moo").insert("spam", 3).build();
+/// assert_eq!(bencoding::encode(dict), b"d3:cow3:moo4:spami3ee");
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DictBuilder {
+    entries: BTreeMap<Vec<u8>, BencodeType>,
+}
+
+impl DictBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds an entry, replacing any earlier value under the same key.
+    pub fn insert(mut self, key: impl Into<Vec<u8>>, value: impl Into<BencodeType>) -> Self {
+        self.entries.insert(key.into(), value.into());
+        self
+    }
+
+    pub fn build(self) -> BencodeType {
+        BencodeType::Dictionary(self.entries)
+    }
+}
+
+impl BencodeType {
+    pub fn dict() -> DictBuilder {
+        DictBuilder::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::encode::encode;
+
+    #[test]
+    fn dict_builder_encode() {
+        let dict = BencodeType::dict()
+            .insert("spam", "eggs")
+            .insert(b"cow".to_vec(), "moo")
+            .build();
+        assert_eq!(encode(dict), b"d3:cow3:moo4:spam4:eggse".to_vec());
+    }
+
+    #[test]
+    fn dict_builder_out_of_order_inserts_encode_sorted() {
+        let dict = BencodeType::dict()
+            .insert("zebra", 1)
+            .insert("apple", 2)
+            .insert("mango", 3)
+            .insert("Zulu", 4)
+            .insert("ab", 5)
+            .build();
+
+        let encoded = encode(&dict);
+        assert_eq!(
+            encoded,
+            b"d4:Zului4e2:abi5e5:applei2e5:mangoi3e5:zebrai1ee".to_vec()
+        );
+        assert!(crate::is_canonical(&encoded).unwrap());
+    }
+
+    #[test]
+    fn dict_builder_nested() {
+        let info = BencodeType::dict().insert("length", 12).build();
+        let dict = BencodeType::dict().insert("info", info).build();
+
+        let mut expected_info = BTreeMap::new();
+        expected_info.insert(b"length".to_vec(), BencodeType::Integer(12));
+        let mut expected = BTreeMap::new();
+        expected.insert(b"info".to_vec(), BencodeType::Dictionary(expected_info));
+
+        assert_eq!(dict, BencodeType::Dictionary(expected));
+    }
+
+    #[test]
+    fn dict_builder_empty() {
+        assert_eq!(
+            DictBuilder::new().build(),
+            BencodeType::Dictionary(BTreeMap::new())
+        );
+    }
+}
@@ -0,0 +1,73 @@
+use core::cell::Cell;
+use std::alloc::{GlobalAlloc, Layout, System};
+
+/// Allocations made on the current thread while running a closure, see
+/// `count_allocations`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct AllocStats {
+    /// Calls to `alloc` and `realloc`
+    pub count: usize,
+    pub bytes: usize,
+}
+
+/// Wraps the system allocator, counting allocations per thread. Install it
+/// with `#[global_allocator]` in a test binary to use `count_allocations`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CountingAllocator;
+
+thread_local! {
+    static STATS: Cell<AllocStats> = const {
+        Cell::new(AllocStats { count: 0, bytes: 0 })
+    };
+}
+
+fn record(bytes: usize) {
+    // Fails only while the thread is being torn down
+    let _ = STATS.try_with(|stats| {
+        let AllocStats {
+            count,
+            bytes: total,
+        } = stats.get();
+        stats.set(AllocStats {
+            count: count + 1,
+            bytes: total + bytes,
+        });
+    });
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        record(layout.size());
+        System.alloc(layout)
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        record(layout.size());
+        System.alloc_zeroed(layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        record(new_size);
+        System.realloc(ptr, layout, new_size)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+/// Runs `f`, returning its result with the allocations it made on this
+/// thread. Counts stay zero unless `CountingAllocator` is the global allocator.
+pub fn count_allocations<T>(f: impl FnOnce() -> T) -> (T, AllocStats) {
+    let before = STATS.with(Cell::get);
+    let result = f();
+    let after = STATS.with(Cell::get);
+
+    (
+        result,
+        AllocStats {
+            count: after.count - before.count,
+            bytes: after.bytes - before.bytes,
+        },
+    )
+}
@@ -0,0 +1,369 @@
+use crate::bencode::BencodeType;
+use crate::decode::decode;
+use crate::serde_error::SerdeError;
+use serde::de::{self, DeserializeOwned, Visitor};
+use serde::forward_to_deserialize_any;
+use std::collections::btree_map;
+
+/// Deserializes a `T` from bencode.
+///
+/// Bytestrings are handed over as raw bytes, so `String` fields require valid UTF-8
+/// while `#[serde(with = "serde_bytes")]` fields such as `pieces` keep arbitrary
+/// data. Missing `Option` fields become `None`, and `bool`s are read from `0` or `1`.
+pub fn from_bytes<T: DeserializeOwned>(input: &[u8]) -> Result<T, SerdeError> {
+    let value = decode(input)?;
+    T::deserialize(ValueDeserializer { value })
+}
+
+struct ValueDeserializer {
+    value: BencodeType,
+}
+
+impl<'de> de::Deserializer<'de> for ValueDeserializer {
+    type Error = SerdeError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, SerdeError> {
+        match self.value {
+            BencodeType::ByteString(s) => visitor.visit_byte_buf(s),
+            BencodeType::Integer(i) => visitor.visit_i64(i),
+            BencodeType::List(v) => visitor.visit_seq(SeqDeserializer {
+                iter: v.into_iter(),
+            }),
+            BencodeType::Dictionary(d) => visitor.visit_map(MapDeserializer {
+                iter: d.into_iter(),
+                value: None,
+            }),
+        }
+    }
+
+    fn deserialize_bool<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, SerdeError> {
+        match self.value {
+            BencodeType::Integer(0) => visitor.visit_bool(false),
+            BencodeType::Integer(1) => visitor.visit_bool(true),
+            _ => self.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_str<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, SerdeError> {
+        match self.value {
+            BencodeType::ByteString(s) => match String::from_utf8(s) {
+                Ok(text) => visitor.visit_string(text),
+                Err(e) => visitor.visit_byte_buf(e.into_bytes()),
+            },
+            value => ValueDeserializer { value }.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_string<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, SerdeError> {
+        self.deserialize_str(visitor)
+    }
+
+    fn deserialize_char<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, SerdeError> {
+        self.deserialize_str(visitor)
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, SerdeError> {
+        // Bencode has no null, an absent value is a missing key
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_unit<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, SerdeError> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_unit_struct<V: Visitor<'de>>(
+        self,
+        _: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, SerdeError> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, SerdeError> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _: &'static str,
+        _: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, SerdeError> {
+        match self.value {
+            BencodeType::ByteString(variant) => visitor.visit_enum(EnumDeserializer {
+                variant,
+                value: None,
+            }),
+            BencodeType::Dictionary(d) if d.len() == 1 => {
+                let (variant, value) = d.into_iter().next().expect("length checked above");
+                visitor.visit_enum(EnumDeserializer {
+                    variant,
+                    value: Some(value),
+                })
+            }
+            _ => Err(SerdeError::Message(
+                "expected a bytestring or single-key dictionary for an enum".to_string(),
+            )),
+        }
+    }
+
+    fn deserialize_ignored_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, SerdeError> {
+        visitor.visit_unit()
+    }
+
+    forward_to_deserialize_any! {
+        i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 bytes byte_buf
+        seq tuple tuple_struct map struct identifier
+    }
+}
+
+struct SeqDeserializer {
+    iter: std::vec::IntoIter<BencodeType>,
+}
+
+impl<'de> de::SeqAccess<'de> for SeqDeserializer {
+    type Error = SerdeError;
+
+    fn next_element_seed<T: de::DeserializeSeed<'de>>(
+        &mut self,
+        seed: T,
+    ) -> Result<Option<T::Value>, SerdeError> {
+        self.iter
+            .next()
+            .map(|value| seed.deserialize(ValueDeserializer { value }))
+            .transpose()
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.iter.len())
+    }
+}
+
+struct MapDeserializer {
+    iter: btree_map::IntoIter<Vec<u8>, BencodeType>,
+    value: Option<BencodeType>,
+}
+
+impl<'de> de::MapAccess<'de> for MapDeserializer {
+    type Error = SerdeError;
+
+    fn next_key_seed<K: de::DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, SerdeError> {
+        let Some((key, value)) = self.iter.next() else {
+            return Ok(None);
+        };
+        self.value = Some(value);
+
+        seed.deserialize(ValueDeserializer {
+            value: BencodeType::ByteString(key),
+        })
+        .map(Some)
+    }
+
+    fn next_value_seed<V: de::DeserializeSeed<'de>>(
+        &mut self,
+        seed: V,
+    ) -> Result<V::Value, SerdeError> {
+        let value = self
+            .value
+            .take()
+            .ok_or_else(|| SerdeError::Message("value requested before key".to_string()))?;
+        seed.deserialize(ValueDeserializer { value })
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.iter.len())
+    }
+}
+
+struct EnumDeserializer {
+    variant: Vec<u8>,
+    value: Option<BencodeType>,
+}
+
+impl<'de> de::EnumAccess<'de> for EnumDeserializer {
+    type Error = SerdeError;
+    type Variant = VariantDeserializer;
+
+    fn variant_seed<V: de::DeserializeSeed<'de>>(
+        self,
+        seed: V,
+    ) -> Result<(V::Value, VariantDeserializer), SerdeError> {
+        let variant = seed.deserialize(ValueDeserializer {
+            value: BencodeType::ByteString(self.variant),
+        })?;
+
+        Ok((variant, VariantDeserializer { value: self.value }))
+    }
+}
+
+struct VariantDeserializer {
+    value: Option<BencodeType>,
+}
+
+impl VariantDeserializer {
+    fn payload(self) -> Result<ValueDeserializer, SerdeError> {
+        self.value
+            .map(|value| ValueDeserializer { value })
+            .ok_or_else(|| {
+                SerdeError::Message("expected an enum variant with a payload".to_string())
+            })
+    }
+}
+
+impl<'de> de::VariantAccess<'de> for VariantDeserializer {
+    type Error = SerdeError;
+
+    fn unit_variant(self) -> Result<(), SerdeError> {
+        match self.value {
+            None => Ok(()),
+            Some(_) => Err(SerdeError::Message("expected a unit variant".to_string())),
+        }
+    }
+
+    fn newtype_variant_seed<T: de::DeserializeSeed<'de>>(
+        self,
+        seed: T,
+    ) -> Result<T::Value, SerdeError> {
+        seed.deserialize(self.payload()?)
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(self, _: usize, visitor: V) -> Result<V::Value, SerdeError> {
+        de::Deserializer::deserialize_seq(self.payload()?, visitor)
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        _: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, SerdeError> {
+        de::Deserializer::deserialize_map(self.payload()?, visitor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ser::to_bytes;
+    use serde::{Deserialize, Serialize};
+    use std::collections::BTreeMap;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Torrent {
+        announce: String,
+        #[serde(rename = "creation date")]
+        creation_date: Option<i64>,
+        comment: Option<String>,
+        info: Info,
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Info {
+        length: u64,
+        name: String,
+        #[serde(rename = "piece length")]
+        piece_length: u32,
+        #[serde(with = "serde_bytes")]
+        pieces: Vec<u8>,
+        private: Option<bool>,
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    enum Event {
+        Started,
+        Progress(i64),
+        Peer { ip: String, port: u16 },
+    }
+
+    fn torrent_fixture() -> Vec<u8> {
+        let mut input = b"d8:announce15:http://tracker/13:creation datei1700000000e4:infod6:lengthi12e4:name8:file.txt12:piece lengthi16384e6:pieces20:".to_vec();
+        input.extend([0xff; 20]);
+        input.extend(b"ee");
+        input
+    }
+
+    #[test]
+    fn from_bytes_torrent() {
+        let torrent: Torrent = from_bytes(&torrent_fixture()).unwrap();
+        assert_eq!(
+            torrent,
+            Torrent {
+                announce: "http://tracker/".to_string(),
+                creation_date: Some(1700000000),
+                comment: None,
+                info: Info {
+                    length: 12,
+                    name: "file.txt".to_string(),
+                    piece_length: 16384,
+                    pieces: vec![0xff; 20],
+                    private: None,
+                },
+            }
+        );
+    }
+
+    #[test]
+    fn to_bytes_torrent_round_trip() {
+        let input = torrent_fixture();
+        let torrent: Torrent = from_bytes(&input).unwrap();
+        assert_eq!(to_bytes(&torrent).unwrap(), input);
+    }
+
+    #[test]
+    fn from_bytes_bool() {
+        #[derive(Debug, PartialEq, Deserialize)]
+        struct Flags {
+            private: bool,
+        }
+
+        let flags: Flags = from_bytes(b"d7:privatei1ee").unwrap();
+        assert_eq!(flags, Flags { private: true });
+        assert!(from_bytes::<Flags>(b"d7:privatei2ee").is_err());
+    }
+
+    #[test]
+    fn from_bytes_wrong_type() {
+        assert!(from_bytes::<Torrent>(b"d8:announcei1ee").is_err());
+        assert!(from_bytes::<u8>(b"i256e").is_err());
+        assert!(from_bytes::<String>(b"2:\xff\xfe").is_err());
+    }
+
+    #[test]
+    fn from_bytes_invalid_bencode() {
+        let err = from_bytes::<i64>(b"i1").unwrap_err();
+        assert!(matches!(err, SerdeError::Decode(_)));
+    }
+
+    #[test]
+    fn enum_round_trip() {
+        let events = vec![
+            Event::Started,
+            Event::Progress(3),
+            Event::Peer {
+                ip: "127.0.0.1".to_string(),
+                port: 6881,
+            },
+        ];
+        let encoded = to_bytes(&events).unwrap();
+        assert_eq!(
+            encoded,
+            b"l7:Startedd8:Progressi3eed4:Peerd2:ip9:127.0.0.14:porti6881eeee".to_vec()
+        );
+        assert_eq!(from_bytes::<Vec<Event>>(&encoded).unwrap(), events);
+    }
+
+    #[test]
+    fn from_bytes_map() {
+        let map: BTreeMap<String, i64> = from_bytes(b"d1:ai1e1:bi2ee").unwrap();
+        assert_eq!(
+            map,
+            BTreeMap::from([("a".to_string(), 1), ("b".to_string(), 2)])
+        );
+    }
+}
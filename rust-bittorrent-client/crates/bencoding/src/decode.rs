@@ -1,72 +1,356 @@
 use crate::bencode::BencodeType;
-use std::{collections::BTreeMap, str::Utf8Error};
+use crate::bencode_interned::{BencodeInterned, KeyInterner};
+use crate::bencode_lazy::BencodeLazy;
+use crate::bencode_ref::BencodeRef;
+use crate::encode::Encodable;
+use crate::span::{SpanMap, SpanRecorder};
+use alloc::{collections::BTreeMap, sync::Arc, vec::Vec};
+use core::{
+    fmt,
+    num::{IntErrorKind, ParseIntError},
+    str::Utf8Error,
+};
+#[cfg(feature = "std")]
+use std::io;
+
+pub const DEFAULT_MAX_DEPTH: usize = 100;
 
 #[derive(Debug)]
-struct Decoder<'a> {
+pub struct Decoder<'a> {
     input: &'a [u8],
     cursor: usize,
+    depth: usize,
+    max_depth: usize,
+    byte_budget: Option<usize>,
+    strict_key_order: bool,
+    reject_duplicates: bool,
+    spans: Option<SpanRecorder>,
+}
+
+/// How strictly `decode_with_options` treats its input. The default is
+/// `strict`, which matches `decode`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DecodeOptions {
+    /// Reject dictionary keys that aren't in ascending order
+    pub strict_key_order: bool,
+    /// Reject a key repeated within one dictionary, otherwise the last
+    /// occurrence wins
+    pub reject_duplicates: bool,
+    /// Reject input after the first value, otherwise it is ignored
+    pub reject_trailing_data: bool,
+    pub max_depth: usize,
+    /// See `Decoder::with_byte_budget`
+    pub byte_budget: Option<usize>,
+}
+
+impl Default for DecodeOptions {
+    fn default() -> Self {
+        Self::strict()
+    }
+}
+
+impl DecodeOptions {
+    /// Canonical bencode only, as needed when hashing.
+    pub fn strict() -> Self {
+        Self {
+            strict_key_order: true,
+            reject_duplicates: true,
+            reject_trailing_data: true,
+            max_depth: DEFAULT_MAX_DEPTH,
+            byte_budget: None,
+        }
+    }
+
+    /// Accepts unsorted and repeated keys and trailing data, as found in
+    /// sloppily generated torrents.
+    pub fn lenient() -> Self {
+        Self {
+            strict_key_order: false,
+            reject_duplicates: false,
+            reject_trailing_data: false,
+            ..Self::strict()
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct DecodeError {
+    kind: DecodeErrorKind,
+    offset: usize,
 }
 
 #[derive(Debug)]
-pub enum DecodeError {
+pub enum DecodeErrorKind {
     InvalidUtf8(Utf8Error),
     InvalidInteger,
     UnexpectedEndOfInput,
     UnexpectedCharacter(u8),
     UnexpectedFormat,
+    TrailingData,
+    MaxDepthExceeded,
+    DuplicateKey(Vec<u8>),
+    UnorderedKeys,
+    /// A dictionary key that isn't a bytestring, e.g. `di1ei2ee`
+    NonStringKey,
+    IntegerOverflow,
+    /// More bytestring bytes than the decoder's byte budget allows
+    BudgetExceeded,
+    #[cfg(feature = "std")]
+    Io(io::Error),
+}
+
+impl DecodeError {
+    pub fn new(kind: DecodeErrorKind, offset: usize) -> Self {
+        Self { kind, offset }
+    }
+
+    pub fn kind(&self) -> &DecodeErrorKind {
+        &self.kind
+    }
+
+    /// Byte offset into the input at which the error was detected.
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "invalid bencode at offset {}: {}",
+            self.offset, self.kind
+        )
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for DecodeError {}
+
+impl fmt::Display for DecodeErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidUtf8(e) => write!(f, "{e}"),
+            Self::InvalidInteger => write!(f, "invalid integer"),
+            Self::UnexpectedEndOfInput => write!(f, "unexpected end of input"),
+            Self::UnexpectedCharacter(c) => {
+                write!(f, "unexpected character '{}'", c.escape_ascii())
+            }
+            Self::UnexpectedFormat => write!(f, "unexpected format"),
+            Self::TrailingData => write!(f, "trailing data after the value"),
+            Self::MaxDepthExceeded => write!(f, "nested too deeply"),
+            Self::DuplicateKey(key) => write!(f, "duplicate key \"{}\"", key.escape_ascii()),
+            Self::UnorderedKeys => write!(f, "dictionary keys are not sorted"),
+            Self::NonStringKey => write!(f, "dictionary key is not a bytestring"),
+            Self::IntegerOverflow => write!(f, "integer out of range"),
+            Self::BudgetExceeded => write!(f, "byte budget exceeded"),
+            #[cfg(feature = "std")]
+            Self::Io(e) => write!(f, "{e}"),
+        }
+    }
 }
 
-impl From<Utf8Error> for DecodeError {
+impl From<Utf8Error> for DecodeErrorKind {
     fn from(value: Utf8Error) -> Self {
         Self::InvalidUtf8(value)
     }
 }
 
+#[cfg(feature = "std")]
+impl From<io::Error> for DecodeErrorKind {
+    fn from(value: io::Error) -> Self {
+        Self::Io(value)
+    }
+}
+
+impl From<ParseIntError> for DecodeErrorKind {
+    fn from(value: ParseIntError) -> Self {
+        match value.kind() {
+            IntErrorKind::PosOverflow | IntErrorKind::NegOverflow => Self::IntegerOverflow,
+            _ => Self::InvalidInteger,
+        }
+    }
+}
+
 impl<'a> Decoder<'a> {
     pub fn new(input: &'a [u8]) -> Self {
-        Self { input, cursor: 0 }
+        Self::with_max_depth(input, DEFAULT_MAX_DEPTH)
+    }
+
+    /// Lists and dictionaries nested deeper than `max_depth` are rejected.
+    pub fn with_max_depth(input: &'a [u8], max_depth: usize) -> Self {
+        Self {
+            input,
+            cursor: 0,
+            depth: 0,
+            max_depth,
+            byte_budget: None,
+            strict_key_order: true,
+            reject_duplicates: true,
+            spans: None,
+        }
+    }
+
+    /// See `DecodeOptions`, `reject_trailing_data` is up to the caller.
+    pub fn with_options(input: &'a [u8], options: &DecodeOptions) -> Self {
+        Self {
+            byte_budget: options.byte_budget,
+            strict_key_order: options.strict_key_order,
+            reject_duplicates: options.reject_duplicates,
+            ..Self::with_max_depth(input, options.max_depth)
+        }
+    }
+
+    /// Values decoded with [`Decoder::decode`] may hold at most `max_bytes`
+    /// bytes of bytestrings in total, dictionary keys included.
+    /// [`Decoder::decode_lazy`] copies only keys, so only keys count there, and
+    /// [`Decoder::decode_interned`] counts only keys not yet in its interner.
+    pub fn with_byte_budget(input: &'a [u8], max_bytes: usize) -> Self {
+        Self {
+            byte_budget: Some(max_bytes),
+            ..Self::new(input)
+        }
     }
 
     pub fn decode(&mut self) -> Result<BencodeType, DecodeError> {
-        match self.input[self.cursor] {
+        match self.peek()? {
             b'i' => self.decode_integer(),
             b'l' => self.decode_list(),
             b'd' => self.decode_dictionary(),
             b'0'..=b'9' => self.decode_bytestring(),
-            c => Err(DecodeError::UnexpectedCharacter(c)),
+            c => Err(self.error(DecodeErrorKind::UnexpectedCharacter(c))),
+        }
+    }
+
+    /// Decodes the next value without copying its bytestrings out of the input.
+    pub fn decode_ref(&mut self) -> Result<BencodeRef<'a>, DecodeError> {
+        match self.peek()? {
+            b'i' => Ok(BencodeRef::Integer(self.read_integer()?)),
+            b'l' => self.decode_ref_list(),
+            b'd' => self.decode_ref_dictionary(),
+            b'0'..=b'9' => Ok(BencodeRef::ByteString(self.read_bytestring()?)),
+            c => Err(self.error(DecodeErrorKind::UnexpectedCharacter(c))),
+        }
+    }
+
+    /// Decodes the next value, recording where its bytestrings sit in the
+    /// input instead of copying them.
+    pub fn decode_lazy(&mut self) -> Result<BencodeLazy, DecodeError> {
+        match self.peek()? {
+            b'i' => Ok(BencodeLazy::Integer(self.read_integer()?)),
+            b'l' => self.decode_lazy_list(),
+            b'd' => self.decode_lazy_dictionary(),
+            b'0'..=b'9' => {
+                let bytes = self.read_bytestring()?;
+                Ok(BencodeLazy::ByteString(
+                    self.cursor - bytes.len()..self.cursor,
+                ))
+            }
+            c => Err(self.error(DecodeErrorKind::UnexpectedCharacter(c))),
         }
     }
 
+    /// Number of input bytes consumed so far.
+    pub fn position(&self) -> usize {
+        self.cursor
+    }
+
+    /// Input not yet consumed.
+    pub fn remaining(&self) -> &'a [u8] {
+        &self.input[self.cursor..]
+    }
+
+    fn peek(&self) -> Result<u8, DecodeError> {
+        self.input
+            .get(self.cursor)
+            .copied()
+            .ok_or_else(|| self.end_of_input())
+    }
+
     fn consume_byte(&mut self) {
         self.cursor += 1;
     }
 
-    fn decode_bytestring(&mut self) -> Result<BencodeType, DecodeError> {
+    fn error(&self, kind: impl Into<DecodeErrorKind>) -> DecodeError {
+        self.error_at(self.cursor, kind)
+    }
+
+    fn error_at(&self, offset: usize, kind: impl Into<DecodeErrorKind>) -> DecodeError {
+        DecodeError::new(kind.into(), offset)
+    }
+
+    fn enter_container(&mut self) -> Result<(), DecodeError> {
+        if self.depth >= self.max_depth {
+            return Err(self.error(DecodeErrorKind::MaxDepthExceeded));
+        }
+        self.depth += 1;
+
+        Ok(())
+    }
+
+    fn leave_container(&mut self) {
+        self.depth -= 1;
+    }
+
+    fn end_of_input(&self) -> DecodeError {
+        self.error_at(self.input.len(), DecodeErrorKind::UnexpectedEndOfInput)
+    }
+
+    fn check_key(
+        &self,
+        previous: Option<&[u8]>,
+        seen: bool,
+        key: &[u8],
+        key_start: usize,
+    ) -> Result<(), DecodeError> {
+        check_dictionary_key(
+            key,
+            previous,
+            seen,
+            self.strict_key_order,
+            self.reject_duplicates,
+        )
+        .map_err(|kind| self.error_at(key_start, kind))
+    }
+
+    fn read_key(&mut self) -> Result<&'a [u8], DecodeError> {
+        if !self.peek()?.is_ascii_digit() {
+            return Err(self.error(DecodeErrorKind::NonStringKey));
+        }
+        self.read_bytestring()
+    }
+
+    fn read_bytestring(&mut self) -> Result<&'a [u8], DecodeError> {
+        self.peek()?;
         let start = self.cursor;
         while self.input.get(self.cursor) != Some(&b':') {
             self.cursor += 1;
 
             if self.cursor >= self.input.len() {
-                return Err(DecodeError::UnexpectedEndOfInput);
+                return Err(self.end_of_input());
             }
         }
 
         // Cursor now points to the colon
-        let string_len: usize = std::str::from_utf8(&self.input[start..self.cursor])?
-            .parse()
-            .map_err(|_| DecodeError::InvalidInteger)?;
+        let string_len = parse_length(&self.input[start..self.cursor])
+            .map_err(|kind| self.error_at(start, kind))?;
 
         self.consume_byte(); // Consume colon byte
 
         let string_start = self.cursor;
-        self.cursor += string_len;
+        let string_end = string_start
+            .checked_add(string_len)
+            .ok_or_else(|| self.error_at(start, DecodeErrorKind::IntegerOverflow))?;
+        if string_end > self.input.len() {
+            return Err(self.end_of_input());
+        }
+        self.cursor = string_end;
 
-        Ok(BencodeType::ByteString(
-            self.input[string_start..self.cursor].to_vec(),
-        ))
+        Ok(&self.input[string_start..string_end])
     }
 
-    fn decode_integer(&mut self) -> Result<BencodeType, DecodeError> {
+    fn read_integer(&mut self) -> Result<i64, DecodeError> {
+        self.peek()?;
         self.consume_byte(); // skip 'i'
         let start = self.cursor;
 
@@ -74,76 +358,475 @@ impl<'a> Decoder<'a> {
             self.cursor += 1;
 
             if self.cursor >= self.input.len() {
-                return Err(DecodeError::UnexpectedEndOfInput);
+                return Err(self.end_of_input());
             }
         }
 
-        let integer_str = std::str::from_utf8(&self.input[start..self.cursor])?;
+        let integer = parse_integer(&self.input[start..self.cursor])
+            .map_err(|kind| self.error_at(start, kind))?;
         self.consume_byte(); // skip 'e'
 
-        // leading zeros
-        if integer_str.len() > 1 && integer_str.starts_with('0') {
-            return Err(DecodeError::InvalidInteger);
-        }
+        Ok(integer)
+    }
 
-        // negative zero
-        if integer_str == "-0" {
-            return Err(DecodeError::InvalidInteger);
-        }
+    fn decode_bytestring(&mut self) -> Result<BencodeType, DecodeError> {
+        let start = self.cursor;
+        let bytes = self.read_bytestring()?;
+        self.spend_budget(bytes.len(), start)?;
+        Ok(BencodeType::ByteString(bytes.to_vec()))
+    }
 
-        let integer = integer_str
-            .parse()
-            .map_err(|_| DecodeError::InvalidInteger)?;
+    fn spend_budget(&mut self, len: usize, offset: usize) -> Result<(), DecodeError> {
+        if let Some(remaining) = self.byte_budget {
+            let remaining = remaining
+                .checked_sub(len)
+                .ok_or_else(|| self.error_at(offset, DecodeErrorKind::BudgetExceeded))?;
+            self.byte_budget = Some(remaining);
+        }
+        Ok(())
+    }
 
-        Ok(BencodeType::Integer(integer))
+    fn decode_integer(&mut self) -> Result<BencodeType, DecodeError> {
+        Ok(BencodeType::Integer(self.read_integer()?))
     }
 
     fn decode_list(&mut self) -> Result<BencodeType, DecodeError> {
+        self.peek()?;
+        self.enter_container()?;
         self.consume_byte(); // skip 'l'
         let mut res = Vec::new();
 
-        while self.input.get(self.cursor) != Some(&b'e') {
+        if let Some(spans) = &mut self.spans {
+            spans.enter_list();
+        }
+
+        while self.peek()? != b'e' {
             res.push(self.decode()?);
+        }
 
-            if self.cursor >= self.input.len() {
-                return Err(DecodeError::UnexpectedEndOfInput);
-            }
+        if let Some(spans) = &mut self.spans {
+            spans.leave_list();
         }
 
         self.consume_byte(); // skip 'e'
+        self.leave_container();
 
         Ok(BencodeType::List(res))
     }
 
     fn decode_dictionary(&mut self) -> Result<BencodeType, DecodeError> {
+        self.peek()?;
+        self.enter_container()?;
         self.consume_byte(); // skip 'd'
         let mut res: BTreeMap<Vec<u8>, BencodeType> = BTreeMap::new();
 
-        while self.input.get(self.cursor) != Some(&b'e') {
-            let k = self.decode_bytestring()?;
-            let k_inner = if let BencodeType::ByteString(inner) = k {
-                inner
-            } else {
-                return Err(DecodeError::UnexpectedFormat);
-            };
+        while self.peek()? != b'e' {
+            let key_start = self.cursor;
+            let key = self.read_key()?;
+            let previous = res.last_key_value().map(|(k, _)| k.as_slice());
+            self.check_key(previous, res.contains_key(key), key, key_start)?;
+            self.spend_budget(key.len(), key_start)?;
+
+            let recording = self
+                .spans
+                .as_mut()
+                .is_some_and(|spans| spans.enter_key(key));
+            let value_start = self.cursor;
             let v = self.decode()?;
+            if recording {
+                let value_end = self.cursor;
+                if let Some(spans) = &mut self.spans {
+                    spans.leave_key(value_start..value_end);
+                }
+            }
 
-            res.insert(k_inner, v);
+            res.insert(key.to_vec(), v);
+        }
 
-            if self.cursor >= self.input.len() {
-                return Err(DecodeError::UnexpectedEndOfInput);
+        self.consume_byte(); // skip 'e'
+        self.leave_container();
+
+        Ok(BencodeType::Dictionary(res))
+    }
+
+    fn decode_ref_list(&mut self) -> Result<BencodeRef<'a>, DecodeError> {
+        self.enter_container()?;
+        self.consume_byte(); // skip 'l'
+        let mut res = Vec::new();
+
+        while self.peek()? != b'e' {
+            res.push(self.decode_ref()?);
+        }
+
+        self.consume_byte(); // skip 'e'
+        self.leave_container();
+
+        Ok(BencodeRef::List(res))
+    }
+
+    fn decode_ref_dictionary(&mut self) -> Result<BencodeRef<'a>, DecodeError> {
+        self.enter_container()?;
+        self.consume_byte(); // skip 'd'
+        let mut res = BTreeMap::new();
+
+        while self.peek()? != b'e' {
+            let key_start = self.cursor;
+            let key = self.read_key()?;
+            let previous = res.last_key_value().map(|(k, _)| *k);
+            self.check_key(previous, res.contains_key(key), key, key_start)?;
+            let v = self.decode_ref()?;
+
+            res.insert(key, v);
+        }
+
+        self.consume_byte(); // skip 'e'
+        self.leave_container();
+
+        Ok(BencodeRef::Dictionary(res))
+    }
+
+    fn decode_lazy_list(&mut self) -> Result<BencodeLazy, DecodeError> {
+        self.enter_container()?;
+        self.consume_byte(); // skip 'l'
+        let mut res = Vec::new();
+
+        while self.peek()? != b'e' {
+            res.push(self.decode_lazy()?);
+        }
+
+        self.consume_byte(); // skip 'e'
+        self.leave_container();
+
+        Ok(BencodeLazy::List(res))
+    }
+
+    fn decode_lazy_dictionary(&mut self) -> Result<BencodeLazy, DecodeError> {
+        self.enter_container()?;
+        self.consume_byte(); // skip 'd'
+        let mut res: BTreeMap<Vec<u8>, BencodeLazy> = BTreeMap::new();
+
+        while self.peek()? != b'e' {
+            let key_start = self.cursor;
+            let key = self.read_key()?;
+            let previous = res.last_key_value().map(|(k, _)| k.as_slice());
+            self.check_key(previous, res.contains_key(key), key, key_start)?;
+            self.spend_budget(key.len(), key_start)?;
+            let v = self.decode_lazy()?;
+
+            res.insert(key.to_vec(), v);
+        }
+
+        self.consume_byte(); // skip 'e'
+        self.leave_container();
+
+        Ok(BencodeLazy::Dictionary(res))
+    }
+
+    /// Decodes the next value, taking dictionary keys from `interner`.
+    pub fn decode_interned(
+        &mut self,
+        interner: &mut KeyInterner,
+    ) -> Result<BencodeInterned, DecodeError> {
+        match self.peek()? {
+            b'i' => Ok(BencodeInterned::Integer(self.read_integer()?)),
+            b'l' => self.decode_interned_list(interner),
+            b'd' => self.decode_interned_dictionary(interner),
+            b'0'..=b'9' => {
+                let start = self.cursor;
+                let bytes = self.read_bytestring()?;
+                self.spend_budget(bytes.len(), start)?;
+                Ok(BencodeInterned::ByteString(bytes.to_vec()))
             }
+            c => Err(self.error(DecodeErrorKind::UnexpectedCharacter(c))),
+        }
+    }
+
+    fn decode_interned_list(
+        &mut self,
+        interner: &mut KeyInterner,
+    ) -> Result<BencodeInterned, DecodeError> {
+        self.enter_container()?;
+        self.consume_byte(); // skip 'l'
+        let mut res = Vec::new();
+
+        while self.peek()? != b'e' {
+            res.push(self.decode_interned(interner)?);
         }
 
         self.consume_byte(); // skip 'e'
+        self.leave_container();
 
-        Ok(BencodeType::Dictionary(res))
+        Ok(BencodeInterned::List(res))
+    }
+
+    fn decode_interned_dictionary(
+        &mut self,
+        interner: &mut KeyInterner,
+    ) -> Result<BencodeInterned, DecodeError> {
+        self.enter_container()?;
+        self.consume_byte(); // skip 'd'
+        let mut res: BTreeMap<Arc<[u8]>, BencodeInterned> = BTreeMap::new();
+
+        while self.peek()? != b'e' {
+            let key_start = self.cursor;
+            let key = self.read_key()?;
+            let previous = res.last_key_value().map(|(k, _)| &k[..]);
+            self.check_key(previous, res.contains_key(key), key, key_start)?;
+            if !interner.contains(key) {
+                self.spend_budget(key.len(), key_start)?;
+            }
+            let v = self.decode_interned(interner)?;
+
+            res.insert(interner.intern(key), v);
+        }
+
+        self.consume_byte(); // skip 'e'
+        self.leave_container();
+
+        Ok(BencodeInterned::Dictionary(res))
+    }
+
+    /// Checks that the next value is well-formed without building it.
+    ///
+    /// Applies the same checks as `decode` but never allocates. A key repeated
+    /// further down a dictionary is reported as `UnorderedKeys`, not as `DuplicateKey`.
+    pub fn validate(&mut self) -> Result<(), DecodeError> {
+        match self.peek()? {
+            b'i' => self.read_integer().map(|_| ()),
+            b'l' => self.validate_list(),
+            b'd' => self.validate_dictionary(),
+            b'0'..=b'9' => self.read_bytestring().map(|_| ()),
+            c => Err(self.error(DecodeErrorKind::UnexpectedCharacter(c))),
+        }
+    }
+
+    fn validate_list(&mut self) -> Result<(), DecodeError> {
+        self.enter_container()?;
+        self.consume_byte(); // skip 'l'
+
+        while self.peek()? != b'e' {
+            self.validate()?;
+        }
+
+        self.consume_byte(); // skip 'e'
+        self.leave_container();
+
+        Ok(())
+    }
+
+    fn validate_dictionary(&mut self) -> Result<(), DecodeError> {
+        self.enter_container()?;
+        self.consume_byte(); // skip 'd'
+        let mut previous = None;
+
+        while self.peek()? != b'e' {
+            let key_start = self.cursor;
+            let key = self.read_key()?;
+            self.check_key(previous, previous == Some(key), key, key_start)?;
+            self.validate()?;
+
+            previous = Some(key);
+        }
+
+        self.consume_byte(); // skip 'e'
+        self.leave_container();
+
+        Ok(())
+    }
+}
+
+/// The one place dictionary keys are checked, shared by every decode mode
+/// and `StreamDecoder`. `seen` is whether `key` already occurred in the
+/// dictionary, `previous` is the key before it.
+pub(crate) fn check_dictionary_key(
+    key: &[u8],
+    previous: Option<&[u8]>,
+    seen: bool,
+    strict_key_order: bool,
+    reject_duplicates: bool,
+) -> Result<(), DecodeErrorKind> {
+    if seen && reject_duplicates {
+        return Err(DecodeErrorKind::DuplicateKey(key.to_vec()));
+    }
+    // Keys must be strictly increasing
+    if previous.is_some_and(|previous| key < previous) && strict_key_order {
+        return Err(DecodeErrorKind::UnorderedKeys);
+    }
+    Ok(())
+}
+
+pub(crate) fn parse_integer(digits: &[u8]) -> Result<i64, DecodeErrorKind> {
+    let integer_str = core::str::from_utf8(digits)?;
+
+    // empty, sign-only and explicitly positive integers
+    if integer_str.is_empty() || integer_str == "-" || integer_str.starts_with('+') {
+        return Err(DecodeErrorKind::InvalidInteger);
+    }
+
+    // leading zeros
+    if integer_str.len() > 1 && integer_str.starts_with('0') {
+        return Err(DecodeErrorKind::InvalidInteger);
+    }
+
+    // negative zero
+    if integer_str == "-0" {
+        return Err(DecodeErrorKind::InvalidInteger);
+    }
+
+    Ok(integer_str.parse()?)
+}
+
+pub(crate) fn parse_length(digits: &[u8]) -> Result<usize, DecodeErrorKind> {
+    let len: u64 = core::str::from_utf8(digits)?.parse()?;
+    checked_length(len, usize::MAX as u64)
+}
+
+// Split out so the 32-bit limit can be exercised on any host
+fn checked_length(len: u64, max: u64) -> Result<usize, DecodeErrorKind> {
+    if len > max {
+        return Err(DecodeErrorKind::IntegerOverflow);
     }
+    usize::try_from(len).map_err(|_| DecodeErrorKind::IntegerOverflow)
 }
 
 pub fn decode<T: AsRef<[u8]>>(input: T) -> Result<BencodeType, DecodeError> {
+    let input = input.as_ref();
+    let mut decoder = Decoder::new(input);
+    let value = decoder.decode()?;
+
+    // Exactly one top-level value is allowed, use `Decoder` to parse a prefix
+    if decoder.position() != input.len() {
+        return Err(decoder.error(DecodeErrorKind::TrailingData));
+    }
+
+    Ok(value)
+}
+
+/// Decodes a buffer that isn't needed afterwards, e.g. straight from `fs::read`.
+pub fn decode_owned(input: Vec<u8>) -> Result<BencodeType, DecodeError> {
+    decode(&input)
+}
+
+/// Whether `input` is exactly the encoding of the value it decodes to, i.e.
+/// `encode(decode(input)) == input`. Unsorted or duplicate keys count as not
+/// canonical rather than as errors.
+pub fn is_canonical(input: &[u8]) -> Result<bool, DecodeError> {
+    match decode(input) {
+        Ok(value) => Ok(value.encode() == input),
+        Err(e)
+            if matches!(
+                e.kind(),
+                DecodeErrorKind::UnorderedKeys | DecodeErrorKind::DuplicateKey(_)
+            ) =>
+        {
+            Ok(false)
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// Entry point for fuzzing. Returns the re-encoded value only when `input` is
+/// canonical, so for any `Some(out)` the invariant `out == input` holds. No
+/// input makes this panic.
+pub fn decode_then_encode(input: &[u8]) -> Option<Vec<u8>> {
+    let encoded = decode(input).ok()?.encode();
+    (encoded == input).then_some(encoded)
+}
+
+/// Decodes `input` like `decode`, with the checks chosen by `options`.
+pub fn decode_with_options(
+    input: &[u8],
+    options: &DecodeOptions,
+) -> Result<BencodeType, DecodeError> {
+    let mut decoder = Decoder::with_options(input, options);
+    let value = decoder.decode()?;
+
+    if options.reject_trailing_data && decoder.position() != input.len() {
+        return Err(decoder.error(DecodeErrorKind::TrailingData));
+    }
+
+    Ok(value)
+}
+
+/// Decodes `input` like `decode`, borrowing bytestrings from it instead of copying them.
+///
+/// Any byte slice works, e.g. a memory-mapped file or a `static`, and the
+/// result lives as long as the slice does.
+pub fn decode_ref(input: &[u8]) -> Result<BencodeRef<'_>, DecodeError> {
+    let mut decoder = Decoder::new(input);
+    let value = decoder.decode_ref()?;
+
+    if decoder.position() != input.len() {
+        return Err(decoder.error(DecodeErrorKind::TrailingData));
+    }
+
+    Ok(value)
+}
+
+/// Decodes `input` like `decode`, leaving bytestrings in place as ranges into
+/// it. Use `BencodeLazy::materialize` to copy them out later.
+pub fn decode_lazy(input: &[u8]) -> Result<BencodeLazy, DecodeError> {
+    let mut decoder = Decoder::new(input);
+    let value = decoder.decode_lazy()?;
+
+    if decoder.position() != input.len() {
+        return Err(decoder.error(DecodeErrorKind::TrailingData));
+    }
+
+    Ok(value)
+}
+
+/// Decodes `input` like `decode`, sharing dictionary keys through
+/// `interner` so that decoding many similar values allocates each key once.
+pub fn decode_interned(
+    input: &[u8],
+    interner: &mut KeyInterner,
+) -> Result<BencodeInterned, DecodeError> {
+    let mut decoder = Decoder::new(input);
+    let value = decoder.decode_interned(interner)?;
+
+    if decoder.position() != input.len() {
+        return Err(decoder.error(DecodeErrorKind::TrailingData));
+    }
+
+    Ok(value)
+}
+
+/// Decodes `input` like `decode`, also recording where each dictionary value sits
+/// within it. See `SpanMap` for which values are recorded.
+pub fn decode_with_spans<T: AsRef<[u8]>>(input: T) -> Result<(BencodeType, SpanMap), DecodeError> {
+    let input = input.as_ref();
+    let mut decoder = Decoder::new(input);
+    decoder.spans = Some(SpanRecorder::default());
+    let value = decoder.decode()?;
+
+    if decoder.position() != input.len() {
+        return Err(decoder.error(DecodeErrorKind::TrailingData));
+    }
+
+    let spans = decoder.spans.take().unwrap_or_default();
+    Ok((value, spans.finish(0..input.len())))
+}
+
+/// Decodes the first value in `input`, returning it with the number of bytes it occupied.
+pub fn decode_prefix<T: AsRef<[u8]>>(input: T) -> Result<(BencodeType, usize), DecodeError> {
     let mut decoder = Decoder::new(input.as_ref());
-    decoder.decode()
+    let value = decoder.decode()?;
+
+    Ok((value, decoder.position()))
+}
+
+/// Decodes every value in a concatenation of top-level values, e.g. `i1e4:spam`.
+pub fn decode_many<T: AsRef<[u8]>>(input: T) -> Result<Vec<BencodeType>, DecodeError> {
+    let input = input.as_ref();
+    let mut decoder = Decoder::new(input);
+    let mut values = Vec::new();
+
+    while decoder.position() != input.len() {
+        values.push(decoder.decode()?);
+    }
+
+    Ok(values)
 }
 
 #[cfg(test)]
@@ -196,6 +879,54 @@ mod tests {
         assert_eq!(result, BencodeType::Integer(0));
     }
 
+    #[test]
+    fn integer_decode_empty() {
+        let input = b"ie";
+        let mut decoder = Decoder::new(input);
+        let err = decoder.decode().unwrap_err();
+        assert!(matches!(err.kind(), DecodeErrorKind::InvalidInteger));
+    }
+
+    #[test]
+    fn integer_decode_lone_minus() {
+        let input = b"i-e";
+        let mut decoder = Decoder::new(input);
+        let err = decoder.decode().unwrap_err();
+        assert!(matches!(err.kind(), DecodeErrorKind::InvalidInteger));
+    }
+
+    #[test]
+    fn integer_decode_leading_plus() {
+        let input = b"i+5e";
+        let mut decoder = Decoder::new(input);
+        let err = decoder.decode().unwrap_err();
+        assert!(matches!(err.kind(), DecodeErrorKind::InvalidInteger));
+    }
+
+    #[test]
+    fn integer_decode_overflow() {
+        let input = b"i99999999999999999999e";
+        let mut decoder = Decoder::new(input);
+        let err = decoder.decode().unwrap_err();
+        assert!(matches!(err.kind(), DecodeErrorKind::IntegerOverflow));
+    }
+
+    #[test]
+    fn integer_decode_negative_overflow() {
+        let input = b"i-99999999999999999999e";
+        let mut decoder = Decoder::new(input);
+        let err = decoder.decode().unwrap_err();
+        assert!(matches!(err.kind(), DecodeErrorKind::IntegerOverflow));
+    }
+
+    #[test]
+    fn integer_decode_malformed() {
+        let input = b"i1.5e";
+        let mut decoder = Decoder::new(input);
+        let err = decoder.decode().unwrap_err();
+        assert!(matches!(err.kind(), DecodeErrorKind::InvalidInteger));
+    }
+
     #[test]
     fn bytestring_decode_spam() {
         let input = vec![b'4', b':', b's', b'p', b'a', b'm'];
@@ -212,6 +943,15 @@ mod tests {
         assert_eq!(result, BencodeType::ByteString(b"".to_vec()));
     }
 
+    #[test]
+    fn bytestring_decode_truncated() {
+        let input = b"10:short";
+        let mut decoder = Decoder::new(input);
+        let err = decoder.decode().unwrap_err();
+        assert!(matches!(err.kind(), DecodeErrorKind::UnexpectedEndOfInput));
+        assert_eq!(err.offset(), input.len());
+    }
+
     #[test]
     fn list_decode() {
         let input = vec![b'l', b'i', b'3', b'e', b'i', b'4', b'e', b'e'];
@@ -334,4 +1074,684 @@ mod tests {
 
         assert_eq!(result, BencodeType::Dictionary(expected_dict));
     }
+
+    #[test]
+    fn decode_empty_input() {
+        let err = decode(b"").unwrap_err();
+        assert!(matches!(err.kind(), DecodeErrorKind::UnexpectedEndOfInput));
+        assert_eq!(err.offset(), 0);
+    }
+
+    #[test]
+    fn list_decode_unterminated() {
+        let err = decode(b"l").unwrap_err();
+        assert!(matches!(err.kind(), DecodeErrorKind::UnexpectedEndOfInput));
+    }
+
+    #[test]
+    fn dictionary_decode_unterminated() {
+        let err = decode(b"d3:foo").unwrap_err();
+        assert!(matches!(err.kind(), DecodeErrorKind::UnexpectedEndOfInput));
+    }
+
+    #[test]
+    fn integer_decode_unterminated() {
+        let err = decode(b"i").unwrap_err();
+        assert!(matches!(err.kind(), DecodeErrorKind::UnexpectedEndOfInput));
+    }
+
+    #[test]
+    fn dictionary_decode_duplicate_key() {
+        let input = b"d1:ai1e1:ai2ee";
+        let mut decoder = Decoder::new(input);
+        let err = decoder.decode().unwrap_err();
+        assert!(matches!(err.kind(), DecodeErrorKind::DuplicateKey(k) if k == b"a"));
+        assert_eq!(err.offset(), 7);
+    }
+
+    #[test]
+    fn dictionary_decode_unordered_keys() {
+        let input = b"d1:bi1e1:ai2ee";
+        let mut decoder = Decoder::new(input);
+        let err = decoder.decode().unwrap_err();
+        assert!(matches!(err.kind(), DecodeErrorKind::UnorderedKeys));
+        assert_eq!(err.offset(), 7);
+    }
+
+    #[test]
+    fn dictionary_decode_ordered_keys() {
+        let input = b"d1:ai1e1:bi2ee";
+        let mut decoder = Decoder::new(input);
+        let result = decoder.decode().unwrap();
+
+        let mut expected_dict = BTreeMap::new();
+        expected_dict.insert(b"a".to_vec(), BencodeType::Integer(1));
+        expected_dict.insert(b"b".to_vec(), BencodeType::Integer(2));
+
+        assert_eq!(result, BencodeType::Dictionary(expected_dict));
+    }
+
+    #[test]
+    fn decode_error_display() {
+        assert_eq!(
+            decode(b"d1:ai1e1:ai2ee").unwrap_err().to_string(),
+            "invalid bencode at offset 7: duplicate key \"a\""
+        );
+        assert_eq!(
+            decode(b"x").unwrap_err().to_string(),
+            "invalid bencode at offset 0: unexpected character 'x'"
+        );
+    }
+
+    #[test]
+    fn byte_budget_exceeded() {
+        let input = b"l4:spam4:eggs4:hame";
+        let mut decoder = Decoder::with_byte_budget(input, 10);
+        let err = decoder.decode().unwrap_err();
+        assert!(matches!(err.kind(), DecodeErrorKind::BudgetExceeded));
+        assert_eq!(err.offset(), 13);
+    }
+
+    #[test]
+    fn byte_budget_within_limit() {
+        let input = b"l4:spam4:eggse";
+        let mut decoder = Decoder::with_byte_budget(input, 8);
+        assert_eq!(
+            decoder.decode().unwrap(),
+            BencodeType::List(vec![
+                BencodeType::ByteString(b"spam".to_vec()),
+                BencodeType::ByteString(b"eggs".to_vec()),
+            ])
+        );
+    }
+
+    #[test]
+    fn byte_budget_counts_keys() {
+        let input = b"d4:spam4:eggse";
+        let mut decoder = Decoder::with_byte_budget(input, 7);
+        let err = decoder.decode().unwrap_err();
+        assert!(matches!(err.kind(), DecodeErrorKind::BudgetExceeded));
+        assert_eq!(err.offset(), 7);
+    }
+
+    #[test]
+    fn byte_budget_counts_lazy_keys() {
+        let mut input = b"d3:ham100:".to_vec();
+        input.extend_from_slice(&[b'x'; 100]);
+        input.extend_from_slice(b"4:spam4:eggse");
+
+        // The value stays in the input, so only the keys are charged
+        let mut decoder = Decoder::with_byte_budget(&input, 7);
+        assert!(decoder.decode_lazy().is_ok());
+
+        let mut decoder = Decoder::with_byte_budget(&input, 6);
+        let err = decoder.decode_lazy().unwrap_err();
+        assert!(matches!(err.kind(), DecodeErrorKind::BudgetExceeded));
+        assert_eq!(err.offset(), 110);
+
+        let options = DecodeOptions {
+            byte_budget: Some(2),
+            ..DecodeOptions::strict()
+        };
+        let err = Decoder::with_options(&input, &options)
+            .decode_lazy()
+            .unwrap_err();
+        assert!(matches!(err.kind(), DecodeErrorKind::BudgetExceeded));
+        assert_eq!(err.offset(), 1);
+    }
+
+    #[test]
+    fn byte_budget_counts_interned_values_and_new_keys() {
+        let input = b"d4:spam4:eggse";
+        let mut interner = KeyInterner::new();
+        let err = Decoder::with_byte_budget(input, 7)
+            .decode_interned(&mut interner)
+            .unwrap_err();
+        assert!(matches!(err.kind(), DecodeErrorKind::BudgetExceeded));
+        assert_eq!(err.offset(), 7);
+
+        // Once interned, a key is shared rather than copied again
+        interner.intern(b"spam");
+        assert!(Decoder::with_byte_budget(input, 4)
+            .decode_interned(&mut interner)
+            .is_ok());
+        let err = Decoder::with_byte_budget(input, 3)
+            .decode_interned(&mut interner)
+            .unwrap_err();
+        assert!(matches!(err.kind(), DecodeErrorKind::BudgetExceeded));
+        assert_eq!(err.offset(), 7);
+    }
+
+    #[test]
+    fn list_decode_max_depth_exceeded() {
+        let mut input = vec![b'l'; 10_000];
+        input.extend(vec![b'e'; 10_000]);
+        let mut decoder = Decoder::new(&input);
+        let err = decoder.decode().unwrap_err();
+        assert!(matches!(err.kind(), DecodeErrorKind::MaxDepthExceeded));
+        assert_eq!(err.offset(), DEFAULT_MAX_DEPTH);
+    }
+
+    #[test]
+    fn list_decode_at_max_depth() {
+        let input = b"llleee";
+        let mut decoder = Decoder::with_max_depth(input, 3);
+        assert!(decoder.decode().is_ok());
+
+        let mut decoder = Decoder::with_max_depth(input, 2);
+        let err = decoder.decode().unwrap_err();
+        assert!(matches!(err.kind(), DecodeErrorKind::MaxDepthExceeded));
+    }
+
+    #[test]
+    fn dictionary_decode_max_depth_exceeded() {
+        let input = b"d1:ad1:ad1:aleeee";
+        let mut decoder = Decoder::with_max_depth(input, 3);
+        let err = decoder.decode().unwrap_err();
+        assert!(matches!(err.kind(), DecodeErrorKind::MaxDepthExceeded));
+        assert_eq!(err.offset(), 12);
+    }
+
+    #[test]
+    fn validate_large_nested() {
+        let mut input = b"d4:infod6:lengthi1048576e6:pieces".to_vec();
+        input.extend(b"5000000:");
+        input.extend(vec![0xab; 5_000_000]);
+        input.extend(b"e4:listl");
+        for _ in 0..10_000 {
+            input.extend(b"li1e3:fooe");
+        }
+        input.extend(b"ee");
+
+        let mut decoder = Decoder::new(&input);
+        decoder.validate().unwrap();
+        assert_eq!(decoder.position(), input.len());
+    }
+
+    #[test]
+    fn validate_rejects_malformed() {
+        let inputs: [&[u8]; 6] = [
+            b"",
+            b"l",
+            b"10:short",
+            b"i03e",
+            b"d1:bi1e1:ai2ee",
+            b"d1:ai1e1:ai2ee",
+        ];
+
+        for input in inputs {
+            let validated = Decoder::new(input).validate().unwrap_err();
+            let decoded = Decoder::new(input).decode().unwrap_err();
+            assert_eq!(
+                std::mem::discriminant(validated.kind()),
+                std::mem::discriminant(decoded.kind())
+            );
+            assert_eq!(validated.offset(), decoded.offset());
+        }
+    }
+
+    #[test]
+    fn validate_max_depth_exceeded() {
+        let input = vec![b'l'; 10_000];
+        let err = Decoder::new(&input).validate().unwrap_err();
+        assert!(matches!(err.kind(), DecodeErrorKind::MaxDepthExceeded));
+    }
+
+    #[test]
+    fn decode_with_spans_info() {
+        let info =
+            b"d6:lengthi12e4:name8:file.txt12:piece lengthi16384e6:pieces20:aaaaaaaaaaaaaaaaaaaae";
+        let mut input = b"d8:announce15:http://tracker/4:info".to_vec();
+        input.extend(info);
+        input.extend(b"e");
+
+        let (value, spans) = decode_with_spans(&input).unwrap();
+
+        let span = spans.get(&[b"info"]).unwrap();
+        assert_eq!(&input[span.clone()], info);
+        assert_eq!(spans.get(&[]), Some(0..input.len()));
+        assert_eq!(
+            spans.get(&[b"info", b"name"]),
+            Some(span.start + 19..span.start + 29)
+        );
+        assert_eq!(decode(&input[span]).unwrap(), decode(info).unwrap());
+        assert_eq!(value, decode(&input).unwrap());
+    }
+
+    #[test]
+    fn decode_with_spans_skips_lists() {
+        let input = b"d5:filesld6:lengthi1eeee";
+        let (_, spans) = decode_with_spans(input).unwrap();
+        assert_eq!(spans.get(&[b"files"]), Some(8..23));
+        assert_eq!(spans.get(&[b"length"]), None);
+        assert_eq!(spans.iter().count(), 2);
+    }
+
+    #[test]
+    fn decode_ref_borrows_input() {
+        let mut input = b"d6:pieces1000:".to_vec();
+        input.extend(vec![0xab; 1000]);
+        input.extend(b"4:spaml1:ai3eee");
+
+        let value = decode_ref(&input).unwrap();
+        let BencodeRef::Dictionary(dict) = &value else {
+            panic!("expected a dictionary");
+        };
+        let Some(BencodeRef::ByteString(pieces)) = dict.get(b"pieces".as_slice()) else {
+            panic!("expected pieces bytestring");
+        };
+
+        // Points into the input rather than at a fresh allocation
+        assert!(std::ptr::eq(pieces.as_ptr(), input[14..].as_ptr()));
+        assert_eq!(value.to_owned(), decode(&input).unwrap());
+    }
+
+    #[test]
+    fn decode_options_key_order() {
+        let input = b"d1:bi1e1:ai2ee";
+        let strict = DecodeOptions::strict();
+        let err = decode_with_options(input, &strict).unwrap_err();
+        assert!(matches!(err.kind(), DecodeErrorKind::UnorderedKeys));
+
+        let options = DecodeOptions {
+            strict_key_order: false,
+            ..strict
+        };
+        let value = decode_with_options(input, &options).unwrap();
+        assert_eq!(
+            value,
+            BencodeType::dict().insert("a", 2).insert("b", 1).build()
+        );
+    }
+
+    #[test]
+    fn decode_options_duplicates() {
+        let input = b"d1:ai1e1:ai2ee";
+        let strict = DecodeOptions::strict();
+        let err = decode_with_options(input, &strict).unwrap_err();
+        assert!(matches!(err.kind(), DecodeErrorKind::DuplicateKey(k) if k == b"a"));
+
+        let options = DecodeOptions {
+            reject_duplicates: false,
+            ..strict
+        };
+        let value = decode_with_options(input, &options).unwrap();
+        assert_eq!(value, BencodeType::dict().insert("a", 2).build());
+
+        // Unsorted keys still trip the order check further down
+        let err = decode_with_options(b"d1:ai1e1:bi2e1:ai3ee", &options).unwrap_err();
+        assert!(matches!(err.kind(), DecodeErrorKind::UnorderedKeys));
+    }
+
+    #[test]
+    fn decode_options_trailing_data() {
+        let strict = DecodeOptions::strict();
+        let err = decode_with_options(b"i1ei2e", &strict).unwrap_err();
+        assert!(matches!(err.kind(), DecodeErrorKind::TrailingData));
+
+        let options = DecodeOptions {
+            reject_trailing_data: false,
+            ..strict
+        };
+        assert_eq!(
+            decode_with_options(b"i1ei2e", &options).unwrap(),
+            BencodeType::Integer(1)
+        );
+    }
+
+    #[test]
+    fn decode_options_limits() {
+        let shallow = DecodeOptions {
+            max_depth: 1,
+            ..DecodeOptions::strict()
+        };
+        let err = decode_with_options(b"llee", &shallow).unwrap_err();
+        assert!(matches!(err.kind(), DecodeErrorKind::MaxDepthExceeded));
+        let deep = DecodeOptions {
+            max_depth: 2,
+            ..shallow
+        };
+        assert!(decode_with_options(b"llee", &deep).is_ok());
+
+        let budget = DecodeOptions {
+            byte_budget: Some(3),
+            ..DecodeOptions::strict()
+        };
+        let err = decode_with_options(b"4:spam", &budget).unwrap_err();
+        assert!(matches!(err.kind(), DecodeErrorKind::BudgetExceeded));
+        assert!(decode_with_options(b"4:spam", &DecodeOptions::strict()).is_ok());
+    }
+
+    #[test]
+    fn decode_options_presets() {
+        assert_eq!(DecodeOptions::default(), DecodeOptions::strict());
+
+        let input = b"d1:bi1e1:ai2e1:ai3eei4e";
+        assert!(decode_with_options(input, &DecodeOptions::strict()).is_err());
+        assert_eq!(
+            decode_with_options(input, &DecodeOptions::lenient()).unwrap(),
+            BencodeType::dict().insert("a", 3).insert("b", 1).build()
+        );
+    }
+
+    #[test]
+    fn decode_ref_over_static_slice() {
+        static TORRENT: &[u8] = b"d8:announce3:url4:infod4:name3:fooee";
+
+        fn parse() -> BencodeRef<'static> {
+            decode_ref(TORRENT).unwrap()
+        }
+
+        // The borrowed value outlives the function that parsed it
+        let value = parse();
+        let BencodeRef::Dictionary(dict) = &value else {
+            panic!("expected a dictionary");
+        };
+        let Some(BencodeRef::ByteString(announce)) = dict.get(b"announce".as_slice()) else {
+            panic!("expected announce bytestring");
+        };
+        assert_eq!(*announce, b"url");
+        assert!(std::ptr::eq(announce.as_ptr(), TORRENT[13..].as_ptr()));
+    }
+
+    #[test]
+    fn decode_ref_errors() {
+        let err = decode_ref(b"d1:bi1e1:ai2ee").unwrap_err();
+        assert!(matches!(err.kind(), DecodeErrorKind::UnorderedKeys));
+
+        let err = decode_ref(b"i1ei2e").unwrap_err();
+        assert!(matches!(err.kind(), DecodeErrorKind::TrailingData));
+
+        let err = decode_ref(b"10:short").unwrap_err();
+        assert!(matches!(err.kind(), DecodeErrorKind::UnexpectedEndOfInput));
+    }
+
+    #[test]
+    fn decode_trailing_data() {
+        let err = decode(b"i3ei4e").unwrap_err();
+        assert!(matches!(err.kind(), DecodeErrorKind::TrailingData));
+        assert_eq!(err.offset(), 3);
+    }
+
+    #[test]
+    fn decoder_decode_prefix() {
+        let input = b"i3ei4e";
+        let mut decoder = Decoder::new(input);
+        let result = decoder.decode().unwrap();
+        assert_eq!(result, BencodeType::Integer(3));
+    }
+
+    #[test]
+    fn decode_many_values() {
+        let values = decode_many(b"i1e4:spamle").unwrap();
+        assert_eq!(
+            values,
+            vec![
+                BencodeType::Integer(1),
+                BencodeType::ByteString(b"spam".to_vec()),
+                BencodeType::List(vec![]),
+            ]
+        );
+    }
+
+    #[test]
+    fn decode_many_truncated() {
+        let err = decode_many(b"i1e4:spamli2e").unwrap_err();
+        assert!(matches!(err.kind(), DecodeErrorKind::UnexpectedEndOfInput));
+    }
+
+    #[test]
+    fn decode_many_empty() {
+        assert!(decode_many(b"").unwrap().is_empty());
+    }
+
+    #[test]
+    fn decode_lazy_leaves_large_field_in_place() {
+        let pieces = vec![0xab; 100_000];
+        let input = BencodeType::dict()
+            .insert("name", "big")
+            .insert("pieces", pieces.clone())
+            .build()
+            .encode();
+
+        let value = decode_lazy(&input).unwrap();
+        let range = value.get("pieces").unwrap().as_range().unwrap().clone();
+        assert_eq!(range.len(), pieces.len());
+        assert_eq!(range.end, input.len() - 1);
+        assert_eq!(value.get("name").unwrap().bytes(&input), Some(&b"big"[..]));
+
+        assert_eq!(value.materialize(&input), decode(&input).unwrap());
+    }
+
+    #[test]
+    fn decode_lazy_nested() {
+        let input = b"l4:spamli1e3:fooee";
+        let value = decode_lazy(input).unwrap();
+        assert_eq!(
+            value,
+            BencodeLazy::List(vec![
+                BencodeLazy::ByteString(3..7),
+                BencodeLazy::List(vec![
+                    BencodeLazy::Integer(1),
+                    BencodeLazy::ByteString(13..16)
+                ]),
+            ])
+        );
+    }
+
+    #[test]
+    fn decode_lazy_errors() {
+        assert!(matches!(
+            decode_lazy(b"d1:bi1e1:ai2ee").unwrap_err().kind(),
+            DecodeErrorKind::UnorderedKeys
+        ));
+        assert!(matches!(
+            decode_lazy(b"i1ei2e").unwrap_err().kind(),
+            DecodeErrorKind::TrailingData
+        ));
+    }
+
+    #[test]
+    fn decoder_remaining() {
+        let mut decoder = Decoder::new(b"i1ei2e");
+        assert_eq!(decoder.decode().unwrap(), BencodeType::Integer(1));
+        assert_eq!(decoder.remaining(), b"i2e");
+
+        assert_eq!(decoder.decode().unwrap(), BencodeType::Integer(2));
+        assert!(decoder.remaining().is_empty());
+    }
+
+    #[test]
+    fn decode_prefix_consecutive() {
+        let input = b"i1ei2e";
+
+        let (first, consumed) = decode_prefix(input).unwrap();
+        assert_eq!(first, BencodeType::Integer(1));
+        assert_eq!(consumed, 3);
+
+        let (second, consumed) = decode_prefix(&input[consumed..]).unwrap();
+        assert_eq!(second, BencodeType::Integer(2));
+        assert_eq!(consumed, 3);
+    }
+
+    #[test]
+    fn decoder_position() {
+        let input = b"4:spami1e";
+        let mut decoder = Decoder::new(input);
+        assert_eq!(decoder.position(), 0);
+        decoder.decode().unwrap();
+        assert_eq!(decoder.position(), 6);
+    }
+
+    #[test]
+    fn error_offset_unexpected_character() {
+        let err = decode(b"li1exe").unwrap_err();
+        assert!(matches!(
+            err.kind(),
+            DecodeErrorKind::UnexpectedCharacter(b'x')
+        ));
+        assert_eq!(err.offset(), 4);
+    }
+
+    #[test]
+    fn error_offset_unexpected_end_of_input() {
+        let err = decode(b"li1ei2").unwrap_err();
+        assert!(matches!(err.kind(), DecodeErrorKind::UnexpectedEndOfInput));
+        assert_eq!(err.offset(), 6);
+    }
+
+    #[test]
+    fn error_offset_invalid_utf8() {
+        let err = decode(b"li1ei\xffee").unwrap_err();
+        assert!(matches!(err.kind(), DecodeErrorKind::InvalidUtf8(_)));
+        assert_eq!(err.offset(), 5);
+    }
+
+    #[test]
+    fn error_offset_invalid_integer() {
+        let err = decode(b"l3:fooi03ee").unwrap_err();
+        assert!(matches!(err.kind(), DecodeErrorKind::InvalidInteger));
+        assert_eq!(err.offset(), 7);
+    }
+
+    #[test]
+    fn decode_owned_outlives_buffer() {
+        let value = {
+            let buffer = b"d4:spaml1:a1:bee".to_vec();
+            decode_owned(buffer).unwrap()
+        };
+        assert_eq!(value.get("spam").and_then(|v| v.len()), Some(2));
+
+        let err = decode_owned(b"i1ex".to_vec()).unwrap_err();
+        assert!(matches!(err.kind(), DecodeErrorKind::TrailingData));
+    }
+
+    #[test]
+    fn is_canonical_inputs() {
+        assert!(is_canonical(b"d3:bar4:spam3:fooi42ee").unwrap());
+        assert!(!is_canonical(b"d3:fooi42e3:bar4:spame").unwrap());
+        assert!(!is_canonical(b"d3:fooi1e3:fooi2ee").unwrap());
+        assert!(!is_canonical(b"04:spam").unwrap());
+
+        let err = is_canonical(b"i1").unwrap_err();
+        assert!(matches!(err.kind(), DecodeErrorKind::UnexpectedEndOfInput));
+    }
+
+    #[test]
+    fn length_over_usize_overflows() {
+        let max_32 = u32::MAX as u64;
+        assert!(matches!(checked_length(max_32, max_32), Ok(len) if len as u64 == max_32));
+        assert!(matches!(
+            checked_length(max_32 + 1, max_32),
+            Err(DecodeErrorKind::IntegerOverflow)
+        ));
+
+        let err = decode(b"18446744073709551616:a").unwrap_err();
+        assert!(matches!(err.kind(), DecodeErrorKind::IntegerOverflow));
+        assert_eq!(err.offset(), 0);
+    }
+
+    #[test]
+    fn length_end_overflows() {
+        // Fits in u64 but the end of the string doesn't fit in usize
+        let err = decode(b"18446744073709551615:a").unwrap_err();
+        assert!(matches!(err.kind(), DecodeErrorKind::IntegerOverflow));
+        assert_eq!(err.offset(), 0);
+    }
+
+    const TRICKY_INPUTS: &[&[u8]] = &[
+        b"",
+        b"i",
+        b"ie",
+        b"i-e",
+        b"i-0e",
+        b"i00e",
+        b"i9223372036854775808e",
+        b"i-9223372036854775809e",
+        b"l",
+        b"d",
+        b"le",
+        b"de",
+        b"e",
+        b":",
+        b"0:",
+        b"1:",
+        b"-1:a",
+        b"+1:a",
+        b"1a:a",
+        b"18446744073709551615:",
+        b"99999999999999999999999:",
+        b"d0:0:e",
+        b"d1:ae",
+        b"d1:ai1e1:ai2ee",
+        b"d1:bi1e1:ai2ee",
+        b"di1ei2ee",
+        b"lli1eee",
+        b"l4:spam",
+        b"i1ei2e",
+        b"\xff",
+    ];
+
+    #[test]
+    fn decode_then_encode_canonical_only() {
+        assert_eq!(
+            decode_then_encode(b"d1:ai1e1:bl0:ee").unwrap(),
+            b"d1:ai1e1:bl0:ee"
+        );
+        assert_eq!(decode_then_encode(b"04:spam"), None);
+        assert_eq!(decode_then_encode(b"d1:bi1e1:ai2ee"), None);
+        assert_eq!(decode_then_encode(b"i1"), None);
+    }
+
+    #[test]
+    fn tricky_inputs_do_not_panic() {
+        for &input in TRICKY_INPUTS {
+            if let Some(out) = decode_then_encode(input) {
+                assert_eq!(out, input);
+            }
+            let _ = decode_ref(input);
+            let _ = decode_prefix(input);
+            let _ = decode_with_spans(input);
+            let _ = Decoder::new(input).validate();
+        }
+    }
+
+    #[test]
+    fn generated_inputs_do_not_panic() {
+        // Short strings over the bencode alphabet, from a fixed xorshift sequence
+        const ALPHABET: &[u8] = b"ild e0123456789:-";
+        let mut state: u64 = 0x2545F4914F6CDD1D;
+        for _ in 0..20_000 {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+
+            let len = (state % 12) as usize;
+            let input: Vec<u8> = (0..len)
+                .map(|i| ALPHABET[((state >> (i * 5)) % ALPHABET.len() as u64) as usize])
+                .collect();
+
+            if let Some(out) = decode_then_encode(&input) {
+                assert_eq!(out, input);
+            }
+            let _ = decode_ref(&input);
+            let _ = Decoder::new(&input).validate();
+        }
+    }
+
+    #[test]
+    fn dictionary_non_string_key() {
+        let err = decode(b"di1ei2ee").unwrap_err();
+        assert!(matches!(err.kind(), DecodeErrorKind::NonStringKey));
+        assert_eq!(err.offset(), 1);
+
+        let err = decode(b"d1:ai1eli1eei2ee").unwrap_err();
+        assert!(matches!(err.kind(), DecodeErrorKind::NonStringKey));
+        assert_eq!(err.offset(), 7);
+
+        let err = decode_ref(b"di1ei2ee").unwrap_err();
+        assert!(matches!(err.kind(), DecodeErrorKind::NonStringKey));
+        let err = Decoder::new(b"di1ei2ee").validate().unwrap_err();
+        assert!(matches!(err.kind(), DecodeErrorKind::NonStringKey));
+    }
 }
@@ -1,72 +1,241 @@
 use crate::bencode::BencodeType;
-use std::collections::BTreeMap;
+use alloc::{collections::BTreeMap, string::String, vec::Vec};
+#[cfg(feature = "std")]
+use std::io::{self, Write};
 
 pub trait Encodable {
-    fn encode(&self) -> Vec<u8>;
-}
+    /// Appends the encoded form of `self` to `out`.
+    fn encode_into(&self, out: &mut Vec<u8>);
+
+    /// Exact number of bytes `encode` produces, computed without encoding.
+    fn encoded_len(&self) -> usize;
 
-impl Encodable for Vec<u8> {
     fn encode(&self) -> Vec<u8> {
-        let mut res = Vec::new();
-        res.extend(format!("{}:", self.len()).into_bytes());
-        res.extend(self);
+        let mut res = Vec::with_capacity(self.encoded_len());
+        self.encode_into(&mut res);
 
         res
     }
 }
 
+// Longest i64 is "-9223372036854775808"
+const MAX_INTEGER_LEN: usize = 20;
+
+// Renders the decimal form of a number without going through `format!`,
+// the digits are `buf[start..]`
+fn decimal(mut magnitude: u64, negative: bool) -> ([u8; MAX_INTEGER_LEN], usize) {
+    let mut digits = [0; MAX_INTEGER_LEN];
+    let mut start = digits.len();
+
+    loop {
+        start -= 1;
+        digits[start] = b'0' + (magnitude % 10) as u8;
+        magnitude /= 10;
+
+        if magnitude == 0 {
+            break;
+        }
+    }
+
+    if negative {
+        start -= 1;
+        digits[start] = b'-';
+    }
+
+    (digits, start)
+}
+
+fn decimal_len(mut magnitude: u64, negative: bool) -> usize {
+    let mut len = 1 + negative as usize;
+    while magnitude >= 10 {
+        magnitude /= 10;
+        len += 1;
+    }
+
+    len
+}
+
+fn push_decimal(out: &mut Vec<u8>, magnitude: u64, negative: bool) {
+    let (digits, start) = decimal(magnitude, negative);
+    out.extend_from_slice(&digits[start..]);
+}
+
+#[cfg(feature = "std")]
+fn write_decimal<W: Write>(w: &mut W, magnitude: u64, negative: bool) -> io::Result<()> {
+    let (digits, start) = decimal(magnitude, negative);
+    w.write_all(&digits[start..])
+}
+
+impl Encodable for [u8] {
+    fn encode_into(&self, out: &mut Vec<u8>) {
+        push_decimal(out, self.len() as u64, false);
+        out.push(b':');
+        out.extend_from_slice(self);
+    }
+
+    fn encoded_len(&self) -> usize {
+        decimal_len(self.len() as u64, false) + 1 + self.len()
+    }
+}
+
+impl Encodable for Vec<u8> {
+    fn encode_into(&self, out: &mut Vec<u8>) {
+        self.as_slice().encode_into(out);
+    }
+
+    fn encoded_len(&self) -> usize {
+        self.as_slice().encoded_len()
+    }
+}
+
+impl Encodable for str {
+    fn encode_into(&self, out: &mut Vec<u8>) {
+        self.as_bytes().encode_into(out);
+    }
+
+    fn encoded_len(&self) -> usize {
+        self.as_bytes().encoded_len()
+    }
+}
+
+impl Encodable for String {
+    fn encode_into(&self, out: &mut Vec<u8>) {
+        self.as_bytes().encode_into(out);
+    }
+
+    fn encoded_len(&self) -> usize {
+        self.as_bytes().encoded_len()
+    }
+}
+
+impl<T: Encodable + ?Sized> Encodable for &T {
+    fn encode_into(&self, out: &mut Vec<u8>) {
+        (**self).encode_into(out);
+    }
+
+    fn encoded_len(&self) -> usize {
+        (**self).encoded_len()
+    }
+}
+
 impl Encodable for i64 {
-    fn encode(&self) -> Vec<u8> {
-        format!("i{self}e").into_bytes()
+    fn encode_into(&self, out: &mut Vec<u8>) {
+        out.push(b'i');
+        push_decimal(out, self.unsigned_abs(), *self < 0);
+        out.push(b'e');
+    }
+
+    fn encoded_len(&self) -> usize {
+        decimal_len(self.unsigned_abs(), *self < 0) + 2
     }
 }
 
 impl Encodable for Vec<BencodeType> {
-    fn encode(&self) -> Vec<u8> {
-        let mut res = Vec::new();
-
-        res.push(b'l');
+    fn encode_into(&self, out: &mut Vec<u8>) {
+        out.push(b'l');
 
         for v in self {
-            res.extend(v.encode());
+            v.encode_into(out);
         }
 
-        res.push(b'e');
+        out.push(b'e');
+    }
 
-        res
+    fn encoded_len(&self) -> usize {
+        2 + self.iter().map(Encodable::encoded_len).sum::<usize>()
     }
 }
 
 impl Encodable for BTreeMap<Vec<u8>, BencodeType> {
-    fn encode(&self) -> Vec<u8> {
-        let mut res = Vec::new();
-
-        res.push(b'd');
+    fn encode_into(&self, out: &mut Vec<u8>) {
+        out.push(b'd');
 
         for (k, v) in self {
-            res.extend(k.encode());
-            res.extend(v.encode());
+            k.encode_into(out);
+            v.encode_into(out);
         }
 
-        res.push(b'e');
+        out.push(b'e');
+    }
 
-        res
+    fn encoded_len(&self) -> usize {
+        2 + self
+            .iter()
+            .map(|(k, v)| k.encoded_len() + v.encoded_len())
+            .sum::<usize>()
     }
 }
 
 impl Encodable for BencodeType {
-    fn encode(&self) -> Vec<u8> {
+    fn encode_into(&self, out: &mut Vec<u8>) {
+        match self {
+            Self::ByteString(s) => s.encode_into(out),
+            Self::Integer(i) => i.encode_into(out),
+            Self::List(v) => v.encode_into(out),
+            Self::Dictionary(d) => d.encode_into(out),
+        }
+    }
+
+    fn encoded_len(&self) -> usize {
         match self {
-            Self::ByteString(s) => s.encode(),
-            Self::Integer(i) => i.encode(),
-            Self::List(v) => v.encode(),
-            Self::Dictionary(d) => d.encode(),
+            Self::ByteString(s) => s.encoded_len(),
+            Self::Integer(i) => i.encoded_len(),
+            Self::List(v) => v.encoded_len(),
+            Self::Dictionary(d) => d.encoded_len(),
         }
     }
 }
 
-pub fn encode(input: impl Encodable) -> Vec<u8> {
-    input.encode()
+impl BencodeType {
+    /// Encodes only the value at `path` (see `path`), e.g. the `info`
+    /// dictionary for hashing. `None` if nothing is there.
+    pub fn encode_subtree(&self, path: &[&[u8]]) -> Option<Vec<u8>> {
+        self.path(path).map(Encodable::encode)
+    }
+}
+
+#[cfg(feature = "std")]
+impl BencodeType {
+    /// Writes the encoded form straight to `w` without building it in memory first.
+    pub fn encode_to_writer<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        match self {
+            Self::ByteString(s) => write_bytestring(w, s),
+            Self::Integer(i) => {
+                w.write_all(b"i")?;
+                write_decimal(w, i.unsigned_abs(), *i < 0)?;
+                w.write_all(b"e")
+            }
+            Self::List(v) => {
+                w.write_all(b"l")?;
+                for item in v {
+                    item.encode_to_writer(w)?;
+                }
+                w.write_all(b"e")
+            }
+            Self::Dictionary(d) => {
+                w.write_all(b"d")?;
+                for (k, v) in d {
+                    write_bytestring(w, k)?;
+                    v.encode_to_writer(w)?;
+                }
+                w.write_all(b"e")
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+fn write_bytestring<W: Write>(w: &mut W, bytes: &[u8]) -> io::Result<()> {
+    write_decimal(w, bytes.len() as u64, false)?;
+    w.write_all(b":")?;
+    w.write_all(bytes)
+}
+
+/// Encodes any `Encodable`: a `BencodeType`, `i64`, bytes (`Vec<u8>`, `&[u8]`),
+/// text (`String`, `&str`), a `Vec<BencodeType>` list or a
+/// `BTreeMap<Vec<u8>, BencodeType>` dictionary, or a reference to any of these.
+pub fn encode<T: Encodable>(value: T) -> Vec<u8> {
+    value.encode()
 }
 
 #[cfg(test)]
@@ -74,6 +243,26 @@ mod tests {
     use super::*;
     use std::collections::BTreeMap;
 
+    #[test]
+    fn encode_accepts_every_impl() {
+        let list = vec![BencodeType::Integer(1)];
+        let mut dict = BTreeMap::new();
+        dict.insert(b"a".to_vec(), BencodeType::Integer(1));
+
+        let value = BencodeType::Integer(1);
+        assert_eq!(encode(&value), b"i1e");
+        assert_eq!(encode(value), b"i1e");
+        assert_eq!(encode(1i64), b"i1e");
+        assert_eq!(encode(b"ab".to_vec()), b"2:ab");
+        assert_eq!(encode(b"ab".as_slice()), b"2:ab");
+        assert_eq!(encode("ab"), b"2:ab");
+        assert_eq!(encode(String::from("ab")), b"2:ab");
+        assert_eq!(encode(&list), b"li1ee");
+        assert_eq!(encode(list), b"li1ee");
+        assert_eq!(encode(&dict), b"d1:ai1ee");
+        assert_eq!(encode(dict), b"d1:ai1ee");
+    }
+
     #[test]
     fn bytestring_encode() {
         let input = BencodeType::ByteString(b"spam".to_vec());
@@ -109,6 +298,129 @@ mod tests {
         assert_eq!(result, b"i0e".to_vec());
     }
 
+    #[test]
+    fn integer_extremes() {
+        assert_eq!(i64::MAX.encode(), b"i9223372036854775807e".to_vec());
+        assert_eq!(i64::MIN.encode(), b"i-9223372036854775808e".to_vec());
+    }
+
+    #[test]
+    fn integer_matches_format() {
+        for i in (-10_000..10_000).chain([1 << 40, -(1 << 40), 10, -10, 100]) {
+            assert_eq!(i.encode(), format!("i{i}e").into_bytes());
+        }
+    }
+
+    #[test]
+    fn encode_subtree_of_built_torrent() {
+        let info = BencodeType::dict()
+            .insert("length", 12)
+            .insert("name", "file.txt")
+            .build();
+        let torrent = BencodeType::dict()
+            .insert("announce", "http://tracker")
+            .insert("info", info.clone())
+            .build();
+
+        assert_eq!(torrent.encode_subtree(&[b"info"]), Some(encode(&info)));
+        assert_eq!(
+            torrent.encode_subtree(&[b"info", b"length"]),
+            Some(b"i12e".to_vec())
+        );
+        assert_eq!(torrent.encode_subtree(&[]), Some(encode(&torrent)));
+        assert_eq!(torrent.encode_subtree(&[b"missing"]), None);
+    }
+
+    #[test]
+    fn encode_into_appends() {
+        let mut out = b"prefix".to_vec();
+        let input = BencodeType::List(vec![
+            BencodeType::Integer(-7),
+            BencodeType::ByteString(b"spam".to_vec()),
+        ]);
+        input.encode_into(&mut out);
+        BencodeType::Integer(1).encode_into(&mut out);
+        assert_eq!(out, b"prefixli-7e4:spamei1e".to_vec());
+    }
+
+    #[test]
+    fn bytestring_encode_long_length() {
+        let input = BencodeType::ByteString(vec![b'a'; 1234]);
+        let result = input.encode();
+        assert_eq!(&result[..5], b"1234:");
+        assert_eq!(result.len(), 1239);
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn encode_to_writer_matches_encode() {
+        let input = BencodeType::dict()
+            .insert("info", BencodeType::dict().insert("length", -12).build())
+            .insert(
+                "list",
+                BencodeType::from(vec![BencodeType::from("spam"), vec![0u8; 300].into()]),
+            )
+            .build();
+
+        let mut out = Vec::new();
+        input.encode_to_writer(&mut out).unwrap();
+        assert_eq!(out, input.encode());
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn encode_to_writer_error() {
+        let mut buf = [0u8; 4];
+        let mut w = buf.as_mut_slice();
+        let input = BencodeType::ByteString(b"spam".to_vec());
+        assert!(input.encode_to_writer(&mut w).is_err());
+    }
+
+    #[test]
+    fn encoded_len_matches_encode() {
+        let inputs = [
+            BencodeType::ByteString(vec![]),
+            BencodeType::ByteString(vec![b'a'; 10]),
+            BencodeType::ByteString(vec![b'a'; 12345]),
+            BencodeType::Integer(0),
+            BencodeType::Integer(9),
+            BencodeType::Integer(10),
+            BencodeType::Integer(-10),
+            BencodeType::Integer(i64::MAX),
+            BencodeType::Integer(i64::MIN),
+            BencodeType::List(vec![]),
+            BencodeType::Dictionary(BTreeMap::new()),
+            BencodeType::dict()
+                .insert("info", BencodeType::dict().insert("length", -12).build())
+                .insert(
+                    "list",
+                    BencodeType::from(vec![BencodeType::from("spam"), vec![0u8; 300].into()]),
+                )
+                .build(),
+        ];
+
+        for input in inputs {
+            let encoded = input.encode();
+            assert_eq!(input.encoded_len(), encoded.len());
+            assert_eq!(encoded.capacity(), encoded.len());
+        }
+    }
+
+    #[test]
+    fn str_encode() {
+        assert_eq!("spam".encode(), b"4:spam".to_vec());
+        assert_eq!(String::from("spam").encode(), b"4:spam".to_vec());
+        assert_eq!(encode("spam"), b"4:spam".to_vec());
+        assert_eq!("".encoded_len(), 2);
+    }
+
+    #[test]
+    fn slice_encode() {
+        let bytes: &[u8] = &[0xff, 0x00];
+        assert_eq!(bytes.encode(), b"2:\xff\x00".to_vec());
+        assert_eq!(encode(b"spam".as_slice()), b"4:spam".to_vec());
+    }
+
     #[test]
     fn list_encode() {
         let input = BencodeType::List(vec![
@@ -1,10 +1,50 @@
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+
+extern crate alloc;
+
 mod bencode;
+mod bencode_interned;
+mod bencode_lazy;
+mod bencode_ref;
+mod builder;
+#[cfg(feature = "test-alloc")]
+mod counting_alloc;
+#[cfg(feature = "serde")]
+mod de;
 mod decode;
 mod encode;
+#[cfg(feature = "serde")]
+mod ser;
+#[cfg(feature = "serde")]
+mod serde_error;
+mod span;
+#[cfg(feature = "std")]
+mod stream;
+#[cfg(feature = "serde")]
+mod value_serde;
 
-pub use bencode::BencodeType;
-pub use decode::{decode, DecodeError};
-pub use encode::encode;
+pub use bencode::{BencodeStats, BencodeType, PathSegment, StrError};
+pub use bencode_interned::{BencodeInterned, KeyInterner};
+pub use bencode_lazy::BencodeLazy;
+pub use bencode_ref::BencodeRef;
+pub use builder::DictBuilder;
+#[cfg(feature = "test-alloc")]
+pub use counting_alloc::{count_allocations, AllocStats, CountingAllocator};
+#[cfg(feature = "serde")]
+pub use de::from_bytes;
+pub use decode::{
+    decode, decode_interned, decode_lazy, decode_many, decode_owned, decode_prefix, decode_ref,
+    decode_then_encode, decode_with_options, decode_with_spans, is_canonical, DecodeError,
+    DecodeErrorKind, DecodeOptions, Decoder, DEFAULT_MAX_DEPTH,
+};
+pub use encode::{encode, Encodable};
+#[cfg(feature = "serde")]
+pub use ser::to_bytes;
+#[cfg(feature = "serde")]
+pub use serde_error::SerdeError;
+pub use span::SpanMap;
+#[cfg(feature = "std")]
+pub use stream::StreamDecoder;
 
 #[cfg(test)]
 mod tests {
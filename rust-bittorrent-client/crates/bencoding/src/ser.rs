@@ -0,0 +1,410 @@
+use crate::bencode::BencodeType;
+use crate::encode::encode;
+use crate::serde_error::SerdeError;
+use serde::ser::{self, Serialize};
+use std::collections::BTreeMap;
+
+/// Serializes `value` to bencode.
+///
+/// Structs and maps become dictionaries with sorted keys, sequences become lists,
+/// and `bool`s become `0` or `1`. Fields that are `None` are left out of their
+/// dictionary. Floats have no bencode form and are rejected.
+pub fn to_bytes<T: Serialize + ?Sized>(value: &T) -> Result<Vec<u8>, SerdeError> {
+    let value = value
+        .serialize(ValueSerializer)?
+        .ok_or(SerdeError::Unsupported("a top-level none"))?;
+
+    Ok(encode(value))
+}
+
+// `None` stands for a value that has no bencode form on its own, like `Option::None`
+struct ValueSerializer;
+
+type Value = Option<BencodeType>;
+
+fn wrap_variant(variant: &'static str, value: BencodeType) -> BencodeType {
+    BencodeType::Dictionary(BTreeMap::from([(variant.as_bytes().to_vec(), value)]))
+}
+
+impl ser::Serializer for ValueSerializer {
+    type Ok = Value;
+    type Error = SerdeError;
+
+    type SerializeSeq = ListSerializer;
+    type SerializeTuple = ListSerializer;
+    type SerializeTupleStruct = ListSerializer;
+    type SerializeTupleVariant = ListSerializer;
+    type SerializeMap = DictSerializer;
+    type SerializeStruct = DictSerializer;
+    type SerializeStructVariant = DictSerializer;
+
+    fn serialize_bool(self, v: bool) -> Result<Value, SerdeError> {
+        self.serialize_i64(v as i64)
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<Value, SerdeError> {
+        self.serialize_i64(v.into())
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<Value, SerdeError> {
+        self.serialize_i64(v.into())
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<Value, SerdeError> {
+        self.serialize_i64(v.into())
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<Value, SerdeError> {
+        Ok(Some(BencodeType::Integer(v)))
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<Value, SerdeError> {
+        self.serialize_i64(v.into())
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<Value, SerdeError> {
+        self.serialize_i64(v.into())
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<Value, SerdeError> {
+        self.serialize_i64(v.into())
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<Value, SerdeError> {
+        let v =
+            i64::try_from(v).map_err(|_| SerdeError::Unsupported("an integer above i64::MAX"))?;
+        self.serialize_i64(v)
+    }
+
+    fn serialize_f32(self, _: f32) -> Result<Value, SerdeError> {
+        Err(SerdeError::Unsupported("a float"))
+    }
+
+    fn serialize_f64(self, _: f64) -> Result<Value, SerdeError> {
+        Err(SerdeError::Unsupported("a float"))
+    }
+
+    fn serialize_char(self, v: char) -> Result<Value, SerdeError> {
+        self.serialize_str(v.encode_utf8(&mut [0; 4]))
+    }
+
+    fn serialize_str(self, v: &str) -> Result<Value, SerdeError> {
+        self.serialize_bytes(v.as_bytes())
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<Value, SerdeError> {
+        Ok(Some(BencodeType::ByteString(v.to_vec())))
+    }
+
+    fn serialize_none(self) -> Result<Value, SerdeError> {
+        Ok(None)
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<Value, SerdeError> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<Value, SerdeError> {
+        Ok(None)
+    }
+
+    fn serialize_unit_struct(self, _: &'static str) -> Result<Value, SerdeError> {
+        Ok(None)
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _: &'static str,
+        _: u32,
+        variant: &'static str,
+    ) -> Result<Value, SerdeError> {
+        self.serialize_str(variant)
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _: &'static str,
+        value: &T,
+    ) -> Result<Value, SerdeError> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _: &'static str,
+        _: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<Value, SerdeError> {
+        let value = value
+            .serialize(self)?
+            .ok_or(SerdeError::Unsupported("a none variant payload"))?;
+        Ok(Some(wrap_variant(variant, value)))
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<ListSerializer, SerdeError> {
+        Ok(ListSerializer {
+            items: Vec::with_capacity(len.unwrap_or(0)),
+            variant: None,
+        })
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<ListSerializer, SerdeError> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _: &'static str,
+        len: usize,
+    ) -> Result<ListSerializer, SerdeError> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _: &'static str,
+        _: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<ListSerializer, SerdeError> {
+        Ok(ListSerializer {
+            items: Vec::with_capacity(len),
+            variant: Some(variant),
+        })
+    }
+
+    fn serialize_map(self, _: Option<usize>) -> Result<DictSerializer, SerdeError> {
+        Ok(DictSerializer {
+            entries: BTreeMap::new(),
+            next_key: None,
+            variant: None,
+        })
+    }
+
+    fn serialize_struct(self, _: &'static str, _: usize) -> Result<DictSerializer, SerdeError> {
+        self.serialize_map(None)
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _: &'static str,
+        _: u32,
+        variant: &'static str,
+        _: usize,
+    ) -> Result<DictSerializer, SerdeError> {
+        Ok(DictSerializer {
+            entries: BTreeMap::new(),
+            next_key: None,
+            variant: Some(variant),
+        })
+    }
+}
+
+struct ListSerializer {
+    items: Vec<BencodeType>,
+    variant: Option<&'static str>,
+}
+
+impl ListSerializer {
+    fn push<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), SerdeError> {
+        let value = value
+            .serialize(ValueSerializer)?
+            .ok_or(SerdeError::Unsupported("a none list element"))?;
+        self.items.push(value);
+
+        Ok(())
+    }
+
+    fn finish(self) -> Result<Value, SerdeError> {
+        let list = BencodeType::List(self.items);
+        Ok(Some(match self.variant {
+            Some(variant) => wrap_variant(variant, list),
+            None => list,
+        }))
+    }
+}
+
+impl ser::SerializeSeq for ListSerializer {
+    type Ok = Value;
+    type Error = SerdeError;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), SerdeError> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Value, SerdeError> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeTuple for ListSerializer {
+    type Ok = Value;
+    type Error = SerdeError;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), SerdeError> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Value, SerdeError> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeTupleStruct for ListSerializer {
+    type Ok = Value;
+    type Error = SerdeError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), SerdeError> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Value, SerdeError> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeTupleVariant for ListSerializer {
+    type Ok = Value;
+    type Error = SerdeError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), SerdeError> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Value, SerdeError> {
+        self.finish()
+    }
+}
+
+struct DictSerializer {
+    entries: BTreeMap<Vec<u8>, BencodeType>,
+    next_key: Option<Vec<u8>>,
+    variant: Option<&'static str>,
+}
+
+impl DictSerializer {
+    fn insert<T: Serialize + ?Sized>(&mut self, key: Vec<u8>, value: &T) -> Result<(), SerdeError> {
+        if let Some(value) = value.serialize(ValueSerializer)? {
+            self.entries.insert(key, value);
+        }
+
+        Ok(())
+    }
+
+    fn finish(self) -> Result<Value, SerdeError> {
+        let dict = BencodeType::Dictionary(self.entries);
+        Ok(Some(match self.variant {
+            Some(variant) => wrap_variant(variant, dict),
+            None => dict,
+        }))
+    }
+}
+
+impl ser::SerializeMap for DictSerializer {
+    type Ok = Value;
+    type Error = SerdeError;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<(), SerdeError> {
+        let key = match key.serialize(ValueSerializer)? {
+            Some(BencodeType::ByteString(k)) => k,
+            Some(BencodeType::Integer(i)) => i.to_string().into_bytes(),
+            _ => return Err(SerdeError::KeyMustBeString),
+        };
+        self.next_key = Some(key);
+
+        Ok(())
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), SerdeError> {
+        let key = self
+            .next_key
+            .take()
+            .ok_or_else(|| SerdeError::Message("map value without a key".to_string()))?;
+        self.insert(key, value)
+    }
+
+    fn end(self) -> Result<Value, SerdeError> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeStruct for DictSerializer {
+    type Ok = Value;
+    type Error = SerdeError;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), SerdeError> {
+        self.insert(key.as_bytes().to_vec(), value)
+    }
+
+    fn end(self) -> Result<Value, SerdeError> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeStructVariant for DictSerializer {
+    type Ok = Value;
+    type Error = SerdeError;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), SerdeError> {
+        self.insert(key.as_bytes().to_vec(), value)
+    }
+
+    fn end(self) -> Result<Value, SerdeError> {
+        self.finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn to_bytes_primitives() {
+        assert_eq!(to_bytes(&42u8).unwrap(), b"i42e".to_vec());
+        assert_eq!(to_bytes(&true).unwrap(), b"i1e".to_vec());
+        assert_eq!(to_bytes("spam").unwrap(), b"4:spam".to_vec());
+        assert_eq!(to_bytes(&('a', -1)).unwrap(), b"l1:ai-1ee".to_vec());
+    }
+
+    #[test]
+    fn to_bytes_skips_none_fields() {
+        let map = BTreeMap::from([("b", Some(2)), ("a", None), ("c", Some(3))]);
+        assert_eq!(to_bytes(&map).unwrap(), b"d1:bi2e1:ci3ee".to_vec());
+    }
+
+    #[test]
+    fn to_bytes_integer_keys() {
+        let map = BTreeMap::from([(1, "one"), (2, "two")]);
+        assert_eq!(to_bytes(&map).unwrap(), b"d1:13:one1:23:twoe".to_vec());
+    }
+
+    #[test]
+    fn to_bytes_unsupported() {
+        assert!(matches!(to_bytes(&1.5f64), Err(SerdeError::Unsupported(_))));
+        assert!(matches!(
+            to_bytes(&u64::MAX),
+            Err(SerdeError::Unsupported(_))
+        ));
+        assert!(matches!(
+            to_bytes(&Option::<i64>::None),
+            Err(SerdeError::Unsupported(_))
+        ));
+        assert!(matches!(
+            to_bytes(&vec![Some(1), None]),
+            Err(SerdeError::Unsupported(_))
+        ));
+        assert!(matches!(
+            to_bytes(&BTreeMap::from([(vec![1], 1)])),
+            Err(SerdeError::KeyMustBeString)
+        ));
+    }
+}
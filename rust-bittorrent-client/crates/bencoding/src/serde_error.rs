@@ -0,0 +1,43 @@
+use crate::decode::DecodeError;
+use std::fmt;
+
+#[derive(Debug)]
+pub enum SerdeError {
+    Decode(DecodeError),
+    Unsupported(&'static str),
+    KeyMustBeString,
+    Message(String),
+}
+
+impl From<DecodeError> for SerdeError {
+    fn from(value: DecodeError) -> Self {
+        Self::Decode(value)
+    }
+}
+
+impl fmt::Display for SerdeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Decode(e) => write!(f, "{e}"),
+            Self::Unsupported(what) => write!(f, "{what} cannot be represented in bencode"),
+            Self::KeyMustBeString => {
+                write!(f, "dictionary keys must be strings, bytes or integers")
+            }
+            Self::Message(msg) => write!(f, "{msg}"),
+        }
+    }
+}
+
+impl std::error::Error for SerdeError {}
+
+impl serde::ser::Error for SerdeError {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        Self::Message(msg.to_string())
+    }
+}
+
+impl serde::de::Error for SerdeError {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        Self::Message(msg.to_string())
+    }
+}
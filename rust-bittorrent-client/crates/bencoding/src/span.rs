@@ -0,0 +1,70 @@
+use alloc::{collections::BTreeMap, vec::Vec};
+use core::ops::Range;
+
+/// Byte ranges of decoded values within the original input.
+///
+/// A span is recorded for the root value (the empty path) and for every value
+/// reachable from it through dictionary keys alone. Values nested inside lists
+/// are not recorded.
+///
+/// ```
+/// let input = b"d8:announce3:url4:infod4:name3:fooee";
+/// let (_, spans) = bencoding::decode_with_spans(input).unwrap();
+///
+/// let info = spans.get(&[b"info"]).unwrap();
+/// assert_eq!(&input[info], b"d4:name3:fooe");
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SpanMap {
+    spans: BTreeMap<Vec<Vec<u8>>, Range<usize>>,
+}
+
+impl SpanMap {
+    pub fn get(&self, path: &[&[u8]]) -> Option<Range<usize>> {
+        let path: Vec<Vec<u8>> = path.iter().map(|segment| segment.to_vec()).collect();
+        self.spans.get(&path).cloned()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&[Vec<u8>], &Range<usize>)> {
+        self.spans
+            .iter()
+            .map(|(path, span)| (path.as_slice(), span))
+    }
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct SpanRecorder {
+    map: SpanMap,
+    path: Vec<Vec<u8>>,
+    lists: usize,
+}
+
+impl SpanRecorder {
+    // Returns whether the value under `key` is being recorded
+    pub(crate) fn enter_key(&mut self, key: &[u8]) -> bool {
+        if self.lists > 0 {
+            return false;
+        }
+
+        self.path.push(key.to_vec());
+        true
+    }
+
+    pub(crate) fn leave_key(&mut self, span: Range<usize>) {
+        self.map.spans.insert(self.path.clone(), span);
+        self.path.pop();
+    }
+
+    pub(crate) fn enter_list(&mut self) {
+        self.lists += 1;
+    }
+
+    pub(crate) fn leave_list(&mut self) {
+        self.lists -= 1;
+    }
+
+    pub(crate) fn finish(mut self, root: Range<usize>) -> SpanMap {
+        self.map.spans.insert(Vec::new(), root);
+        self.map
+    }
+}
@@ -0,0 +1,336 @@
+use crate::bencode::BencodeType;
+use crate::decode::{
+    check_dictionary_key, parse_integer, parse_length, DecodeError, DecodeErrorKind,
+    DEFAULT_MAX_DEPTH,
+};
+use std::{
+    collections::BTreeMap,
+    io::{ErrorKind, Read},
+};
+
+// Longest valid integer is "-9223372036854775808" and longest length is u64::MAX
+const MAX_DIGITS: usize = 20;
+
+/// Decodes a single value from an `io::Read`, pulling bytes as they are needed.
+///
+/// Bytes are read one at a time, so wrap unbuffered sources such as sockets in a
+/// `BufReader`.
+#[derive(Debug)]
+pub struct StreamDecoder<R> {
+    reader: R,
+    peeked: Option<u8>,
+    position: usize,
+    depth: usize,
+    max_depth: usize,
+}
+
+impl<R: Read> StreamDecoder<R> {
+    pub fn new(reader: R) -> Self {
+        Self::with_max_depth(reader, DEFAULT_MAX_DEPTH)
+    }
+
+    pub fn with_max_depth(reader: R, max_depth: usize) -> Self {
+        Self {
+            reader,
+            peeked: None,
+            position: 0,
+            depth: 0,
+            max_depth,
+        }
+    }
+
+    /// Number of bytes consumed from the reader so far.
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+
+    pub fn decode(&mut self) -> Result<BencodeType, DecodeError> {
+        match self.peek()? {
+            b'i' => self.decode_integer(),
+            b'l' => self.decode_list(),
+            b'd' => self.decode_dictionary(),
+            b'0'..=b'9' => self.decode_bytestring(),
+            c => Err(self.error(DecodeErrorKind::UnexpectedCharacter(c))),
+        }
+    }
+
+    fn error(&self, kind: impl Into<DecodeErrorKind>) -> DecodeError {
+        self.error_at(self.position, kind)
+    }
+
+    fn error_at(&self, offset: usize, kind: impl Into<DecodeErrorKind>) -> DecodeError {
+        DecodeError::new(kind.into(), offset)
+    }
+
+    fn end_of_input(&self) -> DecodeError {
+        self.error(DecodeErrorKind::UnexpectedEndOfInput)
+    }
+
+    fn peek(&mut self) -> Result<u8, DecodeError> {
+        if let Some(byte) = self.peeked {
+            return Ok(byte);
+        }
+
+        let mut buf = [0; 1];
+        loop {
+            match self.reader.read(&mut buf) {
+                Ok(0) => return Err(self.end_of_input()),
+                Ok(_) => break,
+                Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+                Err(e) => return Err(self.error(e)),
+            }
+        }
+
+        self.peeked = Some(buf[0]);
+        Ok(buf[0])
+    }
+
+    fn next_byte(&mut self) -> Result<u8, DecodeError> {
+        let byte = self.peek()?;
+        self.peeked = None;
+        self.position += 1;
+
+        Ok(byte)
+    }
+
+    fn read_until(&mut self, terminator: u8) -> Result<Vec<u8>, DecodeError> {
+        let start = self.position;
+        let mut digits = Vec::new();
+
+        loop {
+            let byte = self.next_byte()?;
+            if byte == terminator {
+                return Ok(digits);
+            }
+
+            digits.push(byte);
+            if digits.len() > MAX_DIGITS {
+                return Err(self.error_at(start, DecodeErrorKind::IntegerOverflow));
+            }
+        }
+    }
+
+    fn enter_container(&mut self) -> Result<(), DecodeError> {
+        if self.depth >= self.max_depth {
+            return Err(self.error(DecodeErrorKind::MaxDepthExceeded));
+        }
+        self.depth += 1;
+
+        Ok(())
+    }
+
+    fn leave_container(&mut self) {
+        self.depth -= 1;
+    }
+
+    fn decode_bytestring(&mut self) -> Result<BencodeType, DecodeError> {
+        Ok(BencodeType::ByteString(self.read_bytestring()?))
+    }
+
+    fn read_bytestring(&mut self) -> Result<Vec<u8>, DecodeError> {
+        let start = self.position;
+        let digits = self.read_until(b':')?;
+        let string_len = parse_length(&digits).map_err(|kind| self.error_at(start, kind))?;
+
+        // Let the buffer grow as data arrives instead of trusting the declared length
+        let mut bytes = Vec::new();
+        let read = (&mut self.reader)
+            .take(string_len as u64)
+            .read_to_end(&mut bytes)
+            .map_err(|e| self.error(e))?;
+        self.position += read;
+
+        if read < string_len {
+            return Err(self.end_of_input());
+        }
+
+        Ok(bytes)
+    }
+
+    fn decode_integer(&mut self) -> Result<BencodeType, DecodeError> {
+        self.next_byte()?; // skip 'i'
+        let start = self.position;
+
+        let digits = self.read_until(b'e')?;
+        let integer = parse_integer(&digits).map_err(|kind| self.error_at(start, kind))?;
+
+        Ok(BencodeType::Integer(integer))
+    }
+
+    fn decode_list(&mut self) -> Result<BencodeType, DecodeError> {
+        self.enter_container()?;
+        self.next_byte()?; // skip 'l'
+        let mut res = Vec::new();
+
+        while self.peek()? != b'e' {
+            res.push(self.decode()?);
+        }
+
+        self.next_byte()?; // skip 'e'
+        self.leave_container();
+
+        Ok(BencodeType::List(res))
+    }
+
+    fn decode_dictionary(&mut self) -> Result<BencodeType, DecodeError> {
+        self.enter_container()?;
+        self.next_byte()?; // skip 'd'
+        let mut res: BTreeMap<Vec<u8>, BencodeType> = BTreeMap::new();
+
+        while self.peek()? != b'e' {
+            let key_start = self.position;
+            if !self.peek()?.is_ascii_digit() {
+                return Err(self.error(DecodeErrorKind::NonStringKey));
+            }
+            let k_inner = self.read_bytestring()?;
+            let previous = res.last_key_value().map(|(k, _)| k.as_slice());
+            check_dictionary_key(&k_inner, previous, res.contains_key(&k_inner), true, true)
+                .map_err(|kind| self.error_at(key_start, kind))?;
+            let v = self.decode()?;
+
+            res.insert(k_inner, v);
+        }
+
+        self.next_byte()?; // skip 'e'
+        self.leave_container();
+
+        Ok(BencodeType::Dictionary(res))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    // Hands out at most one byte per read call
+    struct Trickle<'a>(&'a [u8]);
+
+    impl Read for Trickle<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            match self.0.split_first() {
+                Some((first, rest)) if !buf.is_empty() => {
+                    buf[0] = *first;
+                    self.0 = rest;
+                    Ok(1)
+                }
+                _ => Ok(0),
+            }
+        }
+    }
+
+    #[test]
+    fn stream_decode_cursor() {
+        let reader = Cursor::new(b"d3:fooi-42e4:spaml1:a1:bee".to_vec());
+        let mut decoder = StreamDecoder::new(reader);
+        let result = decoder.decode().unwrap();
+
+        let mut expected_dict = BTreeMap::new();
+        expected_dict.insert(
+            b"spam".to_vec(),
+            BencodeType::List(vec![
+                BencodeType::ByteString(b"a".to_vec()),
+                BencodeType::ByteString(b"b".to_vec()),
+            ]),
+        );
+        expected_dict.insert(b"foo".to_vec(), BencodeType::Integer(-42));
+
+        assert_eq!(result, BencodeType::Dictionary(expected_dict));
+        assert_eq!(decoder.position(), 26);
+    }
+
+    #[test]
+    fn stream_decode_one_byte_reads() {
+        let mut decoder = StreamDecoder::new(Trickle(b"l4:spam4:eggse"));
+        let result = decoder.decode().unwrap();
+        assert_eq!(
+            result,
+            BencodeType::List(vec![
+                BencodeType::ByteString(b"spam".to_vec()),
+                BencodeType::ByteString(b"eggs".to_vec())
+            ])
+        );
+    }
+
+    #[test]
+    fn stream_decode_leaves_rest_unread() {
+        let mut decoder = StreamDecoder::new(Cursor::new(b"i1ei2e".to_vec()));
+        assert_eq!(decoder.decode().unwrap(), BencodeType::Integer(1));
+        assert_eq!(decoder.decode().unwrap(), BencodeType::Integer(2));
+        assert_eq!(decoder.position(), 6);
+    }
+
+    #[test]
+    fn stream_decode_truncated_bytestring() {
+        let mut decoder = StreamDecoder::new(Cursor::new(b"4000000000:short".to_vec()));
+        let err = decoder.decode().unwrap_err();
+        assert!(matches!(err.kind(), DecodeErrorKind::UnexpectedEndOfInput));
+        assert_eq!(err.offset(), 16);
+    }
+
+    #[test]
+    fn stream_decode_empty() {
+        let mut decoder = StreamDecoder::new(Cursor::new(Vec::new()));
+        let err = decoder.decode().unwrap_err();
+        assert!(matches!(err.kind(), DecodeErrorKind::UnexpectedEndOfInput));
+    }
+
+    #[test]
+    fn stream_decode_invalid_integer() {
+        let mut decoder = StreamDecoder::new(Cursor::new(b"i03e".to_vec()));
+        let err = decoder.decode().unwrap_err();
+        assert!(matches!(err.kind(), DecodeErrorKind::InvalidInteger));
+        assert_eq!(err.offset(), 1);
+    }
+
+    #[test]
+    fn stream_decode_max_depth_exceeded() {
+        let input = vec![b'l'; 10_000];
+        let mut decoder = StreamDecoder::new(Cursor::new(input));
+        let err = decoder.decode().unwrap_err();
+        assert!(matches!(err.kind(), DecodeErrorKind::MaxDepthExceeded));
+    }
+
+    #[test]
+    fn stream_decode_io_error() {
+        struct Broken;
+
+        impl Read for Broken {
+            fn read(&mut self, _: &mut [u8]) -> std::io::Result<usize> {
+                Err(std::io::Error::other("connection reset"))
+            }
+        }
+
+        let mut decoder = StreamDecoder::new(Broken);
+        let err = decoder.decode().unwrap_err();
+        assert!(matches!(err.kind(), DecodeErrorKind::Io(_)));
+    }
+
+    #[test]
+    fn stream_decode_non_string_key() {
+        let mut decoder = StreamDecoder::new(Cursor::new(b"di1ei2ee".to_vec()));
+        let err = decoder.decode().unwrap_err();
+        assert!(matches!(err.kind(), DecodeErrorKind::NonStringKey));
+        assert_eq!(err.offset(), 1);
+    }
+
+    #[test]
+    fn stream_decode_key_checks_match_decode() {
+        for input in [
+            &b"d1:ai1e1:ai2ee"[..],
+            b"d1:bi1e1:ai2ee",
+            b"d1:ai1e1:bi2e1:ai3ee",
+        ] {
+            let stream_err = StreamDecoder::new(Cursor::new(input.to_vec()))
+                .decode()
+                .unwrap_err();
+            let err = crate::decode(input).unwrap_err();
+            assert_eq!(stream_err.offset(), err.offset());
+            assert_eq!(stream_err.kind().to_string(), err.kind().to_string());
+        }
+    }
+}
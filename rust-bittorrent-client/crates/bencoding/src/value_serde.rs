@@ -0,0 +1,188 @@
+use crate::bencode::BencodeType;
+use serde::de::{self, Deserialize, Deserializer, MapAccess, SeqAccess, Visitor};
+use serde::ser::{Serialize, SerializeMap, SerializeSeq, Serializer};
+use std::{collections::BTreeMap, fmt};
+
+// Bytestrings become strings when they are valid UTF-8 and raw bytes otherwise.
+// Formats without a bytes type, like JSON, render the latter as an array of numbers.
+impl Serialize for BencodeType {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Self::ByteString(s) => match std::str::from_utf8(s) {
+                Ok(text) => serializer.serialize_str(text),
+                Err(_) => serializer.serialize_bytes(s),
+            },
+            Self::Integer(i) => serializer.serialize_i64(*i),
+            Self::List(v) => {
+                let mut seq = serializer.serialize_seq(Some(v.len()))?;
+                for item in v {
+                    seq.serialize_element(item)?;
+                }
+                seq.end()
+            }
+            Self::Dictionary(d) => {
+                let mut map = serializer.serialize_map(Some(d.len()))?;
+                for (k, v) in d {
+                    map.serialize_entry(&String::from_utf8_lossy(k), v)?;
+                }
+                map.end()
+            }
+        }
+    }
+}
+
+// The reverse direction is lossy: strings become their UTF-8 bytes, so a binary
+// bytestring serialized as a JSON array of numbers comes back as a list of integers.
+// Booleans, floats, null and integers above i64::MAX have no bencode form and are errors.
+impl<'de> Deserialize<'de> for BencodeType {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(BencodeVisitor)
+    }
+}
+
+struct BencodeVisitor;
+
+impl<'de> Visitor<'de> for BencodeVisitor {
+    type Value = BencodeType;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a string, bytes, integer, sequence or map")
+    }
+
+    fn visit_i64<E: de::Error>(self, v: i64) -> Result<Self::Value, E> {
+        Ok(BencodeType::Integer(v))
+    }
+
+    fn visit_u64<E: de::Error>(self, v: u64) -> Result<Self::Value, E> {
+        i64::try_from(v)
+            .map(BencodeType::Integer)
+            .map_err(|_| E::invalid_value(de::Unexpected::Unsigned(v), &self))
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<Self::Value, E> {
+        Ok(BencodeType::ByteString(v.as_bytes().to_vec()))
+    }
+
+    fn visit_string<E: de::Error>(self, v: String) -> Result<Self::Value, E> {
+        Ok(BencodeType::ByteString(v.into_bytes()))
+    }
+
+    fn visit_bytes<E: de::Error>(self, v: &[u8]) -> Result<Self::Value, E> {
+        Ok(BencodeType::ByteString(v.to_vec()))
+    }
+
+    fn visit_byte_buf<E: de::Error>(self, v: Vec<u8>) -> Result<Self::Value, E> {
+        Ok(BencodeType::ByteString(v))
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let mut res = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+        while let Some(item) = seq.next_element()? {
+            res.push(item);
+        }
+
+        Ok(BencodeType::List(res))
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+        let mut res = BTreeMap::new();
+        while let Some((ByteKey(k), v)) = map.next_entry()? {
+            res.insert(k, v);
+        }
+
+        Ok(BencodeType::Dictionary(res))
+    }
+}
+
+// Dictionary keys, accepted as either strings or bytes
+struct ByteKey(Vec<u8>);
+
+impl<'de> Deserialize<'de> for ByteKey {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct ByteKeyVisitor;
+
+        impl Visitor<'_> for ByteKeyVisitor {
+            type Value = ByteKey;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a string or bytes key")
+            }
+
+            fn visit_str<E: de::Error>(self, v: &str) -> Result<Self::Value, E> {
+                Ok(ByteKey(v.as_bytes().to_vec()))
+            }
+
+            fn visit_bytes<E: de::Error>(self, v: &[u8]) -> Result<Self::Value, E> {
+                Ok(ByteKey(v.to_vec()))
+            }
+        }
+
+        deserializer.deserialize_any(ByteKeyVisitor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn serialize_json() {
+        let dict = BencodeType::dict()
+            .insert("announce", "http://tracker/")
+            .insert(
+                "info",
+                BencodeType::dict()
+                    .insert("length", 12)
+                    .insert("pieces", vec![0xffu8, 0x00, 0x10])
+                    .build(),
+            )
+            .insert(
+                "list",
+                BencodeType::from(vec![BencodeType::from(-1), "a".into()]),
+            )
+            .build();
+
+        assert_eq!(
+            serde_json::to_string(&dict).unwrap(),
+            r#"{"announce":"http://tracker/","info":{"length":12,"pieces":[255,0,16]},"list":[-1,"a"]}"#
+        );
+    }
+
+    #[test]
+    fn serialize_json_binary_key() {
+        let dict: BencodeType = [(vec![b'a', 0xff], BencodeType::Integer(1))]
+            .into_iter()
+            .collect();
+        assert_eq!(serde_json::to_string(&dict).unwrap(), "{\"a\u{fffd}\":1}");
+    }
+
+    #[test]
+    fn deserialize_json() {
+        let json = r#"{"announce":"http://tracker/","info":{"length":12},"list":[-1,"a",[]]}"#;
+        let value: BencodeType = serde_json::from_str(json).unwrap();
+
+        let expected = BencodeType::dict()
+            .insert("announce", "http://tracker/")
+            .insert("info", BencodeType::dict().insert("length", 12).build())
+            .insert(
+                "list",
+                BencodeType::from(vec![
+                    BencodeType::from(-1),
+                    "a".into(),
+                    BencodeType::List(vec![]),
+                ]),
+            )
+            .build();
+
+        assert_eq!(value, expected);
+        assert_eq!(serde_json::to_string(&value).unwrap(), json);
+    }
+
+    #[test]
+    fn deserialize_json_unsupported() {
+        assert!(serde_json::from_str::<BencodeType>("1.5").is_err());
+        assert!(serde_json::from_str::<BencodeType>("true").is_err());
+        assert!(serde_json::from_str::<BencodeType>("null").is_err());
+        assert!(serde_json::from_str::<BencodeType>("18446744073709551615").is_err());
+    }
+}
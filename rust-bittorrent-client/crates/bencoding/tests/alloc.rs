@@ -0,0 +1,45 @@
+#![cfg(feature = "test-alloc")]
+
+use bencoding::{count_allocations, CountingAllocator};
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+#[test]
+fn decode_ref_does_not_copy_bytestrings() {
+    let mut input = b"d4:name8:file.txt6:pieces1000:".to_vec();
+    input.extend(vec![0xab; 1000]);
+    input.push(b'e');
+
+    let (owned, owned_stats) = count_allocations(|| bencoding::decode(&input).unwrap());
+    let (borrowed, ref_stats) = count_allocations(|| bencoding::decode_ref(&input).unwrap());
+    assert_eq!(borrowed.to_owned(), owned);
+
+    // The owned decode copies `pieces`, the borrowed one only builds the map
+    assert!(owned_stats.bytes >= 1000, "{owned_stats:?}");
+    assert!(ref_stats.bytes < 1000, "{ref_stats:?}");
+    assert!(
+        ref_stats.count < owned_stats.count,
+        "{ref_stats:?} {owned_stats:?}"
+    );
+}
+
+#[test]
+fn encode_integers_into_presized_buffer() {
+    use bencoding::{BencodeType, Encodable};
+
+    let encode = |count: i64| {
+        let list = BencodeType::List((0..count).map(BencodeType::Integer).collect());
+        let mut out = Vec::with_capacity(list.encoded_len());
+        let ((), stats) = count_allocations(|| list.encode_into(&mut out));
+        assert_eq!(out, list.encode());
+        stats
+    };
+
+    // The digits are written straight into `out`, so 10k integers cost no
+    // more than 10
+    let small = encode(10);
+    let large = encode(10_000);
+    assert_eq!(large, small);
+    assert_eq!(large.count, 0, "{large:?}");
+}
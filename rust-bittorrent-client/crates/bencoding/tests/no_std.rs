@@ -0,0 +1,17 @@
+use std::process::Command;
+
+// The unit tests always link std, so build the library on its own to catch
+// std-only paths slipping in outside the `std` feature
+#[test]
+fn builds_without_std() {
+    let status = Command::new(env!("CARGO"))
+        .args(["build", "--quiet", "--offline", "--no-default-features"])
+        .args([
+            "--manifest-path",
+            concat!(env!("CARGO_MANIFEST_DIR"), "/Cargo.toml"),
+        ])
+        .args(["--target-dir", env!("CARGO_TARGET_TMPDIR")])
+        .status()
+        .unwrap();
+    assert!(status.success());
+}
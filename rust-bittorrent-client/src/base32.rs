@@ -0,0 +1,82 @@
+const ALPHABET: &[u8; 32] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+
+/// Unpadded uppercase RFC 4648 base32 representation of `bytes`.
+pub fn encode(bytes: &[u8]) -> String {
+    let mut out = String::with_capacity(bytes.len().div_ceil(5) * 8);
+    let mut buffer: u32 = 0;
+    let mut bits = 0;
+
+    for &byte in bytes {
+        buffer = (buffer << 8) | byte as u32;
+        bits += 8;
+
+        while bits >= 5 {
+            bits -= 5;
+            out.push(ALPHABET[(buffer >> bits) as usize & 0x1f] as char);
+        }
+        buffer &= (1 << bits) - 1;
+    }
+    if bits > 0 {
+        out.push(ALPHABET[(buffer << (5 - bits)) as usize & 0x1f] as char);
+    }
+
+    out
+}
+
+/// Decodes unpadded RFC 4648 base32, ignoring case.
+pub fn decode(input: &str) -> Option<Vec<u8>> {
+    let mut out = Vec::with_capacity(input.len() * 5 / 8);
+    let mut buffer: u32 = 0;
+    let mut bits = 0;
+
+    for c in input.bytes() {
+        let value = match c.to_ascii_uppercase() {
+            c @ b'A'..=b'Z' => c - b'A',
+            c @ b'2'..=b'7' => c - b'2' + 26,
+            _ => return None,
+        };
+        buffer = (buffer << 5) | value as u32;
+        bits += 5;
+
+        if bits >= 8 {
+            bits -= 8;
+            out.push((buffer >> bits) as u8);
+            buffer &= (1 << bits) - 1;
+        }
+    }
+
+    Some(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn base32_encode() {
+        assert_eq!(encode(b""), "");
+        assert_eq!(encode(b"f"), "MY");
+        assert_eq!(encode(b"fooba"), "MZXW6YTB");
+        assert_eq!(encode(b"foobar"), "MZXW6YTBOI");
+    }
+
+    #[test]
+    fn base32_info_hash_round_trip() {
+        let info_hash: Vec<u8> = (0..20).map(|i| i * 13).collect();
+        let encoded = encode(&info_hash);
+        assert_eq!(encoded.len(), 32);
+        assert_eq!(decode(&encoded).unwrap(), info_hash);
+        assert_eq!(
+            crate::hex::decode(&crate::hex::encode(&info_hash)).unwrap(),
+            info_hash
+        );
+    }
+
+    #[test]
+    fn base32_decode() {
+        assert_eq!(decode("").unwrap(), b"");
+        assert_eq!(decode("MZXW6YTBOI").unwrap(), b"foobar");
+        assert_eq!(decode("mzxw6ytboi").unwrap(), b"foobar");
+        assert_eq!(decode("MZXW6!"), None);
+    }
+}
@@ -0,0 +1,193 @@
+/// Set of pieces, in the wire layout where piece 0 is the high bit of byte 0.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Bitfield {
+    bytes: Vec<u8>,
+    num_pieces: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BitfieldError {
+    WrongLength {
+        expected: usize,
+        actual: usize,
+    },
+    /// Bits past the last piece must be zero
+    SpareBitsSet,
+    IndexOutOfRange {
+        index: usize,
+        len: usize,
+    },
+}
+
+impl Bitfield {
+    pub fn new(num_pieces: usize) -> Self {
+        Self {
+            bytes: vec![0; num_pieces.div_ceil(8)],
+            num_pieces,
+        }
+    }
+
+    /// Parses a bitfield received from a peer.
+    pub fn from_bytes(bytes: &[u8], num_pieces: usize) -> Result<Self, BitfieldError> {
+        let expected = num_pieces.div_ceil(8);
+        if bytes.len() != expected {
+            return Err(BitfieldError::WrongLength {
+                expected,
+                actual: bytes.len(),
+            });
+        }
+
+        let spare = expected * 8 - num_pieces;
+        if let Some(last) = bytes.last() {
+            if last & ((1u16 << spare) - 1) as u8 != 0 {
+                return Err(BitfieldError::SpareBitsSet);
+            }
+        }
+
+        Ok(Self {
+            bytes: bytes.to_vec(),
+            num_pieces,
+        })
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.bytes.clone()
+    }
+
+    pub fn len(&self) -> usize {
+        self.num_pieces
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.num_pieces == 0
+    }
+
+    /// `false` for indices past the end.
+    pub fn has(&self, index: usize) -> bool {
+        index < self.num_pieces && self.bytes[index / 8] & mask(index) != 0
+    }
+
+    /// Panics if `index` is past the end.
+    pub fn set(&mut self, index: usize) {
+        assert!(
+            index < self.num_pieces,
+            "piece index {index} out of range for {} pieces",
+            self.num_pieces
+        );
+        self.bytes[index / 8] |= mask(index);
+    }
+
+    /// Records a peer's `Have`, which unlike `set` must not trust the index.
+    pub fn apply_have(&mut self, index: usize) -> Result<(), BitfieldError> {
+        if index >= self.num_pieces {
+            return Err(BitfieldError::IndexOutOfRange {
+                index,
+                len: self.num_pieces,
+            });
+        }
+        self.set(index);
+        Ok(())
+    }
+
+    /// Whether all of the first `num_pieces` pieces are set, `false` if the
+    /// bitfield is shorter than that.
+    pub fn is_complete(&self, num_pieces: usize) -> bool {
+        (0..num_pieces).all(|index| self.has(index))
+    }
+
+    pub fn count_ones(&self) -> usize {
+        self.bytes
+            .iter()
+            .map(|byte| byte.count_ones() as usize)
+            .sum()
+    }
+}
+
+fn mask(index: usize) -> u8 {
+    0x80 >> (index % 8)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bitfield_set_and_has() {
+        let mut bitfield = Bitfield::new(10);
+        assert_eq!(bitfield.to_bytes(), [0, 0]);
+
+        bitfield.set(0);
+        bitfield.set(7);
+        bitfield.set(8);
+        bitfield.set(9);
+
+        assert!(bitfield.has(0));
+        assert!(!bitfield.has(1));
+        assert!(bitfield.has(7));
+        assert!(bitfield.has(8));
+        assert!(bitfield.has(9));
+        assert!(!bitfield.has(10));
+        assert_eq!(bitfield.count_ones(), 4);
+        assert_eq!(bitfield.to_bytes(), [0b1000_0001, 0b1100_0000]);
+    }
+
+    #[test]
+    #[should_panic(expected = "out of range")]
+    fn bitfield_set_out_of_range() {
+        Bitfield::new(3).set(3);
+    }
+
+    #[test]
+    fn bitfield_apply_have() {
+        let mut bitfield = Bitfield::from_bytes(&[0b1010_0000], 3).unwrap();
+        assert!(!bitfield.is_complete(3));
+
+        bitfield.apply_have(1).unwrap();
+        bitfield.apply_have(1).unwrap();
+        assert!(bitfield.has(1));
+        assert!(bitfield.is_complete(3));
+        assert!(!bitfield.is_complete(4));
+
+        assert_eq!(
+            bitfield.apply_have(3),
+            Err(BitfieldError::IndexOutOfRange { index: 3, len: 3 })
+        );
+        assert_eq!(bitfield.to_bytes(), [0b1110_0000]);
+    }
+
+    #[test]
+    fn bitfield_is_complete_empty() {
+        assert!(Bitfield::new(0).is_complete(0));
+        assert!(!Bitfield::new(9).is_complete(9));
+    }
+
+    #[test]
+    fn bitfield_from_bytes() {
+        let bitfield = Bitfield::from_bytes(&[0b0100_0000, 0b1000_0000], 9).unwrap();
+        assert!(bitfield.has(1));
+        assert!(bitfield.has(8));
+        assert_eq!(bitfield.count_ones(), 2);
+
+        assert!(Bitfield::from_bytes(&[0xff], 8).is_ok());
+        assert!(Bitfield::from_bytes(&[], 0).is_ok());
+    }
+
+    #[test]
+    fn bitfield_rejects_spare_bits() {
+        assert_eq!(
+            Bitfield::from_bytes(&[0, 0b0100_0000], 9),
+            Err(BitfieldError::SpareBitsSet)
+        );
+    }
+
+    #[test]
+    fn bitfield_rejects_wrong_length() {
+        assert_eq!(
+            Bitfield::from_bytes(&[0], 9),
+            Err(BitfieldError::WrongLength {
+                expected: 2,
+                actual: 1
+            })
+        );
+    }
+}
@@ -0,0 +1,116 @@
+use crate::message::PeerMessage;
+use std::{collections::BTreeMap, net::SocketAddr};
+
+/// Outstanding block requests and the peers they went to. In endgame mode the
+/// same block is requested from several peers, and the rest are cancelled
+/// once one of them delivers it.
+#[derive(Debug, Clone, Default)]
+pub struct BlockTracker {
+    // Keyed by (piece index, begin)
+    pending: BTreeMap<(u32, u32), PendingBlock>,
+}
+
+#[derive(Debug, Clone)]
+struct PendingBlock {
+    length: u32,
+    peers: Vec<SocketAddr>,
+}
+
+impl BlockTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records that block `begin..begin + length` of piece `index` was
+    /// requested from `peer`.
+    pub fn request(&mut self, peer: SocketAddr, index: u32, begin: u32, length: u32) {
+        let block = self
+            .pending
+            .entry((index, begin))
+            .or_insert_with(|| PendingBlock {
+                length,
+                peers: Vec::new(),
+            });
+        if !block.peers.contains(&peer) {
+            block.peers.push(peer);
+        }
+    }
+
+    /// Marks the block as received from `peer`, returning a `Cancel` for
+    /// every other peer it is still requested from.
+    pub fn received(
+        &mut self,
+        peer: SocketAddr,
+        index: u32,
+        begin: u32,
+    ) -> Vec<(SocketAddr, PeerMessage)> {
+        let Some(block) = self.pending.remove(&(index, begin)) else {
+            return Vec::new();
+        };
+
+        block
+            .peers
+            .into_iter()
+            .filter(|&other| other != peer)
+            .map(|other| {
+                let cancel = PeerMessage::Cancel {
+                    index,
+                    begin,
+                    length: block.length,
+                };
+                (other, cancel)
+            })
+            .collect()
+    }
+
+    /// Number of blocks requested but not yet received, each counted once
+    /// however many peers it went to.
+    pub fn pending_count(&self) -> usize {
+        self.pending.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metainfo::BLOCK_LEN;
+
+    fn peer(port: u16) -> SocketAddr {
+        SocketAddr::from(([10, 0, 0, 1], port))
+    }
+
+    #[test]
+    fn endgame_race_cancels_loser() {
+        let mut tracker = BlockTracker::new();
+        tracker.request(peer(1), 7, BLOCK_LEN, BLOCK_LEN);
+        tracker.request(peer(2), 7, BLOCK_LEN, BLOCK_LEN);
+        tracker.request(peer(2), 7, BLOCK_LEN, BLOCK_LEN);
+        tracker.request(peer(1), 7, 0, BLOCK_LEN);
+        assert_eq!(tracker.pending_count(), 2);
+
+        let cancels = tracker.received(peer(2), 7, BLOCK_LEN);
+        assert_eq!(
+            cancels,
+            [(
+                peer(1),
+                PeerMessage::Cancel {
+                    index: 7,
+                    begin: BLOCK_LEN,
+                    length: BLOCK_LEN,
+                }
+            )]
+        );
+        assert_eq!(tracker.pending_count(), 1);
+
+        // The losing peer's late copy needs no further cancels
+        assert!(tracker.received(peer(1), 7, BLOCK_LEN).is_empty());
+    }
+
+    #[test]
+    fn received_from_only_peer() {
+        let mut tracker = BlockTracker::new();
+        tracker.request(peer(1), 0, 0, 100);
+        assert!(tracker.received(peer(1), 0, 0).is_empty());
+        assert_eq!(tracker.pending_count(), 0);
+    }
+}
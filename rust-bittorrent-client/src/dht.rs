@@ -0,0 +1,88 @@
+use std::net::{Ipv4Addr, SocketAddrV4};
+
+/// Length of one entry in a compact `nodes` bytestring.
+pub const COMPACT_NODE_LEN: usize = 26;
+
+/// A DHT node as returned by `find_node` and `get_peers`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct NodeInfo {
+    pub id: [u8; 20],
+    pub addr: SocketAddrV4,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DhtError {
+    /// Compact nodes not a multiple of 26 bytes
+    InvalidNodesLength(usize),
+}
+
+/// Splits 26-byte entries of a node id followed by a compact IPv4 address and port.
+pub fn parse_compact_nodes(bytes: &[u8]) -> Result<Vec<NodeInfo>, DhtError> {
+    if !bytes.len().is_multiple_of(COMPACT_NODE_LEN) {
+        return Err(DhtError::InvalidNodesLength(bytes.len()));
+    }
+
+    Ok(bytes
+        .chunks_exact(COMPACT_NODE_LEN)
+        .map(|entry| {
+            let ip = Ipv4Addr::new(entry[20], entry[21], entry[22], entry[23]);
+            NodeInfo {
+                id: entry[..20].try_into().unwrap(),
+                addr: SocketAddrV4::new(ip, u16::from_be_bytes([entry[24], entry[25]])),
+            }
+        })
+        .collect())
+}
+
+/// The inverse of `parse_compact_nodes`.
+pub fn encode_compact_nodes(nodes: &[NodeInfo]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(nodes.len() * COMPACT_NODE_LEN);
+    for node in nodes {
+        out.extend_from_slice(&node.id);
+        out.extend_from_slice(&node.addr.ip().octets());
+        out.extend_from_slice(&node.addr.port().to_be_bytes());
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_two_nodes() {
+        let mut bytes = vec![0x11; 20];
+        bytes.extend_from_slice(&[10, 0, 0, 1, 0x1a, 0xe1]);
+        bytes.extend_from_slice(&[0x22; 20]);
+        bytes.extend_from_slice(&[192, 168, 1, 2, 0x00, 0x50]);
+
+        let nodes = parse_compact_nodes(&bytes).unwrap();
+        assert_eq!(
+            nodes,
+            vec![
+                NodeInfo {
+                    id: [0x11; 20],
+                    addr: "10.0.0.1:6881".parse().unwrap(),
+                },
+                NodeInfo {
+                    id: [0x22; 20],
+                    addr: "192.168.1.2:80".parse().unwrap(),
+                },
+            ]
+        );
+        assert_eq!(encode_compact_nodes(&nodes), bytes);
+    }
+
+    #[test]
+    fn parse_invalid_length() {
+        assert_eq!(
+            parse_compact_nodes(&[0; 27]),
+            Err(DhtError::InvalidNodesLength(27))
+        );
+    }
+
+    #[test]
+    fn parse_empty() {
+        assert_eq!(parse_compact_nodes(&[]), Ok(Vec::new()));
+    }
+}
@@ -0,0 +1,181 @@
+use crate::message::PeerMessage;
+use bencoding::{BencodeType, DecodeError, Encodable};
+use std::collections::BTreeMap;
+
+/// Extended message id of the handshake itself (BEP 10).
+pub const EXTENDED_HANDSHAKE_ID: u8 = 0;
+
+/// The first extended message, announcing which extensions a peer supports.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ExtensionHandshake {
+    /// Extension name to the id the sender wants its messages tagged with,
+    /// from `m`. An id of 0 disables the extension.
+    pub extensions: BTreeMap<String, u8>,
+    /// Client name and version, from `v`
+    pub version: Option<String>,
+    /// Outstanding requests the sender allows, from `reqq`
+    pub reqq: Option<i64>,
+    /// Size of the bencoded info dictionary, for `ut_metadata` (BEP 9)
+    pub metadata_size: Option<i64>,
+}
+
+#[derive(Debug)]
+pub enum ExtensionError {
+    Decode(DecodeError),
+    NotADictionary,
+    MissingKey(&'static str),
+    WrongType(&'static str),
+}
+
+impl ExtensionHandshake {
+    /// The bencoded dictionary carried by the handshake message.
+    pub fn encode(&self) -> Vec<u8> {
+        let extensions: BTreeMap<Vec<u8>, BencodeType> = self
+            .extensions
+            .iter()
+            .map(|(name, id)| (name.as_bytes().to_vec(), BencodeType::Integer(*id as i64)))
+            .collect();
+
+        let mut dict = BencodeType::dict().insert("m", extensions);
+        if let Some(version) = &self.version {
+            dict = dict.insert("v", version.as_str());
+        }
+        if let Some(reqq) = self.reqq {
+            dict = dict.insert("reqq", reqq);
+        }
+        if let Some(size) = self.metadata_size {
+            dict = dict.insert("metadata_size", size);
+        }
+        dict.build().encode()
+    }
+
+    /// Wraps the handshake in an extended peer message.
+    pub fn to_message(&self) -> PeerMessage {
+        PeerMessage::Extended {
+            id: EXTENDED_HANDSHAKE_ID,
+            payload: self.encode(),
+        }
+    }
+
+    /// Decodes the payload of an extended message with id 0. Unknown keys are ignored.
+    pub fn decode(payload: &[u8]) -> Result<Self, ExtensionError> {
+        let value = bencoding::decode(payload).map_err(ExtensionError::Decode)?;
+        if value.as_dict().is_none() {
+            return Err(ExtensionError::NotADictionary);
+        }
+
+        let extensions = value
+            .get("m")
+            .ok_or(ExtensionError::MissingKey("m"))?
+            .iter_dict()
+            .ok_or(ExtensionError::WrongType("m"))?
+            .map(|(name, id)| {
+                let name = String::from_utf8(name.to_vec()).ok()?;
+                let id = u8::try_from(id.as_integer()?).ok()?;
+                Some((name, id))
+            })
+            .collect::<Option<_>>()
+            .ok_or(ExtensionError::WrongType("m"))?;
+
+        let version = value
+            .get("v")
+            .map(|v| {
+                v.as_bytes()
+                    .map(|bytes| String::from_utf8_lossy(bytes).into_owned())
+                    .ok_or(ExtensionError::WrongType("v"))
+            })
+            .transpose()?;
+
+        let integer = |key: &'static str| {
+            value
+                .get(key)
+                .map(|v| v.as_integer().ok_or(ExtensionError::WrongType(key)))
+                .transpose()
+        };
+
+        Ok(Self {
+            extensions,
+            version,
+            reqq: integer("reqq")?,
+            metadata_size: integer("metadata_size")?,
+        })
+    }
+
+    /// The id the peer wants messages of extension `name` tagged with, if it
+    /// supports it.
+    pub fn id_of(&self, name: &str) -> Option<u8> {
+        self.extensions.get(name).copied().filter(|&id| id != 0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encode_ut_metadata() {
+        let handshake = ExtensionHandshake {
+            extensions: BTreeMap::from([("ut_metadata".to_string(), 3)]),
+            version: Some("rbt 0.1".to_string()),
+            reqq: Some(250),
+            metadata_size: None,
+        };
+
+        assert_eq!(
+            handshake.encode(),
+            b"d1:md11:ut_metadatai3ee4:reqqi250e1:v7:rbt 0.1e"
+        );
+
+        let message = handshake.to_message().encode();
+        assert_eq!(&message[4..6], &[20, 0]);
+        assert_eq!(&message[6..], handshake.encode());
+    }
+
+    #[test]
+    fn decode_received() {
+        let payload =
+            b"d1:md11:ut_metadatai2e6:ut_pexi0ee13:metadata_sizei31235e1:v13:qBittorrent/4e";
+        let handshake = ExtensionHandshake::decode(payload).unwrap();
+
+        assert_eq!(handshake.version.as_deref(), Some("qBittorrent/4"));
+        assert_eq!(handshake.reqq, None);
+        assert_eq!(handshake.metadata_size, Some(31235));
+        assert_eq!(handshake.id_of("ut_metadata"), Some(2));
+        assert_eq!(handshake.id_of("ut_pex"), None);
+        assert_eq!(handshake.id_of("lt_donthave"), None);
+    }
+
+    #[test]
+    fn round_trip() {
+        let handshake = ExtensionHandshake {
+            extensions: BTreeMap::from([("ut_metadata".to_string(), 1), ("ut_pex".to_string(), 2)]),
+            version: None,
+            reqq: None,
+            metadata_size: Some(31235),
+        };
+        assert_eq!(
+            ExtensionHandshake::decode(&handshake.encode()).unwrap(),
+            handshake
+        );
+    }
+
+    #[test]
+    fn decode_errors() {
+        assert!(matches!(
+            ExtensionHandshake::decode(b"d1:v1:xe"),
+            Err(ExtensionError::MissingKey("m"))
+        ));
+        assert!(matches!(
+            ExtensionHandshake::decode(b"d1:md1:ai300eee"),
+            Err(ExtensionError::WrongType("m"))
+        ));
+        assert!(matches!(
+            ExtensionHandshake::decode(b"le"),
+            Err(ExtensionError::NotADictionary)
+        ));
+        assert!(matches!(
+            ExtensionHandshake::decode(b"d1:m"),
+            Err(ExtensionError::Decode(_))
+        ));
+    }
+}
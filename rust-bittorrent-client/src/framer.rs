@@ -0,0 +1,164 @@
+use crate::message::{MessageError, PeerMessage};
+use std::io::{self, ErrorKind, Read};
+
+/// Enough for a 16 KiB block or the bitfield of a very large torrent.
+pub const DEFAULT_MAX_MESSAGE_LEN: usize = 1 << 20;
+
+/// Reads length-prefixed peer messages off a stream.
+#[derive(Debug)]
+pub struct MessageFramer<R> {
+    reader: R,
+    max_len: usize,
+}
+
+#[derive(Debug)]
+pub enum FramerError {
+    Io(io::Error),
+    /// The stream ended partway through a frame
+    UnexpectedEof,
+    /// The length prefix is over the configured maximum
+    TooLarge(usize),
+    Message(MessageError),
+}
+
+impl From<io::Error> for FramerError {
+    fn from(e: io::Error) -> Self {
+        FramerError::Io(e)
+    }
+}
+
+impl<R: Read> MessageFramer<R> {
+    pub fn new(reader: R) -> Self {
+        Self::with_max_len(reader, DEFAULT_MAX_MESSAGE_LEN)
+    }
+
+    pub fn with_max_len(reader: R, max_len: usize) -> Self {
+        Self { reader, max_len }
+    }
+
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+
+    /// The next message, or `None` if the stream ended cleanly between frames.
+    pub fn next_message(&mut self) -> Result<Option<PeerMessage>, FramerError> {
+        let mut prefix = [0; 4];
+        match self.fill(&mut prefix)? {
+            0 => return Ok(None),
+            4 => {}
+            _ => return Err(FramerError::UnexpectedEof),
+        }
+
+        let len = u32::from_be_bytes(prefix) as usize;
+        if len > self.max_len {
+            return Err(FramerError::TooLarge(len));
+        }
+
+        let mut payload = vec![0; len];
+        if self.fill(&mut payload)? != len {
+            return Err(FramerError::UnexpectedEof);
+        }
+
+        PeerMessage::from_payload(&payload)
+            .map(Some)
+            .map_err(FramerError::Message)
+    }
+
+    // Reads until `buf` is full or the stream ends, returning the bytes read
+    fn fill(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let mut filled = 0;
+        while filled < buf.len() {
+            match self.reader.read(&mut buf[filled..]) {
+                Ok(0) => break,
+                Ok(read) => filled += read,
+                Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(filled)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    // Hands out at most `step` bytes per read call
+    struct Chunked<R> {
+        inner: R,
+        step: usize,
+    }
+
+    impl<R: Read> Read for Chunked<R> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let len = buf.len().min(self.step);
+            self.inner.read(&mut buf[..len])
+        }
+    }
+
+    fn two_messages() -> Vec<u8> {
+        let piece = PeerMessage::Piece {
+            index: 1,
+            begin: 0,
+            block: vec![7; 100],
+        };
+        [PeerMessage::Have(3).encode(), piece.encode()].concat()
+    }
+
+    #[test]
+    fn framer_split_reads() {
+        for step in [1, 2, 3, 5, 7, 64, 1000] {
+            let reader = Chunked {
+                inner: Cursor::new(two_messages()),
+                step,
+            };
+            let mut framer = MessageFramer::new(reader);
+
+            assert_eq!(framer.next_message().unwrap(), Some(PeerMessage::Have(3)));
+            assert!(matches!(
+                framer.next_message().unwrap(),
+                Some(PeerMessage::Piece { index: 1, begin: 0, block }) if block == [7; 100]
+            ));
+            assert_eq!(framer.next_message().unwrap(), None);
+        }
+    }
+
+    #[test]
+    fn framer_eof_mid_frame() {
+        let mut bytes = two_messages();
+        bytes.truncate(bytes.len() - 1);
+        let mut framer = MessageFramer::new(Cursor::new(bytes));
+
+        framer.next_message().unwrap();
+        assert!(matches!(
+            framer.next_message(),
+            Err(FramerError::UnexpectedEof)
+        ));
+    }
+
+    #[test]
+    fn framer_eof_mid_prefix() {
+        let mut framer = MessageFramer::new(Cursor::new(vec![0, 0]));
+        assert!(matches!(
+            framer.next_message(),
+            Err(FramerError::UnexpectedEof)
+        ));
+    }
+
+    #[test]
+    fn framer_rejects_huge_prefix() {
+        let mut framer =
+            MessageFramer::with_max_len(Cursor::new(vec![0xff, 0xff, 0xff, 0xff]), 1024);
+        assert!(matches!(
+            framer.next_message(),
+            Err(FramerError::TooLarge(0xffff_ffff))
+        ));
+    }
+
+    #[test]
+    fn framer_keep_alive() {
+        let mut framer = MessageFramer::new(Cursor::new(vec![0, 0, 0, 0]));
+        assert_eq!(framer.next_message().unwrap(), Some(PeerMessage::KeepAlive));
+    }
+}
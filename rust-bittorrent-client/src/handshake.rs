@@ -0,0 +1,125 @@
+const PROTOCOL: &[u8; 19] = b"BitTorrent protocol";
+
+pub const HANDSHAKE_LEN: usize = 68;
+
+// Bit 20 counted from the right of the reserved bytes
+const EXTENSION_PROTOCOL_BIT: u8 = 0x10;
+
+/// The first message on a peer connection.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Handshake {
+    /// Extension flag bits, kept as received
+    pub reserved: [u8; 8],
+    pub info_hash: [u8; 20],
+    pub peer_id: [u8; 20],
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HandshakeError {
+    InvalidLength(usize),
+    InvalidPstrlen(u8),
+    InvalidProtocol,
+}
+
+impl Handshake {
+    pub fn new(info_hash: [u8; 20], peer_id: [u8; 20]) -> Self {
+        Self {
+            reserved: [0; 8],
+            info_hash,
+            peer_id,
+        }
+    }
+
+    /// Whether the extension protocol bit (BEP 10) is set.
+    pub fn supports_extensions(&self) -> bool {
+        self.reserved[5] & EXTENSION_PROTOCOL_BIT != 0
+    }
+
+    pub fn set_supports_extensions(&mut self) {
+        self.reserved[5] |= EXTENSION_PROTOCOL_BIT;
+    }
+
+    pub fn encode(&self) -> [u8; HANDSHAKE_LEN] {
+        let mut out = [0; HANDSHAKE_LEN];
+        out[0] = PROTOCOL.len() as u8;
+        out[1..20].copy_from_slice(PROTOCOL);
+        out[20..28].copy_from_slice(&self.reserved);
+        out[28..48].copy_from_slice(&self.info_hash);
+        out[48..68].copy_from_slice(&self.peer_id);
+        out
+    }
+
+    pub fn decode(bytes: &[u8]) -> Result<Handshake, HandshakeError> {
+        if bytes.len() != HANDSHAKE_LEN {
+            return Err(HandshakeError::InvalidLength(bytes.len()));
+        }
+        if bytes[0] as usize != PROTOCOL.len() {
+            return Err(HandshakeError::InvalidPstrlen(bytes[0]));
+        }
+        if &bytes[1..20] != PROTOCOL {
+            return Err(HandshakeError::InvalidProtocol);
+        }
+
+        Ok(Self {
+            reserved: bytes[20..28].try_into().unwrap(),
+            info_hash: bytes[28..48].try_into().unwrap(),
+            peer_id: bytes[48..68].try_into().unwrap(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn handshake_round_trip() {
+        let mut handshake = Handshake::new([1; 20], *b"-RS0001-abcdefghijkl");
+        handshake.reserved[5] = 0x10;
+
+        let bytes = handshake.encode();
+        assert_eq!(bytes[0], 19);
+        assert_eq!(&bytes[1..20], b"BitTorrent protocol");
+        assert_eq!(bytes[25], 0x10);
+        assert_eq!(Handshake::decode(&bytes), Ok(handshake));
+    }
+
+    #[test]
+    fn handshake_extension_bit() {
+        let mut handshake = Handshake::new([1; 20], [2; 20]);
+        assert!(!handshake.supports_extensions());
+
+        handshake.set_supports_extensions();
+        assert!(handshake.supports_extensions());
+        assert_eq!(handshake.encode()[25], 0x10);
+    }
+
+    #[test]
+    fn handshake_wrong_protocol() {
+        let mut bytes = Handshake::new([1; 20], [2; 20]).encode();
+        bytes[1..20].copy_from_slice(b"BitTorrent protocoX");
+        assert_eq!(
+            Handshake::decode(&bytes),
+            Err(HandshakeError::InvalidProtocol)
+        );
+    }
+
+    #[test]
+    fn handshake_wrong_pstrlen() {
+        let mut bytes = Handshake::new([1; 20], [2; 20]).encode();
+        bytes[0] = 18;
+        assert_eq!(
+            Handshake::decode(&bytes),
+            Err(HandshakeError::InvalidPstrlen(18))
+        );
+    }
+
+    #[test]
+    fn handshake_wrong_length() {
+        let bytes = Handshake::new([1; 20], [2; 20]).encode();
+        assert_eq!(
+            Handshake::decode(&bytes[..67]),
+            Err(HandshakeError::InvalidLength(67))
+        );
+    }
+}
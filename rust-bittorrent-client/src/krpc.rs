@@ -0,0 +1,372 @@
+use crate::{
+    dht::{encode_compact_nodes, parse_compact_nodes, NodeInfo},
+    tracker::parse_compact_peers,
+};
+use bencoding::{BencodeType, DecodeError, Encodable};
+use std::net::SocketAddrV4;
+
+/// A DHT message (BEP 5). The transaction id is opaque and echoed back as is.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Message {
+    Query {
+        transaction_id: Vec<u8>,
+        query: Query,
+    },
+    Response {
+        transaction_id: Vec<u8>,
+        response: Response,
+    },
+    Error {
+        transaction_id: Vec<u8>,
+        code: i64,
+        message: String,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Query {
+    Ping { id: [u8; 20] },
+    FindNode { id: [u8; 20], target: [u8; 20] },
+    GetPeers { id: [u8; 20], info_hash: [u8; 20] },
+}
+
+/// The `r` dictionary of a response. Responses don't name the query they
+/// answer, so every field but `id` is optional.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Response {
+    pub id: [u8; 20],
+    /// Closer nodes, from `find_node` and `get_peers`
+    pub nodes: Vec<NodeInfo>,
+    /// Peers for the info hash, from `get_peers`
+    pub values: Vec<SocketAddrV4>,
+    /// Token for a later `announce_peer`, from `get_peers`
+    pub token: Option<Vec<u8>>,
+}
+
+#[derive(Debug)]
+pub enum KrpcError {
+    Decode(DecodeError),
+    NotADictionary,
+    MissingKey(&'static str),
+    WrongType(&'static str),
+    UnknownMessageType(Vec<u8>),
+    UnknownQuery(Vec<u8>),
+}
+
+impl Response {
+    pub fn new(id: [u8; 20]) -> Self {
+        Self {
+            id,
+            nodes: Vec::new(),
+            values: Vec::new(),
+            token: None,
+        }
+    }
+}
+
+impl Message {
+    pub fn encode(&self) -> Vec<u8> {
+        let dict = match self {
+            Message::Query {
+                transaction_id,
+                query,
+            } => {
+                let (name, args) = match query {
+                    Query::Ping { id } => ("ping", BencodeType::dict().insert("id", id.to_vec())),
+                    Query::FindNode { id, target } => (
+                        "find_node",
+                        BencodeType::dict()
+                            .insert("id", id.to_vec())
+                            .insert("target", target.to_vec()),
+                    ),
+                    Query::GetPeers { id, info_hash } => (
+                        "get_peers",
+                        BencodeType::dict()
+                            .insert("id", id.to_vec())
+                            .insert("info_hash", info_hash.to_vec()),
+                    ),
+                };
+                BencodeType::dict()
+                    .insert("t", transaction_id.clone())
+                    .insert("y", "q")
+                    .insert("q", name)
+                    .insert("a", args.build())
+            }
+            Message::Response {
+                transaction_id,
+                response,
+            } => BencodeType::dict()
+                .insert("t", transaction_id.clone())
+                .insert("y", "r")
+                .insert("r", response.to_bencode()),
+            Message::Error {
+                transaction_id,
+                code,
+                message,
+            } => BencodeType::dict()
+                .insert("t", transaction_id.clone())
+                .insert("y", "e")
+                .insert(
+                    "e",
+                    vec![BencodeType::Integer(*code), message.as_str().into()],
+                ),
+        };
+
+        dict.build().encode()
+    }
+
+    pub fn decode(input: &[u8]) -> Result<Self, KrpcError> {
+        let value = bencoding::decode(input).map_err(KrpcError::Decode)?;
+        if value.as_dict().is_none() {
+            return Err(KrpcError::NotADictionary);
+        }
+
+        let transaction_id = bytes(&value, "t")?.to_vec();
+        match bytes(&value, "y")? {
+            b"q" => Ok(Message::Query {
+                transaction_id,
+                query: Query::from_bencode(bytes(&value, "q")?, required(&value, "a")?)?,
+            }),
+            b"r" => Ok(Message::Response {
+                transaction_id,
+                response: Response::from_bencode(required(&value, "r")?)?,
+            }),
+            b"e" => {
+                let error = required(&value, "e")?
+                    .as_list()
+                    .ok_or(KrpcError::WrongType("e"))?;
+                let [code, message] = error else {
+                    return Err(KrpcError::WrongType("e"));
+                };
+                Ok(Message::Error {
+                    transaction_id,
+                    code: code.as_integer().ok_or(KrpcError::WrongType("e"))?,
+                    message: message
+                        .as_bytes()
+                        .map(|bytes| String::from_utf8_lossy(bytes).into_owned())
+                        .ok_or(KrpcError::WrongType("e"))?,
+                })
+            }
+            other => Err(KrpcError::UnknownMessageType(other.to_vec())),
+        }
+    }
+}
+
+impl Query {
+    fn from_bencode(name: &[u8], args: &BencodeType) -> Result<Self, KrpcError> {
+        if args.as_dict().is_none() {
+            return Err(KrpcError::WrongType("a"));
+        }
+
+        let id = node_id(args, "id")?;
+        match name {
+            b"ping" => Ok(Query::Ping { id }),
+            b"find_node" => Ok(Query::FindNode {
+                id,
+                target: node_id(args, "target")?,
+            }),
+            b"get_peers" => Ok(Query::GetPeers {
+                id,
+                info_hash: node_id(args, "info_hash")?,
+            }),
+            other => Err(KrpcError::UnknownQuery(other.to_vec())),
+        }
+    }
+}
+
+impl Response {
+    fn to_bencode(&self) -> BencodeType {
+        let mut dict = BencodeType::dict().insert("id", self.id.to_vec());
+        if !self.nodes.is_empty() {
+            dict = dict.insert("nodes", encode_compact_nodes(&self.nodes));
+        }
+        if let Some(token) = &self.token {
+            dict = dict.insert("token", token.clone());
+        }
+        if !self.values.is_empty() {
+            let values: BencodeType = self
+                .values
+                .iter()
+                .map(|peer| {
+                    let mut compact = peer.ip().octets().to_vec();
+                    compact.extend_from_slice(&peer.port().to_be_bytes());
+                    BencodeType::from(compact)
+                })
+                .collect();
+            dict = dict.insert("values", values);
+        }
+        dict.build()
+    }
+
+    fn from_bencode(value: &BencodeType) -> Result<Self, KrpcError> {
+        if value.as_dict().is_none() {
+            return Err(KrpcError::WrongType("r"));
+        }
+
+        let nodes = match value.get("nodes") {
+            None => Vec::new(),
+            Some(nodes) => {
+                let compact = nodes.as_bytes().ok_or(KrpcError::WrongType("nodes"))?;
+                parse_compact_nodes(compact).map_err(|_| KrpcError::WrongType("nodes"))?
+            }
+        };
+
+        let values = match value.get("values") {
+            None => Vec::new(),
+            Some(values) => values
+                .iter_list()
+                .ok_or(KrpcError::WrongType("values"))?
+                .map(|peer| parse_compact_peers(peer.as_bytes()?))
+                .collect::<Option<Vec<_>>>()
+                .ok_or(KrpcError::WrongType("values"))?
+                .into_iter()
+                .flatten()
+                .collect(),
+        };
+
+        let token = value
+            .get("token")
+            .map(|token| {
+                token
+                    .as_bytes()
+                    .map(<[u8]>::to_vec)
+                    .ok_or(KrpcError::WrongType("token"))
+            })
+            .transpose()?;
+
+        Ok(Self {
+            id: node_id(value, "id")?,
+            nodes,
+            values,
+            token,
+        })
+    }
+}
+
+fn required<'a>(value: &'a BencodeType, key: &'static str) -> Result<&'a BencodeType, KrpcError> {
+    value.get(key).ok_or(KrpcError::MissingKey(key))
+}
+
+fn bytes<'a>(value: &'a BencodeType, key: &'static str) -> Result<&'a [u8], KrpcError> {
+    required(value, key)?
+        .as_bytes()
+        .ok_or(KrpcError::WrongType(key))
+}
+
+fn node_id(value: &BencodeType, key: &'static str) -> Result<[u8; 20], KrpcError> {
+    bytes(value, key)?
+        .try_into()
+        .map_err(|_| KrpcError::WrongType(key))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encode_ping_query() {
+        let message = Message::Query {
+            transaction_id: b"aa".to_vec(),
+            query: Query::Ping {
+                id: *b"abcdefghij0123456789",
+            },
+        };
+
+        let bytes = b"d1:ad2:id20:abcdefghij0123456789e1:q4:ping1:t2:aa1:y1:qe";
+        assert_eq!(message.encode(), bytes);
+        assert_eq!(Message::decode(bytes).unwrap(), message);
+    }
+
+    #[test]
+    fn decode_find_node_response() {
+        let mut nodes = vec![0x11; 20];
+        nodes.extend_from_slice(&[10, 0, 0, 1, 0x1a, 0xe1]);
+        let r = BencodeType::dict()
+            .insert("id", b"0123456789abcdefghij".to_vec())
+            .insert("nodes", nodes)
+            .build();
+        let bytes = BencodeType::dict()
+            .insert("t", vec![0xff, 0x00])
+            .insert("y", "r")
+            .insert("r", r)
+            .build()
+            .encode();
+
+        let message = Message::decode(&bytes).unwrap();
+        let Message::Response {
+            transaction_id,
+            response,
+        } = &message
+        else {
+            panic!("expected a response, got {message:?}");
+        };
+        assert_eq!(transaction_id, &[0xff, 0x00]);
+        assert_eq!(&response.id, b"0123456789abcdefghij");
+        assert_eq!(
+            response.nodes,
+            vec![NodeInfo {
+                id: [0x11; 20],
+                addr: "10.0.0.1:6881".parse().unwrap(),
+            }]
+        );
+        assert!(response.values.is_empty());
+        assert_eq!(message.encode(), bytes);
+    }
+
+    #[test]
+    fn get_peers_round_trip() {
+        let query = Message::Query {
+            transaction_id: b"t1".to_vec(),
+            query: Query::GetPeers {
+                id: [1; 20],
+                info_hash: [2; 20],
+            },
+        };
+        assert_eq!(Message::decode(&query.encode()).unwrap(), query);
+
+        let mut response = Response::new([3; 20]);
+        response.token = Some(b"secret".to_vec());
+        response.values = vec!["1.2.3.4:5".parse().unwrap(), "5.6.7.8:9".parse().unwrap()];
+        let response = Message::Response {
+            transaction_id: b"t1".to_vec(),
+            response,
+        };
+        assert_eq!(Message::decode(&response.encode()).unwrap(), response);
+    }
+
+    #[test]
+    fn error_round_trip() {
+        let message = Message::Error {
+            transaction_id: b"aa".to_vec(),
+            code: 201,
+            message: "A Generic Error Ocurred".to_string(),
+        };
+        let bytes = b"d1:eli201e23:A Generic Error Ocurrede1:t2:aa1:y1:ee";
+        assert_eq!(message.encode(), bytes);
+        assert_eq!(Message::decode(bytes).unwrap(), message);
+    }
+
+    #[test]
+    fn decode_errors() {
+        assert!(matches!(
+            Message::decode(b"d1:t2:aa1:y1:xe"),
+            Err(KrpcError::UnknownMessageType(y)) if y == b"x"
+        ));
+        assert!(matches!(
+            Message::decode(b"d1:ad2:id20:abcdefghij0123456789e1:q4:pong1:t2:aa1:y1:qe"),
+            Err(KrpcError::UnknownQuery(q)) if q == b"pong"
+        ));
+        assert!(matches!(
+            Message::decode(b"d1:ad2:id3:abce1:q4:ping1:t2:aa1:y1:qe"),
+            Err(KrpcError::WrongType("id"))
+        ));
+        assert!(matches!(
+            Message::decode(b"d1:y1:qe"),
+            Err(KrpcError::MissingKey("t"))
+        ));
+        assert!(matches!(
+            Message::decode(b"i1e"),
+            Err(KrpcError::NotADictionary)
+        ));
+    }
+}
@@ -0,0 +1,41 @@
+pub mod base32;
+mod bitfield;
+mod block_tracker;
+pub mod dht;
+mod extension;
+mod framer;
+mod handshake;
+pub mod hex;
+pub mod krpc;
+mod magnet;
+mod message;
+mod metadata;
+mod metainfo;
+pub mod peer_id;
+mod peer_state;
+mod percent;
+mod picker;
+mod resume;
+mod rng;
+mod sha1;
+mod sha256;
+mod storage;
+pub mod tracker;
+pub mod udp_tracker;
+
+pub use bitfield::{Bitfield, BitfieldError};
+pub use block_tracker::BlockTracker;
+pub use extension::{ExtensionError, ExtensionHandshake, EXTENDED_HANDSHAKE_ID};
+pub use framer::{FramerError, MessageFramer, DEFAULT_MAX_MESSAGE_LEN};
+pub use handshake::{Handshake, HandshakeError, HANDSHAKE_LEN};
+pub use magnet::{MagnetError, MagnetLink};
+pub use message::{MessageError, PeerMessage};
+pub use metadata::{MetadataAssembler, MetadataError, MetadataMessage, METADATA_PIECE_LEN};
+pub use metainfo::{FileEntry, FileMode, Info, MetaInfo, MetaInfoError, MetaVersion, BLOCK_LEN};
+pub use peer_state::PeerState;
+pub use picker::PiecePicker;
+pub use resume::{ResumeData, ResumeError};
+pub use rng::{Rng, XorShift64};
+pub use sha1::{sha1, Sha1};
+pub use sha256::{sha256, Sha256};
+pub use storage::{Storage, StorageError};
@@ -0,0 +1,130 @@
+use crate::{base32, hex, percent};
+
+/// A parsed `magnet:?xt=urn:btih:...` link.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MagnetLink {
+    pub info_hash: [u8; 20],
+    pub display_name: Option<String>,
+    pub trackers: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MagnetError {
+    NotAMagnet,
+    MissingInfoHash,
+    /// `btih` is neither 40 hex digits nor 32 base32 characters
+    InvalidInfoHash(String),
+    InvalidPercentEncoding,
+}
+
+impl MagnetLink {
+    pub fn parse(uri: &str) -> Result<Self, MagnetError> {
+        let query = uri
+            .strip_prefix("magnet:?")
+            .ok_or(MagnetError::NotAMagnet)?;
+
+        let mut info_hash = None;
+        let mut display_name = None;
+        let mut trackers = Vec::new();
+
+        for pair in query.split('&').filter(|pair| !pair.is_empty()) {
+            let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+            let value = percent::decode(value).ok_or(MagnetError::InvalidPercentEncoding)?;
+            let value = String::from_utf8_lossy(&value).into_owned();
+
+            match key {
+                "xt" if info_hash.is_none() => {
+                    if let Some(btih) = value.strip_prefix("urn:btih:") {
+                        info_hash = Some(parse_btih(btih)?);
+                    }
+                }
+                "dn" => display_name = Some(value),
+                "tr" => trackers.push(value),
+                _ => {}
+            }
+        }
+
+        Ok(Self {
+            info_hash: info_hash.ok_or(MagnetError::MissingInfoHash)?,
+            display_name,
+            trackers,
+        })
+    }
+}
+
+fn parse_btih(btih: &str) -> Result<[u8; 20], MagnetError> {
+    let bytes = match btih.len() {
+        40 => hex::decode(btih),
+        32 => base32::decode(btih),
+        _ => None,
+    };
+
+    bytes
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or_else(|| MagnetError::InvalidInfoHash(btih.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HASH: [u8; 20] = [
+        0x96, 0x70, 0xeb, 0x81, 0x86, 0x5d, 0x58, 0xa1, 0xed, 0x39, 0x7a, 0x46, 0x1f, 0x51, 0x5c,
+        0x53, 0x83, 0xd8, 0x83, 0xc6,
+    ];
+
+    #[test]
+    fn magnet_parse_hex() {
+        let magnet = MagnetLink::parse(
+            "magnet:?xt=urn:btih:9670eb81865d58a1ed397a461f515c5383d883c6\
+             &dn=archlinux-2022.11.01-x86_64.iso\
+             &tr=http%3A%2F%2Ftracker.one%2Fannounce\
+             &tr=udp%3A%2F%2Ftracker.two%3A80",
+        )
+        .unwrap();
+
+        assert_eq!(
+            magnet,
+            MagnetLink {
+                info_hash: HASH,
+                display_name: Some("archlinux-2022.11.01-x86_64.iso".to_string()),
+                trackers: vec![
+                    "http://tracker.one/announce".to_string(),
+                    "udp://tracker.two:80".to_string()
+                ],
+            }
+        );
+    }
+
+    #[test]
+    fn magnet_parse_base32() {
+        let magnet =
+            MagnetLink::parse("magnet:?xt=urn:btih:SZYOXAMGLVMKD3JZPJDB6UK4KOB5RA6G&dn=a+b")
+                .unwrap();
+        assert_eq!(magnet.info_hash, HASH);
+        assert_eq!(magnet.display_name.as_deref(), Some("a b"));
+        assert!(magnet.trackers.is_empty());
+    }
+
+    #[test]
+    fn magnet_parse_malformed() {
+        assert_eq!(
+            MagnetLink::parse("http://example.com"),
+            Err(MagnetError::NotAMagnet)
+        );
+        assert_eq!(
+            MagnetLink::parse("magnet:?dn=name"),
+            Err(MagnetError::MissingInfoHash)
+        );
+        assert_eq!(
+            MagnetLink::parse("magnet:?xt=urn:btih:1234"),
+            Err(MagnetError::InvalidInfoHash("1234".to_string()))
+        );
+        assert_eq!(
+            MagnetLink::parse(
+                "magnet:?xt=urn:btih:9670eb81865d58a1ed397a461f515c5383d883c6&dn=%G0"
+            ),
+            Err(MagnetError::InvalidPercentEncoding)
+        );
+    }
+}
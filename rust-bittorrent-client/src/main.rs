@@ -1,10 +1,182 @@
-fn main() {
-    let bytestring = std::fs::read("./archlinux-2022.11.01-x86_64.iso.torrent").unwrap();
+use bencoding::Encodable;
+use rust_bittorrent_client::{FileMode, MetaInfo, MetaInfoError};
+use std::{env, fs, process::ExitCode};
 
-    let decoded = bencoding::decode(&bytestring).unwrap();
-    println!("{:#?}", decoded);
-    let encoded = bencoding::encode(decoded);
-    println!("{:#?}", encoded);
+const USAGE: &str = "usage: rust-bittorrent-client <info|dump> <torrent> [--verify]";
 
-    assert_eq!(bytestring, encoded);
+#[derive(Debug, PartialEq, Eq)]
+enum Command {
+    Info,
+    Dump,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+struct Args {
+    command: Command,
+    path: String,
+    verify: bool,
+}
+
+fn parse_args(args: impl IntoIterator<Item = String>) -> Result<Args, String> {
+    let mut command = None;
+    let mut path = None;
+    let mut verify = false;
+
+    for arg in args {
+        match arg.as_str() {
+            "--verify" => verify = true,
+            "-h" | "--help" => return Err(USAGE.to_string()),
+            flag if flag.starts_with("--") => return Err(format!("unknown flag {flag}\n{USAGE}")),
+            "info" if command.is_none() => command = Some(Command::Info),
+            "dump" if command.is_none() => command = Some(Command::Dump),
+            _ if command.is_none() => return Err(format!("unknown command {arg}\n{USAGE}")),
+            _ if path.is_none() => path = Some(arg),
+            _ => return Err(format!("unexpected argument {arg}\n{USAGE}")),
+        }
+    }
+
+    Ok(Args {
+        command: command.ok_or(USAGE)?,
+        path: path.ok_or(USAGE)?,
+        verify,
+    })
+}
+
+fn run(args: Args) -> Result<(), String> {
+    let bytes = fs::read(&args.path).map_err(|e| format!("cannot read {}: {e}", args.path))?;
+    let decoded = bencoding::decode(&bytes).map_err(|e| format!("{}: {e}", args.path))?;
+
+    if args.verify && decoded.encode() != bytes {
+        return Err(format!(
+            "{}: re-encoding does not match the file",
+            args.path
+        ));
+    }
+
+    match args.command {
+        Command::Info => {
+            let metainfo =
+                MetaInfo::from_bytes(&bytes).map_err(|e| format!("{}: {e}", args.path))?;
+            print_info(&metainfo).map_err(|e| format!("{}: {e}", args.path))?;
+        }
+        Command::Dump => println!("{}", decoded.display_truncated(64)),
+    }
+
+    Ok(())
+}
+
+fn print_info(metainfo: &MetaInfo) -> Result<(), MetaInfoError> {
+    let info = &metainfo.info;
+    let size = info
+        .mode
+        .total_length()
+        .ok_or(MetaInfoError::LengthOverflow)?
+        .max(0) as u64;
+
+    println!("name:         {}", info.safe_name());
+    println!("size:         {} ({size} bytes)", format_bytes(size));
+    println!(
+        "piece length: {}",
+        format_bytes(info.piece_length.max(0) as u64)
+    );
+    println!("pieces:       {}", info.piece_hashes()?.len());
+    println!("info hash:    {}", metainfo.info_hash_hex());
+
+    let trackers = metainfo.all_trackers();
+    if trackers.is_empty() {
+        println!("trackers:     none");
+    } else {
+        println!("trackers:");
+        for tracker in trackers {
+            println!("  {tracker}");
+        }
+    }
+
+    if let FileMode::Multi { files } = &info.mode {
+        println!("files:");
+        for file in files {
+            println!(
+                "  {:>10}  {}",
+                format_bytes(file.length.max(0) as u64),
+                file.path.join("/")
+            );
+        }
+    }
+
+    Ok(())
+}
+
+// Binary units with one decimal, e.g. "842.0 MiB", plain bytes below 1 KiB
+fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 6] = ["KiB", "MiB", "GiB", "TiB", "PiB", "EiB"];
+
+    if bytes < 1024 {
+        return format!("{bytes} B");
+    }
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    format!("{size:.1} {}", UNITS[unit])
+}
+
+fn main() -> ExitCode {
+    match parse_args(env::args().skip(1)).and_then(run) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(message) => {
+            eprintln!("{message}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> Result<Args, String> {
+        parse_args(args.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn parse_args_commands() {
+        assert_eq!(
+            args(&["info", "a.torrent"]),
+            Ok(Args {
+                command: Command::Info,
+                path: "a.torrent".to_string(),
+                verify: false,
+            })
+        );
+        assert_eq!(
+            args(&["dump", "--verify", "a.torrent"]),
+            Ok(Args {
+                command: Command::Dump,
+                path: "a.torrent".to_string(),
+                verify: true,
+            })
+        );
+    }
+
+    #[test]
+    fn parse_args_errors() {
+        assert!(args(&[]).is_err());
+        assert!(args(&["info"]).is_err());
+        assert!(args(&["seed", "a.torrent"]).is_err());
+        assert!(args(&["info", "a.torrent", "b.torrent"]).is_err());
+        assert!(args(&["info", "--force", "a.torrent"]).is_err());
+    }
+
+    #[test]
+    fn format_bytes_boundaries() {
+        assert_eq!(format_bytes(0), "0 B");
+        assert_eq!(format_bytes(1023), "1023 B");
+        assert_eq!(format_bytes(1024), "1.0 KiB");
+        assert_eq!(format_bytes(1536), "1.5 KiB");
+        assert_eq!(format_bytes(842 * 1024 * 1024), "842.0 MiB");
+        assert_eq!(format_bytes(5 << 40), "5.0 TiB");
+        assert_eq!(format_bytes(u64::MAX), "16.0 EiB");
+    }
 }
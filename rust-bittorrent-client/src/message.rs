@@ -0,0 +1,312 @@
+const CHOKE: u8 = 0;
+const UNCHOKE: u8 = 1;
+const INTERESTED: u8 = 2;
+const NOT_INTERESTED: u8 = 3;
+const HAVE: u8 = 4;
+const BITFIELD: u8 = 5;
+const REQUEST: u8 = 6;
+const PIECE: u8 = 7;
+const CANCEL: u8 = 8;
+const EXTENDED: u8 = 20;
+
+/// A message on the peer wire after the handshake.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PeerMessage {
+    KeepAlive,
+    Choke,
+    Unchoke,
+    Interested,
+    NotInterested,
+    Have(u32),
+    Bitfield(Vec<u8>),
+    Request {
+        index: u32,
+        begin: u32,
+        length: u32,
+    },
+    Piece {
+        index: u32,
+        begin: u32,
+        block: Vec<u8>,
+    },
+    Cancel {
+        index: u32,
+        begin: u32,
+        length: u32,
+    },
+    /// An extension protocol message (BEP 10), `id` 0 being the extension handshake
+    Extended {
+        id: u8,
+        payload: Vec<u8>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MessageError {
+    /// Fewer than the 4 bytes of the length prefix
+    TooShort,
+    LengthMismatch {
+        declared: usize,
+        actual: usize,
+    },
+    UnknownId(u8),
+    /// The payload doesn't have the size its message id requires
+    InvalidPayload(u8),
+}
+
+impl PeerMessage {
+    /// The message with its 4-byte big-endian length prefix.
+    pub fn encode(&self) -> Vec<u8> {
+        let mut payload = Vec::new();
+        match self {
+            PeerMessage::KeepAlive => {}
+            PeerMessage::Choke => payload.push(CHOKE),
+            PeerMessage::Unchoke => payload.push(UNCHOKE),
+            PeerMessage::Interested => payload.push(INTERESTED),
+            PeerMessage::NotInterested => payload.push(NOT_INTERESTED),
+            PeerMessage::Have(index) => {
+                payload.push(HAVE);
+                payload.extend_from_slice(&index.to_be_bytes());
+            }
+            PeerMessage::Bitfield(bits) => {
+                payload.push(BITFIELD);
+                payload.extend_from_slice(bits);
+            }
+            PeerMessage::Request {
+                index,
+                begin,
+                length,
+            } => {
+                payload.push(REQUEST);
+                push_u32s(&mut payload, &[*index, *begin, *length]);
+            }
+            PeerMessage::Piece {
+                index,
+                begin,
+                block,
+            } => {
+                payload.push(PIECE);
+                push_u32s(&mut payload, &[*index, *begin]);
+                payload.extend_from_slice(block);
+            }
+            PeerMessage::Cancel {
+                index,
+                begin,
+                length,
+            } => {
+                payload.push(CANCEL);
+                push_u32s(&mut payload, &[*index, *begin, *length]);
+            }
+            PeerMessage::Extended { id, payload: body } => {
+                payload.extend_from_slice(&[EXTENDED, *id]);
+                payload.extend_from_slice(body);
+            }
+        }
+
+        let mut out = Vec::with_capacity(4 + payload.len());
+        out.extend_from_slice(&(payload.len() as u32).to_be_bytes());
+        out.extend_from_slice(&payload);
+        out
+    }
+
+    /// Decodes a whole frame, length prefix included.
+    pub fn decode(frame: &[u8]) -> Result<PeerMessage, MessageError> {
+        let (prefix, payload) = frame
+            .split_first_chunk::<4>()
+            .ok_or(MessageError::TooShort)?;
+        let declared = u32::from_be_bytes(*prefix) as usize;
+        if declared != payload.len() {
+            return Err(MessageError::LengthMismatch {
+                declared,
+                actual: payload.len(),
+            });
+        }
+
+        Self::from_payload(payload)
+    }
+
+    /// Decodes the bytes following the length prefix.
+    pub fn from_payload(payload: &[u8]) -> Result<PeerMessage, MessageError> {
+        let Some((&id, body)) = payload.split_first() else {
+            return Ok(PeerMessage::KeepAlive);
+        };
+
+        let fixed = |len: usize| {
+            if body.len() == len {
+                Ok(())
+            } else {
+                Err(MessageError::InvalidPayload(id))
+            }
+        };
+
+        let message = match id {
+            CHOKE => fixed(0).map(|_| PeerMessage::Choke)?,
+            UNCHOKE => fixed(0).map(|_| PeerMessage::Unchoke)?,
+            INTERESTED => fixed(0).map(|_| PeerMessage::Interested)?,
+            NOT_INTERESTED => fixed(0).map(|_| PeerMessage::NotInterested)?,
+            HAVE => {
+                fixed(4)?;
+                PeerMessage::Have(read_u32(body, 0))
+            }
+            BITFIELD => PeerMessage::Bitfield(body.to_vec()),
+            REQUEST => {
+                fixed(12)?;
+                PeerMessage::Request {
+                    index: read_u32(body, 0),
+                    begin: read_u32(body, 4),
+                    length: read_u32(body, 8),
+                }
+            }
+            PIECE => {
+                if body.len() < 8 {
+                    return Err(MessageError::InvalidPayload(id));
+                }
+                PeerMessage::Piece {
+                    index: read_u32(body, 0),
+                    begin: read_u32(body, 4),
+                    block: body[8..].to_vec(),
+                }
+            }
+            CANCEL => {
+                fixed(12)?;
+                PeerMessage::Cancel {
+                    index: read_u32(body, 0),
+                    begin: read_u32(body, 4),
+                    length: read_u32(body, 8),
+                }
+            }
+            EXTENDED => {
+                let (&extended_id, extended) =
+                    body.split_first().ok_or(MessageError::InvalidPayload(id))?;
+                PeerMessage::Extended {
+                    id: extended_id,
+                    payload: extended.to_vec(),
+                }
+            }
+            _ => return Err(MessageError::UnknownId(id)),
+        };
+
+        Ok(message)
+    }
+}
+
+fn push_u32s(out: &mut Vec<u8>, values: &[u32]) {
+    for value in values {
+        out.extend_from_slice(&value.to_be_bytes());
+    }
+}
+
+fn read_u32(bytes: &[u8], at: usize) -> u32 {
+    u32::from_be_bytes(bytes[at..at + 4].try_into().unwrap())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_layout(message: PeerMessage, bytes: &[u8]) {
+        assert_eq!(message.encode(), bytes);
+        assert_eq!(PeerMessage::decode(bytes), Ok(message));
+    }
+
+    #[test]
+    fn message_keep_alive() {
+        assert_layout(PeerMessage::KeepAlive, &[0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn message_no_payload() {
+        assert_layout(PeerMessage::Choke, &[0, 0, 0, 1, 0]);
+        assert_layout(PeerMessage::Unchoke, &[0, 0, 0, 1, 1]);
+        assert_layout(PeerMessage::Interested, &[0, 0, 0, 1, 2]);
+        assert_layout(PeerMessage::NotInterested, &[0, 0, 0, 1, 3]);
+    }
+
+    #[test]
+    fn message_have() {
+        assert_layout(PeerMessage::Have(0x01020304), &[0, 0, 0, 5, 4, 1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn message_bitfield() {
+        assert_layout(
+            PeerMessage::Bitfield(vec![0b1010_0000, 0xff]),
+            &[0, 0, 0, 3, 5, 0b1010_0000, 0xff],
+        );
+    }
+
+    #[test]
+    fn message_request() {
+        assert_layout(
+            PeerMessage::Request {
+                index: 1,
+                begin: 0x4000,
+                length: 0x4000,
+            },
+            &[0, 0, 0, 13, 6, 0, 0, 0, 1, 0, 0, 0x40, 0, 0, 0, 0x40, 0],
+        );
+    }
+
+    #[test]
+    fn message_piece() {
+        assert_layout(
+            PeerMessage::Piece {
+                index: 2,
+                begin: 0x10,
+                block: b"abc".to_vec(),
+            },
+            &[0, 0, 0, 12, 7, 0, 0, 0, 2, 0, 0, 0, 0x10, b'a', b'b', b'c'],
+        );
+    }
+
+    #[test]
+    fn message_cancel() {
+        assert_layout(
+            PeerMessage::Cancel {
+                index: 1,
+                begin: 2,
+                length: 3,
+            },
+            &[0, 0, 0, 13, 8, 0, 0, 0, 1, 0, 0, 0, 2, 0, 0, 0, 3],
+        );
+    }
+
+    #[test]
+    fn message_extended() {
+        assert_layout(
+            PeerMessage::Extended {
+                id: 0,
+                payload: b"de".to_vec(),
+            },
+            &[0, 0, 0, 4, 20, 0, b'd', b'e'],
+        );
+        assert_eq!(
+            PeerMessage::decode(&[0, 0, 0, 1, 20]),
+            Err(MessageError::InvalidPayload(20))
+        );
+    }
+
+    #[test]
+    fn message_decode_errors() {
+        assert_eq!(PeerMessage::decode(&[0, 0]), Err(MessageError::TooShort));
+        assert_eq!(
+            PeerMessage::decode(&[0, 0, 0, 2, 0]),
+            Err(MessageError::LengthMismatch {
+                declared: 2,
+                actual: 1
+            })
+        );
+        assert_eq!(
+            PeerMessage::decode(&[0, 0, 0, 1, 42]),
+            Err(MessageError::UnknownId(42))
+        );
+        assert_eq!(
+            PeerMessage::decode(&[0, 0, 0, 2, 4, 0]),
+            Err(MessageError::InvalidPayload(4))
+        );
+        assert_eq!(
+            PeerMessage::decode(&[0, 0, 0, 2, 0, 0]),
+            Err(MessageError::InvalidPayload(0))
+        );
+    }
+}
@@ -0,0 +1,337 @@
+use crate::sha1::sha1;
+use bencoding::{BencodeType, DecodeError, Encodable};
+
+/// Size of every metadata piece but the last (BEP 9).
+pub const METADATA_PIECE_LEN: usize = 16384;
+
+// Larger `metadata_size` values are refused rather than allocated
+const MAX_METADATA_SIZE: usize = 16 * 1024 * 1024;
+
+const REQUEST: i64 = 0;
+const DATA: i64 = 1;
+const REJECT: i64 = 2;
+
+/// A `ut_metadata` message, the payload of an extended message tagged with
+/// the id the peer chose for `ut_metadata`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MetadataMessage {
+    Request {
+        piece: u32,
+    },
+    /// `data` follows the bencoded dictionary on the wire
+    Data {
+        piece: u32,
+        total_size: u64,
+        data: Vec<u8>,
+    },
+    Reject {
+        piece: u32,
+    },
+}
+
+#[derive(Debug)]
+pub enum MetadataError {
+    Decode(DecodeError),
+    NotADictionary,
+    MissingKey(&'static str),
+    WrongType(&'static str),
+    UnknownMessageType(i64),
+    /// Bytes after the dictionary of a message other than `data`
+    TrailingData,
+    /// `metadata_size` is zero or implausibly large
+    InvalidTotalSize(u64),
+    PieceOutOfRange {
+        piece: u32,
+        count: usize,
+    },
+    WrongPieceLength {
+        piece: u32,
+        expected: usize,
+        actual: usize,
+    },
+    /// Pieces are still missing, the first of them is given
+    Incomplete(u32),
+    /// The reassembled metadata doesn't hash to the expected info hash
+    HashMismatch,
+}
+
+impl MetadataMessage {
+    pub fn encode(&self) -> Vec<u8> {
+        let (msg_type, piece) = match self {
+            MetadataMessage::Request { piece } => (REQUEST, piece),
+            MetadataMessage::Data { piece, .. } => (DATA, piece),
+            MetadataMessage::Reject { piece } => (REJECT, piece),
+        };
+
+        let mut dict = BencodeType::dict()
+            .insert("msg_type", msg_type)
+            .insert("piece", *piece as i64);
+        if let MetadataMessage::Data { total_size, .. } = self {
+            dict = dict.insert("total_size", *total_size as i64);
+        }
+
+        let mut out = dict.build().encode();
+        if let MetadataMessage::Data { data, .. } = self {
+            out.extend_from_slice(data);
+        }
+        out
+    }
+
+    pub fn decode(payload: &[u8]) -> Result<Self, MetadataError> {
+        let (value, consumed) = bencoding::decode_prefix(payload).map_err(MetadataError::Decode)?;
+        if value.as_dict().is_none() {
+            return Err(MetadataError::NotADictionary);
+        }
+        let rest = &payload[consumed..];
+
+        let piece = u32::try_from(integer(&value, "piece")?)
+            .map_err(|_| MetadataError::WrongType("piece"))?;
+        let message = match integer(&value, "msg_type")? {
+            REQUEST => MetadataMessage::Request { piece },
+            DATA => {
+                let total_size = u64::try_from(integer(&value, "total_size")?)
+                    .map_err(|_| MetadataError::WrongType("total_size"))?;
+                return Ok(MetadataMessage::Data {
+                    piece,
+                    total_size,
+                    data: rest.to_vec(),
+                });
+            }
+            REJECT => MetadataMessage::Reject { piece },
+            other => return Err(MetadataError::UnknownMessageType(other)),
+        };
+
+        if !rest.is_empty() {
+            return Err(MetadataError::TrailingData);
+        }
+        Ok(message)
+    }
+}
+
+fn integer(value: &BencodeType, key: &'static str) -> Result<i64, MetadataError> {
+    value
+        .get(key)
+        .ok_or(MetadataError::MissingKey(key))?
+        .as_integer()
+        .ok_or(MetadataError::WrongType(key))
+}
+
+/// Collects metadata pieces received from peers into the bencoded `info`
+/// dictionary of a torrent.
+#[derive(Debug, Clone)]
+pub struct MetadataAssembler {
+    info_hash: [u8; 20],
+    total_size: usize,
+    pieces: Vec<Option<Vec<u8>>>,
+}
+
+impl MetadataAssembler {
+    /// `total_size` is the `metadata_size` from the peer's extension handshake.
+    pub fn new(info_hash: [u8; 20], total_size: u64) -> Result<Self, MetadataError> {
+        let size = usize::try_from(total_size)
+            .ok()
+            .filter(|&size| size > 0 && size <= MAX_METADATA_SIZE)
+            .ok_or(MetadataError::InvalidTotalSize(total_size))?;
+
+        Ok(Self {
+            info_hash,
+            total_size: size,
+            pieces: vec![None; size.div_ceil(METADATA_PIECE_LEN)],
+        })
+    }
+
+    pub fn piece_count(&self) -> usize {
+        self.pieces.len()
+    }
+
+    /// The lowest piece not yet received, to request next.
+    pub fn next_missing(&self) -> Option<u32> {
+        self.pieces
+            .iter()
+            .position(Option::is_none)
+            .map(|piece| piece as u32)
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.next_missing().is_none()
+    }
+
+    /// Stores a received piece, replacing any earlier copy of it.
+    pub fn add_piece(&mut self, piece: u32, data: Vec<u8>) -> Result<(), MetadataError> {
+        let count = self.pieces.len();
+        let index = piece as usize;
+        if index >= count {
+            return Err(MetadataError::PieceOutOfRange { piece, count });
+        }
+
+        let expected = if index + 1 == count {
+            self.total_size - index * METADATA_PIECE_LEN
+        } else {
+            METADATA_PIECE_LEN
+        };
+        if data.len() != expected {
+            return Err(MetadataError::WrongPieceLength {
+                piece,
+                expected,
+                actual: data.len(),
+            });
+        }
+
+        self.pieces[index] = Some(data);
+        Ok(())
+    }
+
+    /// The bencoded `info` dictionary, once every piece is in and it hashes
+    /// to the expected info hash.
+    pub fn finish(&self) -> Result<Vec<u8>, MetadataError> {
+        if let Some(missing) = self.next_missing() {
+            return Err(MetadataError::Incomplete(missing));
+        }
+
+        let mut metadata = Vec::with_capacity(self.total_size);
+        for piece in self.pieces.iter().flatten() {
+            metadata.extend_from_slice(piece);
+        }
+
+        if sha1(&metadata) != self.info_hash {
+            return Err(MetadataError::HashMismatch);
+        }
+        Ok(metadata)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn message_request() {
+        let message = MetadataMessage::Request { piece: 0 };
+        let bytes = b"d8:msg_typei0e5:piecei0ee";
+        assert_eq!(message.encode(), bytes);
+        assert_eq!(MetadataMessage::decode(bytes).unwrap(), message);
+    }
+
+    #[test]
+    fn message_reject() {
+        let message = MetadataMessage::Reject { piece: 3 };
+        let bytes = b"d8:msg_typei2e5:piecei3ee";
+        assert_eq!(message.encode(), bytes);
+        assert_eq!(MetadataMessage::decode(bytes).unwrap(), message);
+    }
+
+    #[test]
+    fn message_data_carries_trailing_bytes() {
+        let message = MetadataMessage::Data {
+            piece: 1,
+            total_size: 16390,
+            data: b"d4:name".to_vec(),
+        };
+        let bytes = b"d8:msg_typei1e5:piecei1e10:total_sizei16390eed4:name";
+        assert_eq!(message.encode(), bytes);
+        assert_eq!(MetadataMessage::decode(bytes).unwrap(), message);
+    }
+
+    #[test]
+    fn message_decode_errors() {
+        assert!(matches!(
+            MetadataMessage::decode(b"d8:msg_typei7e5:piecei0ee"),
+            Err(MetadataError::UnknownMessageType(7))
+        ));
+        assert!(matches!(
+            MetadataMessage::decode(b"d8:msg_typei0ee"),
+            Err(MetadataError::MissingKey("piece"))
+        ));
+        assert!(matches!(
+            MetadataMessage::decode(b"d8:msg_typei0e5:piecei-1ee"),
+            Err(MetadataError::WrongType("piece"))
+        ));
+        assert!(matches!(
+            MetadataMessage::decode(b"d8:msg_typei0e5:piecei0eexx"),
+            Err(MetadataError::TrailingData)
+        ));
+        assert!(matches!(
+            MetadataMessage::decode(b"d8:msg_type"),
+            Err(MetadataError::Decode(_))
+        ));
+    }
+
+    fn sample_metadata() -> Vec<u8> {
+        BencodeType::dict()
+            .insert("length", 1)
+            .insert("name", "a".repeat(20000))
+            .insert("piece length", 16384)
+            .insert("pieces", vec![0xab; 20])
+            .build()
+            .encode()
+    }
+
+    #[test]
+    fn assemble_pieces_in_any_order() {
+        let metadata = sample_metadata();
+        let mut assembler = MetadataAssembler::new(sha1(&metadata), metadata.len() as u64).unwrap();
+        assert_eq!(assembler.piece_count(), 2);
+
+        let (first, second) = metadata.split_at(METADATA_PIECE_LEN);
+        assembler.add_piece(1, second.to_vec()).unwrap();
+        assert_eq!(assembler.next_missing(), Some(0));
+        assert!(matches!(
+            assembler.finish(),
+            Err(MetadataError::Incomplete(0))
+        ));
+
+        assembler.add_piece(0, first.to_vec()).unwrap();
+        assert!(assembler.is_complete());
+        assert_eq!(assembler.finish().unwrap(), metadata);
+    }
+
+    #[test]
+    fn assemble_hash_mismatch() {
+        let metadata = sample_metadata();
+        let mut assembler = MetadataAssembler::new([0; 20], metadata.len() as u64).unwrap();
+        for (piece, chunk) in metadata.chunks(METADATA_PIECE_LEN).enumerate() {
+            assembler.add_piece(piece as u32, chunk.to_vec()).unwrap();
+        }
+        assert!(matches!(
+            assembler.finish(),
+            Err(MetadataError::HashMismatch)
+        ));
+    }
+
+    #[test]
+    fn assemble_rejects_bad_pieces() {
+        let mut assembler = MetadataAssembler::new([0; 20], 20000).unwrap();
+        assert!(matches!(
+            assembler.add_piece(2, vec![0; 10]),
+            Err(MetadataError::PieceOutOfRange { piece: 2, count: 2 })
+        ));
+        assert!(matches!(
+            assembler.add_piece(0, vec![0; 10]),
+            Err(MetadataError::WrongPieceLength {
+                piece: 0,
+                expected: 16384,
+                actual: 10
+            })
+        ));
+        assert!(matches!(
+            assembler.add_piece(1, vec![0; 16384]),
+            Err(MetadataError::WrongPieceLength {
+                piece: 1,
+                expected: 3616,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn assembler_total_size_bounds() {
+        assert!(matches!(
+            MetadataAssembler::new([0; 20], 0),
+            Err(MetadataError::InvalidTotalSize(0))
+        ));
+        assert!(matches!(
+            MetadataAssembler::new([0; 20], u64::MAX),
+            Err(MetadataError::InvalidTotalSize(_))
+        ));
+    }
+}
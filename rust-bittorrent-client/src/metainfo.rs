@@ -0,0 +1,1435 @@
+use crate::{base32, hex, percent, sha1::sha1, sha256::sha256};
+use bencoding::{BencodeType, DecodeError, Encodable};
+use std::{
+    collections::BTreeMap,
+    fmt, fs,
+    io::{self, Read},
+    ops::Range,
+    path::Path,
+};
+
+type Dict = BTreeMap<Vec<u8>, BencodeType>;
+
+/// Size of the blocks pieces are requested in, only the last block of a piece
+/// may be shorter.
+pub const BLOCK_LEN: u32 = 16384;
+
+/// The contents of a `.torrent` file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MetaInfo {
+    pub announce: Option<String>,
+    pub info: Info,
+    pub creation_date: Option<i64>,
+    pub comment: Option<String>,
+    pub created_by: Option<String>,
+    announce_list: Option<Vec<Vec<String>>>,
+    url_list: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Info {
+    pub piece_length: i64,
+    pub pieces: Vec<u8>,
+    pub mode: FileMode,
+    /// Raw bytes, use `name` or `safe_name`
+    name: Vec<u8>,
+    private: bool,
+    info_hash: [u8; 20],
+    version: MetaVersion,
+    /// SHA-256 of the `info` dictionary, only for v2 and hybrid torrents
+    info_hash_v2: Option<[u8; 32]>,
+}
+
+/// Which BitTorrent protocol versions a torrent's info dictionary describes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MetaVersion {
+    /// SHA-1 `pieces` with `length` or `files`
+    V1,
+    /// `meta version` 2 with a `file tree` (BEP 52)
+    V2,
+    /// Both of the above, readable by v1 and v2 clients
+    Hybrid,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FileMode {
+    Single { length: i64 },
+    Multi { files: Vec<FileEntry> },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileEntry {
+    pub length: i64,
+    pub path: Vec<String>,
+}
+
+#[derive(Debug)]
+pub enum MetaInfoError {
+    Io(io::Error),
+    Decode(DecodeError),
+    NotADictionary,
+    MissingKey(&'static str),
+    WrongType {
+        key: &'static str,
+        expected: &'static str,
+        /// `BencodeType::type_name` of the value found
+        found: &'static str,
+    },
+    /// Right type, but unusable, e.g. a name that isn't UTF-8
+    InvalidValue {
+        key: &'static str,
+        reason: &'static str,
+    },
+    /// The info dictionary must have exactly one of `length` and `files`
+    AmbiguousFileMode,
+    /// `pieces` is not a whole number of 20-byte hashes
+    InvalidPiecesLength(usize),
+    NonPositivePieceLength(i64),
+    NegativeLength(i64),
+    /// The file lengths add up to more than `i64::MAX`
+    LengthOverflow,
+    PieceCountMismatch {
+        expected: u64,
+        actual: usize,
+    },
+    PieceIndexOutOfRange {
+        index: usize,
+        count: usize,
+    },
+}
+
+impl fmt::Display for MetaInfoError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "cannot read torrent: {e}"),
+            Self::Decode(e) => write!(f, "{e}"),
+            Self::NotADictionary => write!(f, "torrent is not a dictionary"),
+            Self::MissingKey(key) => write!(f, "missing key {key:?}"),
+            Self::WrongType {
+                key,
+                expected,
+                found,
+            } => write!(f, "{key:?}: expected {expected}, found {found}"),
+            Self::InvalidValue { key, reason } => write!(f, "{key:?}: {reason}"),
+            Self::AmbiguousFileMode => {
+                write!(f, "info must have exactly one of \"length\" and \"files\"")
+            }
+            Self::InvalidPiecesLength(len) => {
+                write!(f, "pieces length {len} is not a multiple of 20")
+            }
+            Self::NonPositivePieceLength(len) => write!(f, "piece length {len} is not positive"),
+            Self::NegativeLength(len) => write!(f, "length {len} is negative"),
+            Self::LengthOverflow => write!(f, "total length overflows"),
+            Self::PieceCountMismatch { expected, actual } => {
+                write!(f, "expected {expected} piece hashes, found {actual}")
+            }
+            Self::PieceIndexOutOfRange { index, count } => {
+                write!(f, "piece index {index} out of range for {count} pieces")
+            }
+        }
+    }
+}
+
+impl std::error::Error for MetaInfoError {}
+
+impl MetaInfo {
+    /// Parses a decoded torrent. The info hash is taken over the re-encoded
+    /// `info` dictionary, use `from_bytes` to hash the original bytes instead.
+    pub fn from_bencode(value: BencodeType) -> Result<Self, MetaInfoError> {
+        let info_bytes = value.get("info").map(Encodable::encode).unwrap_or_default();
+        Self::parse(value, &info_bytes)
+    }
+
+    pub fn from_bytes(input: &[u8]) -> Result<Self, MetaInfoError> {
+        let (value, spans) = bencoding::decode_with_spans(input).map_err(MetaInfoError::Decode)?;
+        let info_bytes = spans
+            .get(&[b"info"])
+            .map(|span| &input[span])
+            .unwrap_or_default();
+        Self::parse(value, info_bytes)
+    }
+
+    /// Reads and parses a `.torrent` file, see `from_bytes`.
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, MetaInfoError> {
+        let bytes = fs::read(path).map_err(MetaInfoError::Io)?;
+        Self::from_bytes(&bytes)
+    }
+
+    /// Reads `reader` to the end and parses the result, see `from_bytes`.
+    pub fn from_reader<R: Read>(mut reader: R) -> Result<Self, MetaInfoError> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).map_err(MetaInfoError::Io)?;
+        Self::from_bytes(&bytes)
+    }
+
+    fn parse(value: BencodeType, info_bytes: &[u8]) -> Result<Self, MetaInfoError> {
+        let mut dict = value
+            .into_dict()
+            .map_err(|_| MetaInfoError::NotADictionary)?;
+
+        let info = Info::parse(required(&mut dict, "info")?, info_bytes)?;
+
+        Ok(Self {
+            announce: optional(&mut dict, "announce", into_string)?,
+            info,
+            creation_date: optional(&mut dict, "creation date", into_integer)?,
+            comment: optional(&mut dict, "comment", into_string)?,
+            created_by: optional(&mut dict, "created by", into_string)?,
+            announce_list: optional(&mut dict, "announce-list", into_tiers)?,
+            url_list: optional(&mut dict, "url-list", into_url_list)?.unwrap_or_default(),
+        })
+    }
+
+    /// SHA-1 of the bencoded `info` dictionary.
+    pub fn info_hash(&self) -> [u8; 20] {
+        self.info.info_hash
+    }
+
+    /// SHA-256 of the bencoded `info` dictionary, `None` for v1-only torrents.
+    pub fn info_hash_v2(&self) -> Option<[u8; 32]> {
+        self.info.info_hash_v2
+    }
+
+    pub fn version(&self) -> MetaVersion {
+        self.info.version
+    }
+
+    pub fn info_hash_hex(&self) -> String {
+        hex::encode(&self.info.info_hash)
+    }
+
+    /// The info hash as 32 base32 characters, the other `btih` form in magnet links.
+    pub fn info_hash_base32(&self) -> String {
+        base32::encode(&self.info.info_hash)
+    }
+
+    /// The info hash with every byte percent-encoded, for HTTP tracker requests.
+    pub fn info_hash_urlencoded(&self) -> String {
+        percent::encode_all(&self.info.info_hash)
+    }
+
+    /// Checks that the piece hashes cover exactly the declared file lengths.
+    pub fn validate(&self) -> Result<(), MetaInfoError> {
+        let info = &self.info;
+        if info.piece_length <= 0 {
+            return Err(MetaInfoError::NonPositivePieceLength(info.piece_length));
+        }
+        if let FileMode::Multi { files } = &info.mode {
+            if let Some(file) = files.iter().find(|file| file.length < 0) {
+                return Err(MetaInfoError::NegativeLength(file.length));
+            }
+        }
+
+        let total_length = info
+            .mode
+            .total_length()
+            .ok_or(MetaInfoError::LengthOverflow)?;
+        if total_length < 0 {
+            return Err(MetaInfoError::NegativeLength(total_length));
+        }
+
+        let expected = (total_length as u64).div_ceil(info.piece_length as u64);
+        let actual = info.piece_hashes()?.len();
+        if expected != actual as u64 {
+            return Err(MetaInfoError::PieceCountMismatch { expected, actual });
+        }
+
+        Ok(())
+    }
+
+    /// Whether `data` hashes to the expected SHA-1 of piece `index`.
+    pub fn verify_piece(&self, index: usize, data: &[u8]) -> Result<bool, MetaInfoError> {
+        let hashes = &self.info.pieces;
+        if !hashes.len().is_multiple_of(20) {
+            return Err(MetaInfoError::InvalidPiecesLength(hashes.len()));
+        }
+
+        let expected =
+            hashes
+                .chunks_exact(20)
+                .nth(index)
+                .ok_or(MetaInfoError::PieceIndexOutOfRange {
+                    index,
+                    count: hashes.len() / 20,
+                })?;
+
+        Ok(sha1(data) == expected)
+    }
+
+    /// A `magnet:` link carrying the info hash, name and every tracker.
+    pub fn to_magnet(&self) -> String {
+        let mut magnet = format!("magnet:?xt=urn:btih:{}", self.info_hash_hex());
+        magnet.push_str("&dn=");
+        magnet.push_str(&percent::encode(&self.info.name));
+
+        for tracker in self.all_trackers() {
+            magnet.push_str("&tr=");
+            magnet.push_str(&percent::encode(tracker.as_bytes()));
+        }
+
+        magnet
+    }
+
+    /// Tracker tiers from `announce-list`, or a single tier holding `announce`
+    /// when the torrent has no list.
+    pub fn announce_list(&self) -> Option<Vec<Vec<String>>> {
+        match (&self.announce_list, &self.announce) {
+            (Some(tiers), _) => Some(tiers.clone()),
+            (None, Some(announce)) => Some(vec![vec![announce.clone()]]),
+            (None, None) => None,
+        }
+    }
+
+    /// Every tracker from `announce` and all `announce-list` tiers, in that
+    /// order, without duplicates or empty URLs.
+    pub fn all_trackers(&self) -> Vec<String> {
+        let tiers = self.announce_list.iter().flatten().flatten();
+        let mut trackers: Vec<String> = Vec::new();
+        for tracker in self.announce.iter().chain(tiers) {
+            if !tracker.is_empty() && !trackers.contains(tracker) {
+                trackers.push(tracker.clone());
+            }
+        }
+        trackers
+    }
+
+    /// HTTP mirrors of the content from `url-list` (BEP 19).
+    pub fn web_seeds(&self) -> Vec<String> {
+        self.url_list.clone()
+    }
+
+    /// Indices of the pieces holding bytes of file `file_index`, the first and
+    /// last of which may be shared with neighbouring files. Empty for an empty
+    /// file, `None` if there is no such file.
+    pub fn file_piece_range(&self, file_index: usize) -> Option<Range<usize>> {
+        let info = &self.info;
+        if info.piece_length <= 0 {
+            return None;
+        }
+
+        let (offset, length) = match &info.mode {
+            FileMode::Single { length } if file_index == 0 => (0, *length),
+            FileMode::Single { .. } => return None,
+            FileMode::Multi { files } => {
+                let file = files.get(file_index)?;
+                let offset = files[..file_index]
+                    .iter()
+                    .try_fold(0i64, |offset, file| offset.checked_add(file.length))?;
+                (offset, file.length)
+            }
+        };
+        if offset < 0 || length < 0 {
+            return None;
+        }
+
+        let piece_length = info.piece_length as u64;
+        let first = offset as u64 / piece_length;
+        if length == 0 {
+            return Some(first as usize..first as usize);
+        }
+        let last = offset.checked_add(length - 1)? as u64 / piece_length;
+        Some(first as usize..last as usize + 1)
+    }
+}
+
+impl Info {
+    /// Parses an `info` dictionary on its own, hashing its re-encoding.
+    pub fn from_bencode(value: BencodeType) -> Result<Self, MetaInfoError> {
+        let info_bytes = value.encode();
+        Self::parse(value, &info_bytes)
+    }
+
+    fn parse(value: BencodeType, info_bytes: &[u8]) -> Result<Self, MetaInfoError> {
+        let mut dict = value
+            .into_dict()
+            .map_err(|other| wrong_type("info", "dictionary", &other))?;
+
+        let name = into_bytes(required(&mut dict, "name")?, "name")?;
+        let meta_version = optional(&mut dict, "meta version", into_integer)?;
+        let file_tree = optional(&mut dict, "file tree", into_file_tree)?;
+        let file_tree = file_tree.filter(|_| meta_version == Some(2));
+
+        let pieces = optional(&mut dict, "pieces", into_bytes)?;
+        let v1_mode = FileMode::from_info(&mut dict)?;
+        let (mode, version) = match (pieces.is_some(), v1_mode, file_tree) {
+            (true, Some(mode), None) => (mode, MetaVersion::V1),
+            (true, Some(mode), Some(_)) => (mode, MetaVersion::Hybrid),
+            (_, None, Some(files)) => (FileMode::from_file_tree(files, &name), MetaVersion::V2),
+            (false, Some(_), _) => return Err(MetaInfoError::MissingKey("pieces")),
+            (_, None, None) => return Err(MetaInfoError::AmbiguousFileMode),
+        };
+
+        Ok(Self {
+            name,
+            piece_length: into_integer(required(&mut dict, "piece length")?, "piece length")?,
+            pieces: pieces.unwrap_or_default(),
+            mode,
+            // Anything but an explicit 0 is treated as private
+            private: !matches!(
+                dict.remove(b"private".as_slice()),
+                None | Some(BencodeType::Integer(0))
+            ),
+            info_hash: sha1(info_bytes),
+            version,
+            info_hash_v2: (version != MetaVersion::V1).then(|| sha256(info_bytes)),
+        })
+    }
+
+    /// The suggested file or directory name, `None` if it isn't UTF-8.
+    pub fn name(&self) -> Option<&str> {
+        std::str::from_utf8(&self.name).ok()
+    }
+
+    /// `name` made safe to use as a single path component: path separators
+    /// and control characters are replaced with `_`. Falls back to the hex
+    /// info hash when the name is not UTF-8, empty or made of dots only.
+    pub fn safe_name(&self) -> String {
+        let sanitized: Option<String> = self.name().map(|name| {
+            name.chars()
+                .map(|c| {
+                    if c == '/' || c == '\\' || c.is_control() {
+                        '_'
+                    } else {
+                        c
+                    }
+                })
+                .collect()
+        });
+
+        match sanitized {
+            Some(name) if !name.trim().trim_matches('.').is_empty() => name,
+            _ => hex::encode(&self.info_hash),
+        }
+    }
+
+    /// Whether `private` is set (BEP 27), which rules out DHT and PEX.
+    pub fn is_private(&self) -> bool {
+        self.private
+    }
+
+    /// `(begin, length)` of every block of piece `index`, empty when the index
+    /// is out of range or the piece is too long to address with a `u32`.
+    pub fn blocks_for_piece(&self, index: usize) -> Vec<(u32, u32)> {
+        let Some(total_length) = self.mode.total_length() else {
+            return Vec::new();
+        };
+        if self.piece_length <= 0 || total_length <= 0 {
+            return Vec::new();
+        }
+
+        let piece_length = self.piece_length as u64;
+        let Some(start) = (index as u64).checked_mul(piece_length) else {
+            return Vec::new();
+        };
+        let Some(remaining) = (total_length as u64).checked_sub(start).filter(|&r| r > 0) else {
+            return Vec::new();
+        };
+        let Ok(len) = u32::try_from(piece_length.min(remaining)) else {
+            return Vec::new();
+        };
+
+        (0..len)
+            .step_by(BLOCK_LEN as usize)
+            .map(|begin| (begin, BLOCK_LEN.min(len - begin)))
+            .collect()
+    }
+
+    pub fn piece_hashes(&self) -> Result<Vec<[u8; 20]>, MetaInfoError> {
+        if !self.pieces.len().is_multiple_of(20) {
+            return Err(MetaInfoError::InvalidPiecesLength(self.pieces.len()));
+        }
+
+        Ok(self
+            .pieces
+            .chunks_exact(20)
+            .map(|chunk| chunk.try_into().unwrap())
+            .collect())
+    }
+}
+
+impl FileMode {
+    /// Sum of the file lengths, `None` if it overflows.
+    pub fn total_length(&self) -> Option<i64> {
+        match self {
+            FileMode::Single { length } => Some(*length),
+            FileMode::Multi { files } => files
+                .iter()
+                .try_fold(0i64, |total, file| total.checked_add(file.length)),
+        }
+    }
+
+    // `None` when the info dictionary has neither `length` nor `files`
+    fn from_info(dict: &mut Dict) -> Result<Option<Self>, MetaInfoError> {
+        let length = optional(dict, "length", into_integer)?;
+        let files = optional(dict, "files", into_files)?;
+
+        match (length, files) {
+            (Some(length), None) => Ok(Some(FileMode::Single { length })),
+            (None, Some(files)) => Ok(Some(FileMode::Multi { files })),
+            (None, None) => Ok(None),
+            (Some(_), Some(_)) => Err(MetaInfoError::AmbiguousFileMode),
+        }
+    }
+
+    // A v2 file tree holding just `name` is a single-file torrent
+    fn from_file_tree(files: Vec<FileEntry>, name: &[u8]) -> Self {
+        match files.as_slice() {
+            [file] if file.path.len() == 1 && file.path[0].as_bytes() == name => FileMode::Single {
+                length: file.length,
+            },
+            _ => FileMode::Multi { files },
+        }
+    }
+}
+
+impl FileEntry {
+    fn from_bencode(value: BencodeType) -> Result<Self, MetaInfoError> {
+        let mut dict = value
+            .into_dict()
+            .map_err(|other| wrong_type("files", "dictionary", &other))?;
+
+        let path = into_list(required(&mut dict, "path")?, "path")?
+            .into_iter()
+            .map(|segment| into_string(segment, "path"))
+            .collect::<Result<_, _>>()?;
+
+        Ok(Self {
+            length: into_integer(required(&mut dict, "length")?, "length")?,
+            path,
+        })
+    }
+}
+
+fn required(dict: &mut Dict, key: &'static str) -> Result<BencodeType, MetaInfoError> {
+    dict.remove(key.as_bytes())
+        .ok_or(MetaInfoError::MissingKey(key))
+}
+
+fn optional<T>(
+    dict: &mut Dict,
+    key: &'static str,
+    convert: fn(BencodeType, &'static str) -> Result<T, MetaInfoError>,
+) -> Result<Option<T>, MetaInfoError> {
+    dict.remove(key.as_bytes())
+        .map(|value| convert(value, key))
+        .transpose()
+}
+
+fn wrong_type(key: &'static str, expected: &'static str, found: &BencodeType) -> MetaInfoError {
+    MetaInfoError::WrongType {
+        key,
+        expected,
+        found: found.type_name(),
+    }
+}
+
+fn into_integer(value: BencodeType, key: &'static str) -> Result<i64, MetaInfoError> {
+    value
+        .into_integer()
+        .map_err(|other| wrong_type(key, "integer", &other))
+}
+
+fn into_bytes(value: BencodeType, key: &'static str) -> Result<Vec<u8>, MetaInfoError> {
+    value
+        .into_bytes()
+        .map_err(|other| wrong_type(key, "bytestring", &other))
+}
+
+fn into_list(value: BencodeType, key: &'static str) -> Result<Vec<BencodeType>, MetaInfoError> {
+    value
+        .into_list()
+        .map_err(|other| wrong_type(key, "list", &other))
+}
+
+fn into_files(value: BencodeType, key: &'static str) -> Result<Vec<FileEntry>, MetaInfoError> {
+    into_list(value, key)?
+        .into_iter()
+        .map(FileEntry::from_bencode)
+        .collect()
+}
+
+// Flattens a v2 `file tree`, where a file is a dictionary under the empty key
+fn into_file_tree(value: BencodeType, key: &'static str) -> Result<Vec<FileEntry>, MetaInfoError> {
+    fn walk(
+        dict: Dict,
+        path: &mut Vec<String>,
+        files: &mut Vec<FileEntry>,
+    ) -> Result<(), MetaInfoError> {
+        for (segment, node) in dict {
+            let mut node = node
+                .into_dict()
+                .map_err(|other| wrong_type("file tree", "dictionary", &other))?;
+            if segment.is_empty() {
+                files.push(FileEntry {
+                    length: into_integer(required(&mut node, "length")?, "length")?,
+                    path: path.clone(),
+                });
+                continue;
+            }
+
+            path.push(into_string(BencodeType::ByteString(segment), "file tree")?);
+            walk(node, path, files)?;
+            path.pop();
+        }
+        Ok(())
+    }
+
+    let dict = value
+        .into_dict()
+        .map_err(|other| wrong_type(key, "dictionary", &other))?;
+    let mut files = Vec::new();
+    walk(dict, &mut Vec::new(), &mut files)?;
+    Ok(files)
+}
+
+fn into_tiers(value: BencodeType, key: &'static str) -> Result<Vec<Vec<String>>, MetaInfoError> {
+    into_list(value, key)?
+        .into_iter()
+        .map(|tier| {
+            into_list(tier, key)?
+                .into_iter()
+                .map(|url| into_string(url, key))
+                .collect()
+        })
+        .collect()
+}
+
+// `url-list` is either a single URL or a list of them, empty URLs are dropped
+fn into_url_list(value: BencodeType, key: &'static str) -> Result<Vec<String>, MetaInfoError> {
+    let urls = match value {
+        BencodeType::List(urls) => urls
+            .into_iter()
+            .map(|url| into_string(url, key))
+            .collect::<Result<Vec<_>, _>>()?,
+        url => vec![into_string(url, key)?],
+    };
+    Ok(urls.into_iter().filter(|url| !url.is_empty()).collect())
+}
+
+fn into_string(value: BencodeType, key: &'static str) -> Result<String, MetaInfoError> {
+    String::from_utf8(into_bytes(value, key)?).map_err(|_| MetaInfoError::InvalidValue {
+        key,
+        reason: "not valid UTF-8",
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn info_dict() -> BencodeType {
+        BencodeType::dict()
+            .insert("length", 12)
+            .insert("name", "file.txt")
+            .insert("piece length", 16384)
+            .insert("pieces", vec![0xab; 20])
+            .build()
+    }
+
+    #[test]
+    fn from_bencode_single_file() {
+        let torrent = BencodeType::dict()
+            .insert("announce", "http://tracker/announce")
+            .insert("comment", "a comment")
+            .insert("created by", "hand")
+            .insert("creation date", 1700000000)
+            .insert("info", info_dict())
+            .build();
+
+        let metainfo = MetaInfo::from_bencode(torrent).unwrap();
+        assert_eq!(
+            metainfo,
+            MetaInfo {
+                announce: Some("http://tracker/announce".to_string()),
+                info: Info {
+                    piece_length: 16384,
+                    pieces: vec![0xab; 20],
+                    mode: FileMode::Single { length: 12 },
+                    name: b"file.txt".to_vec(),
+                    private: false,
+                    info_hash: sha1(&info_dict().encode()),
+                    version: MetaVersion::V1,
+                    info_hash_v2: None,
+                },
+                creation_date: Some(1700000000),
+                comment: Some("a comment".to_string()),
+                created_by: Some("hand".to_string()),
+                announce_list: None,
+                url_list: Vec::new(),
+            }
+        );
+    }
+
+    #[test]
+    fn from_bencode_optional_keys_absent() {
+        let torrent = BencodeType::dict().insert("info", info_dict()).build();
+        let metainfo = MetaInfo::from_bencode(torrent).unwrap();
+        assert_eq!(metainfo.announce, None);
+        assert_eq!(metainfo.creation_date, None);
+        assert_eq!(metainfo.comment, None);
+        assert_eq!(metainfo.created_by, None);
+    }
+
+    #[test]
+    fn from_bencode_missing_info() {
+        let torrent = BencodeType::dict().insert("announce", "url").build();
+        assert!(matches!(
+            MetaInfo::from_bencode(torrent),
+            Err(MetaInfoError::MissingKey("info"))
+        ));
+    }
+
+    #[test]
+    fn from_bencode_wrong_type() {
+        let info = BencodeType::dict()
+            .insert("length", 12)
+            .insert("name", "file.txt")
+            .insert("piece length", "16384")
+            .insert("pieces", vec![0xab; 20])
+            .build();
+        let torrent = BencodeType::dict().insert("info", info).build();
+        let err = MetaInfo::from_bencode(torrent).unwrap_err();
+        assert!(matches!(
+            err,
+            MetaInfoError::WrongType {
+                key: "piece length",
+                expected: "integer",
+                found: "bytestring"
+            }
+        ));
+        assert_eq!(
+            err.to_string(),
+            "\"piece length\": expected integer, found bytestring"
+        );
+    }
+
+    #[test]
+    fn from_bencode_missing_field() {
+        let info = BencodeType::dict()
+            .insert("length", 12)
+            .insert("piece length", 16384)
+            .insert("pieces", vec![0xab; 20])
+            .build();
+        let err =
+            MetaInfo::from_bencode(BencodeType::dict().insert("info", info).build()).unwrap_err();
+        assert!(matches!(err, MetaInfoError::MissingKey("name")));
+        assert_eq!(err.to_string(), "missing key \"name\"");
+    }
+
+    #[test]
+    fn from_bencode_invalid_value() {
+        let torrent = BencodeType::dict()
+            .insert("comment", vec![0xff, 0xfe])
+            .insert("info", info_dict())
+            .build();
+        assert!(matches!(
+            MetaInfo::from_bencode(torrent),
+            Err(MetaInfoError::InvalidValue {
+                key: "comment",
+                reason: "not valid UTF-8"
+            })
+        ));
+    }
+
+    #[test]
+    fn from_bencode_not_a_dictionary() {
+        assert!(matches!(
+            MetaInfo::from_bencode(BencodeType::Integer(1)),
+            Err(MetaInfoError::NotADictionary)
+        ));
+    }
+
+    #[test]
+    fn announce_list_tiers() {
+        let tier = |urls: &[&str]| -> BencodeType {
+            urls.iter().map(|url| BencodeType::from(*url)).collect()
+        };
+        let torrent = BencodeType::dict()
+            .insert("announce", "http://a/announce")
+            .insert(
+                "announce-list",
+                vec![
+                    tier(&["http://a/announce", "http://b/announce"]),
+                    tier(&["udp://c:80"]),
+                ],
+            )
+            .insert("info", info_dict())
+            .build();
+
+        let metainfo = MetaInfo::from_bencode(torrent).unwrap();
+        assert_eq!(
+            metainfo.announce_list(),
+            Some(vec![
+                vec![
+                    "http://a/announce".to_string(),
+                    "http://b/announce".to_string()
+                ],
+                vec!["udp://c:80".to_string()],
+            ])
+        );
+    }
+
+    #[test]
+    fn announce_list_falls_back_to_announce() {
+        let torrent = BencodeType::dict()
+            .insert("announce", "http://a/announce")
+            .insert("info", info_dict())
+            .build();
+        let metainfo = MetaInfo::from_bencode(torrent).unwrap();
+        assert_eq!(
+            metainfo.announce_list(),
+            Some(vec![vec!["http://a/announce".to_string()]])
+        );
+
+        let torrent = BencodeType::dict().insert("info", info_dict()).build();
+        let metainfo = MetaInfo::from_bencode(torrent).unwrap();
+        assert_eq!(metainfo.announce_list(), None);
+    }
+
+    #[test]
+    fn all_trackers_merges_without_duplicates() {
+        let tier = |urls: &[&str]| -> BencodeType {
+            urls.iter().map(|url| BencodeType::from(*url)).collect()
+        };
+        let torrent = BencodeType::dict()
+            .insert("announce", "http://b/announce")
+            .insert(
+                "announce-list",
+                vec![
+                    tier(&["http://a/announce", "http://b/announce"]),
+                    tier(&["", "udp://c:80", "http://a/announce"]),
+                ],
+            )
+            .insert("info", info_dict())
+            .build();
+
+        let metainfo = MetaInfo::from_bencode(torrent).unwrap();
+        assert_eq!(
+            metainfo.all_trackers(),
+            ["http://b/announce", "http://a/announce", "udp://c:80"]
+        );
+
+        let torrent = BencodeType::dict().insert("info", info_dict()).build();
+        assert!(MetaInfo::from_bencode(torrent)
+            .unwrap()
+            .all_trackers()
+            .is_empty());
+    }
+
+    #[test]
+    fn web_seeds_single_url() {
+        let torrent = BencodeType::dict()
+            .insert("info", info_dict())
+            .insert("url-list", "http://mirror/file.txt")
+            .build();
+        let metainfo = MetaInfo::from_bencode(torrent).unwrap();
+        assert_eq!(metainfo.web_seeds(), vec!["http://mirror/file.txt"]);
+    }
+
+    #[test]
+    fn web_seeds_list() {
+        let urls: BencodeType = ["http://a/", "", "http://b/"]
+            .into_iter()
+            .map(BencodeType::from)
+            .collect();
+        let torrent = BencodeType::dict()
+            .insert("info", info_dict())
+            .insert("url-list", urls)
+            .build();
+        let metainfo = MetaInfo::from_bencode(torrent).unwrap();
+        assert_eq!(metainfo.web_seeds(), vec!["http://a/", "http://b/"]);
+    }
+
+    #[test]
+    fn web_seeds_absent() {
+        let torrent = BencodeType::dict().insert("info", info_dict()).build();
+        let metainfo = MetaInfo::from_bencode(torrent).unwrap();
+        assert!(metainfo.web_seeds().is_empty());
+    }
+
+    #[test]
+    fn web_seeds_wrong_type() {
+        let torrent = BencodeType::dict()
+            .insert("info", info_dict())
+            .insert("url-list", 1)
+            .build();
+        assert!(matches!(
+            MetaInfo::from_bencode(torrent),
+            Err(MetaInfoError::WrongType {
+                key: "url-list",
+                found: "integer",
+                ..
+            })
+        ));
+    }
+
+    fn info_with_private(private: Option<BencodeType>) -> Info {
+        let mut info = info_dict().into_dict().unwrap();
+        if let Some(private) = private {
+            info.insert(b"private".to_vec(), private);
+        }
+        Info::from_bencode(BencodeType::Dictionary(info)).unwrap()
+    }
+
+    #[test]
+    fn is_private() {
+        assert!(info_with_private(Some(BencodeType::Integer(1))).is_private());
+        assert!(!info_with_private(Some(BencodeType::Integer(0))).is_private());
+        assert!(!info_with_private(None).is_private());
+    }
+
+    #[test]
+    fn is_private_unexpected_values() {
+        assert!(info_with_private(Some(BencodeType::Integer(2))).is_private());
+        assert!(info_with_private(Some(BencodeType::from("0"))).is_private());
+    }
+
+    fn with_pieces(length: i64, piece_length: i64, pieces: usize) -> MetaInfo {
+        let info = BencodeType::dict()
+            .insert("length", length)
+            .insert("name", "file.txt")
+            .insert("piece length", piece_length)
+            .insert("pieces", vec![0xab; pieces * 20])
+            .build();
+        MetaInfo::from_bencode(BencodeType::dict().insert("info", info).build()).unwrap()
+    }
+
+    #[test]
+    fn validate_consistent() {
+        with_pieces(32768, 16384, 2).validate().unwrap();
+        with_pieces(32769, 16384, 3).validate().unwrap();
+        with_pieces(0, 16384, 0).validate().unwrap();
+    }
+
+    #[test]
+    fn validate_piece_count_mismatch() {
+        assert!(matches!(
+            with_pieces(32769, 16384, 2).validate(),
+            Err(MetaInfoError::PieceCountMismatch {
+                expected: 3,
+                actual: 2
+            })
+        ));
+    }
+
+    #[test]
+    fn validate_non_positive_piece_length() {
+        assert!(matches!(
+            with_pieces(12, 0, 1).validate(),
+            Err(MetaInfoError::NonPositivePieceLength(0))
+        ));
+    }
+
+    #[test]
+    fn validate_negative_length() {
+        assert!(matches!(
+            with_pieces(-1, 16384, 1).validate(),
+            Err(MetaInfoError::NegativeLength(-1))
+        ));
+    }
+
+    #[test]
+    fn validate_negative_file_length() {
+        // The total is positive, but a file can't be
+        assert!(matches!(
+            multi_file(&[-5, 10], 16384).validate(),
+            Err(MetaInfoError::NegativeLength(-5))
+        ));
+    }
+
+    #[test]
+    fn validate_length_overflow() {
+        let metainfo = multi_file(&[i64::MAX, i64::MAX], 16384);
+        assert_eq!(metainfo.info.mode.total_length(), None);
+        assert!(matches!(
+            metainfo.validate(),
+            Err(MetaInfoError::LengthOverflow)
+        ));
+        assert!(metainfo.info.blocks_for_piece(0).is_empty());
+    }
+
+    #[test]
+    fn verify_piece_hashes() {
+        let mut metainfo = with_pieces(8, 4, 2);
+        metainfo.info.pieces = [sha1(b"abcd"), sha1(b"efgh")].concat();
+
+        assert!(metainfo.verify_piece(0, b"abcd").unwrap());
+        assert!(metainfo.verify_piece(1, b"efgh").unwrap());
+        assert!(!metainfo.verify_piece(1, b"efgX").unwrap());
+        assert!(matches!(
+            metainfo.verify_piece(2, b"ijkl"),
+            Err(MetaInfoError::PieceIndexOutOfRange { index: 2, count: 2 })
+        ));
+    }
+
+    #[test]
+    fn to_magnet_round_trip() {
+        let torrent = BencodeType::dict()
+            .insert("announce", "http://a/announce")
+            .insert(
+                "announce-list",
+                vec![
+                    BencodeType::List(vec!["http://a/announce".into()]),
+                    BencodeType::List(vec!["udp://b:80".into()]),
+                ],
+            )
+            .insert(
+                "info",
+                BencodeType::dict()
+                    .insert("length", 12)
+                    .insert("name", "my file.txt")
+                    .insert("piece length", 16384)
+                    .insert("pieces", vec![0xab; 20])
+                    .build(),
+            )
+            .build();
+        let metainfo = MetaInfo::from_bencode(torrent).unwrap();
+
+        let magnet = metainfo.to_magnet();
+        assert_eq!(
+            magnet,
+            format!(
+                "magnet:?xt=urn:btih:{}&dn=my%20file.txt\
+                 &tr=http%3A%2F%2Fa%2Fannounce&tr=udp%3A%2F%2Fb%3A80",
+                metainfo.info_hash_hex()
+            )
+        );
+
+        let parsed = crate::MagnetLink::parse(&magnet).unwrap();
+        assert_eq!(parsed.info_hash, metainfo.info_hash());
+        assert_eq!(parsed.display_name.as_deref(), Some("my file.txt"));
+        assert_eq!(parsed.trackers, ["http://a/announce", "udp://b:80"]);
+    }
+
+    #[test]
+    fn to_magnet_keeps_announce_missing_from_list() {
+        let torrent = BencodeType::dict()
+            .insert("announce", "http://a/announce")
+            .insert(
+                "announce-list",
+                vec![BencodeType::List(vec!["udp://b:80".into()])],
+            )
+            .insert("info", info_dict())
+            .build();
+        let metainfo = MetaInfo::from_bencode(torrent).unwrap();
+
+        let parsed = crate::MagnetLink::parse(&metainfo.to_magnet()).unwrap();
+        assert_eq!(parsed.info_hash, metainfo.info_hash());
+        assert_eq!(parsed.trackers, ["http://a/announce", "udp://b:80"]);
+    }
+
+    fn multi_file(lengths: &[i64], piece_length: i64) -> MetaInfo {
+        let files: BencodeType = lengths
+            .iter()
+            .enumerate()
+            .map(|(i, length)| {
+                BencodeType::dict()
+                    .insert("length", *length)
+                    .insert("path", vec![BencodeType::from(format!("{i}.txt"))])
+                    .build()
+            })
+            .collect();
+        let info = BencodeType::dict()
+            .insert("files", files)
+            .insert("name", "dir")
+            .insert("piece length", piece_length)
+            .insert("pieces", Vec::<u8>::new())
+            .build();
+        MetaInfo::from_bencode(BencodeType::dict().insert("info", info).build()).unwrap()
+    }
+
+    #[test]
+    fn file_piece_range_overflow() {
+        // The offset of the third file overflows
+        let metainfo = multi_file(&[i64::MAX, i64::MAX, 4], 8);
+        assert_eq!(metainfo.file_piece_range(2), None);
+        // The second file's offset fits, but its end doesn't
+        assert_eq!(metainfo.file_piece_range(1), None);
+        assert_eq!(
+            metainfo.file_piece_range(0),
+            Some(0..(i64::MAX as usize / 8 + 1))
+        );
+    }
+
+    #[test]
+    fn file_piece_range_within_one_piece() {
+        let metainfo = multi_file(&[3, 4, 9], 8);
+        assert_eq!(metainfo.file_piece_range(0), Some(0..1));
+        assert_eq!(metainfo.file_piece_range(1), Some(0..1));
+    }
+
+    #[test]
+    fn file_piece_range_spanning_pieces() {
+        let metainfo = multi_file(&[3, 20, 1, 0, 8], 8);
+        // Bytes 3..23 share piece 0 with file 0 and piece 2 with file 2
+        assert_eq!(metainfo.file_piece_range(1), Some(0..3));
+        assert_eq!(metainfo.file_piece_range(2), Some(2..3));
+        assert_eq!(metainfo.file_piece_range(3), Some(3..3));
+        assert_eq!(metainfo.file_piece_range(4), Some(3..4));
+        assert_eq!(metainfo.file_piece_range(5), None);
+    }
+
+    #[test]
+    fn file_piece_range_single_file() {
+        let metainfo = with_pieces(32769, 16384, 3);
+        assert_eq!(metainfo.file_piece_range(0), Some(0..3));
+        assert_eq!(metainfo.file_piece_range(1), None);
+    }
+
+    #[test]
+    fn from_bencode_multi_file() {
+        let files: BencodeType = vec![
+            BencodeType::dict()
+                .insert("length", 5)
+                .insert("path", vec![BencodeType::from("a.txt")])
+                .build(),
+            BencodeType::dict()
+                .insert("length", 7)
+                .insert(
+                    "path",
+                    vec![BencodeType::from("sub"), BencodeType::from("b.txt")],
+                )
+                .build(),
+        ]
+        .into();
+        let info = BencodeType::dict()
+            .insert("files", files)
+            .insert("name", "dir")
+            .insert("piece length", 16384)
+            .insert("pieces", vec![0xab; 20])
+            .build();
+
+        let info = Info::from_bencode(info).unwrap();
+        assert_eq!(
+            info.mode,
+            FileMode::Multi {
+                files: vec![
+                    FileEntry {
+                        length: 5,
+                        path: vec!["a.txt".to_string()],
+                    },
+                    FileEntry {
+                        length: 7,
+                        path: vec!["sub".to_string(), "b.txt".to_string()],
+                    },
+                ]
+            }
+        );
+    }
+
+    #[test]
+    fn from_bencode_both_length_and_files() {
+        let info = BencodeType::dict()
+            .insert("files", BencodeType::List(Vec::new()))
+            .insert("length", 12)
+            .insert("name", "dir")
+            .insert("piece length", 16384)
+            .insert("pieces", vec![0xab; 20])
+            .build();
+        assert!(matches!(
+            Info::from_bencode(info),
+            Err(MetaInfoError::AmbiguousFileMode)
+        ));
+    }
+
+    #[test]
+    fn from_bencode_neither_length_nor_files() {
+        let info = BencodeType::dict()
+            .insert("name", "dir")
+            .insert("piece length", 16384)
+            .insert("pieces", vec![0xab; 20])
+            .build();
+        assert!(matches!(
+            Info::from_bencode(info),
+            Err(MetaInfoError::AmbiguousFileMode)
+        ));
+    }
+
+    fn info_named(name: impl Into<BencodeType>) -> Info {
+        let mut info = info_dict().into_dict().unwrap();
+        info.insert(b"name".to_vec(), name.into());
+        Info::from_bencode(BencodeType::Dictionary(info)).unwrap()
+    }
+
+    #[test]
+    fn safe_name_plain() {
+        let info = info_named("debian 12.iso");
+        assert_eq!(info.name(), Some("debian 12.iso"));
+        assert_eq!(info.safe_name(), "debian 12.iso");
+    }
+
+    #[test]
+    fn safe_name_path_traversal() {
+        let info = info_named("../../etc");
+        assert_eq!(info.name(), Some("../../etc"));
+        assert_eq!(info.safe_name(), ".._.._etc");
+
+        assert_eq!(info_named("a\\b\0c\n").safe_name(), "a_b_c_");
+        for unusable in ["", "..", ".", " "] {
+            let info = info_named(unusable);
+            assert_eq!(info.safe_name(), hex::encode(&info.info_hash));
+        }
+    }
+
+    #[test]
+    fn safe_name_not_utf8() {
+        let info = info_named(vec![b'a', 0xff, b'b']);
+        assert_eq!(info.name(), None);
+        assert_eq!(info.safe_name(), hex::encode(&info.info_hash));
+        assert_eq!(info.safe_name().len(), 40);
+    }
+
+    fn info_with(length: i64, piece_length: i64) -> Info {
+        Info {
+            piece_length,
+            pieces: Vec::new(),
+            mode: FileMode::Single { length },
+            name: b"file.txt".to_vec(),
+            private: false,
+            info_hash: [0; 20],
+            version: MetaVersion::V1,
+            info_hash_v2: None,
+        }
+    }
+
+    #[test]
+    fn blocks_for_full_piece() {
+        let info = info_with(3 * 65536, 65536);
+        assert_eq!(
+            info.blocks_for_piece(1),
+            vec![(0, 16384), (16384, 16384), (32768, 16384), (49152, 16384)]
+        );
+    }
+
+    #[test]
+    fn blocks_for_short_last_piece() {
+        let info = info_with(65536 + 20000, 65536);
+        assert_eq!(info.blocks_for_piece(1), vec![(0, 16384), (16384, 3616)]);
+        assert!(info.blocks_for_piece(2).is_empty());
+    }
+
+    #[test]
+    fn blocks_for_piece_smaller_than_block() {
+        let info = info_with(10000, 8192);
+        assert_eq!(info.blocks_for_piece(0), vec![(0, 8192)]);
+        assert_eq!(info.blocks_for_piece(1), vec![(0, 1808)]);
+    }
+
+    #[test]
+    fn blocks_for_piece_out_of_range() {
+        let info = info_with(10, 2);
+        assert!(info.blocks_for_piece(5).is_empty());
+        assert!(info.blocks_for_piece(usize::MAX).is_empty());
+    }
+
+    #[test]
+    fn blocks_for_piece_longer_than_u32() {
+        let info = info_with(1 << 33, 1 << 33);
+        assert!(info.blocks_for_piece(0).is_empty());
+    }
+
+    #[test]
+    fn piece_hashes_splits_blob() {
+        let mut info = Info::from_bencode(info_dict()).unwrap();
+        info.pieces = [[1; 20], [2; 20]].concat();
+        assert_eq!(info.piece_hashes().unwrap(), vec![[1; 20], [2; 20]]);
+    }
+
+    #[test]
+    fn piece_hashes_rejects_partial_hash() {
+        let mut info = Info::from_bencode(info_dict()).unwrap();
+        info.pieces = vec![0; 41];
+        assert!(matches!(
+            info.piece_hashes(),
+            Err(MetaInfoError::InvalidPiecesLength(41))
+        ));
+    }
+
+    #[test]
+    fn info_hash_arch_torrent() {
+        let bytes = std::fs::read("./archlinux-2022.11.01-x86_64.iso.torrent").unwrap();
+        let expected = "9670eb81865d58a1ed397a461f515c5383d883c6";
+
+        let metainfo = MetaInfo::from_bytes(&bytes).unwrap();
+        assert_eq!(metainfo.info_hash_hex(), expected);
+
+        let metainfo = MetaInfo::from_bencode(bencoding::decode(&bytes).unwrap()).unwrap();
+        assert_eq!(metainfo.info_hash_hex(), expected);
+    }
+
+    #[test]
+    fn info_hash_encodings() {
+        let torrent = BencodeType::dict().insert("info", info_dict()).build();
+        let mut metainfo = MetaInfo::from_bencode(torrent).unwrap();
+        metainfo.info.info_hash = [
+            0x12, 0x34, 0x56, 0x78, 0x9a, 0xbc, 0xde, 0xf0, 0x00, 0x01, 0x02, 0x03, 0x04, 0x05,
+            0x06, 0x07, 0x08, 0x09, 0x0a, 0xff,
+        ];
+
+        assert_eq!(
+            metainfo.info_hash_hex(),
+            "123456789abcdef0000102030405060708090aff"
+        );
+        assert_eq!(
+            metainfo.info_hash_base32(),
+            "CI2FM6E2XTPPAAABAIBQIBIGA4EASCX7"
+        );
+        assert_eq!(
+            crate::MagnetLink::parse(&format!(
+                "magnet:?xt=urn:btih:{}",
+                metainfo.info_hash_base32()
+            ))
+            .unwrap()
+            .info_hash,
+            metainfo.info_hash()
+        );
+        assert_eq!(
+            metainfo.info_hash_urlencoded(),
+            "%12%34%56%78%9A%BC%DE%F0%00%01%02%03%04%05%06%07%08%09%0A%FF"
+        );
+    }
+
+    #[test]
+    fn info_hash_uses_original_bytes() {
+        // "04:name" is accepted but re-encodes as "4:name"
+        let input = b"d4:infod6:lengthi12e04:name1:a12:piece lengthi1e6:pieces0:ee";
+        let raw_info = &input[7..input.len() - 1];
+
+        let metainfo = MetaInfo::from_bytes(input).unwrap();
+        assert_eq!(metainfo.info_hash(), sha1(raw_info));
+
+        let reencoded = MetaInfo::from_bencode(bencoding::decode(input).unwrap()).unwrap();
+        assert_ne!(reencoded.info_hash(), metainfo.info_hash());
+    }
+
+    #[test]
+    fn from_bytes_decode_error() {
+        assert!(matches!(
+            MetaInfo::from_bytes(b"d4:info"),
+            Err(MetaInfoError::Decode(_))
+        ));
+    }
+
+    #[test]
+    fn from_reader_cursor() {
+        let torrent = BencodeType::dict().insert("info", info_dict()).build();
+        let bytes = torrent.encode();
+
+        let metainfo = MetaInfo::from_reader(io::Cursor::new(&bytes)).unwrap();
+        assert_eq!(metainfo, MetaInfo::from_bytes(&bytes).unwrap());
+    }
+
+    #[test]
+    fn from_reader_decode_error() {
+        assert!(matches!(
+            MetaInfo::from_reader(io::Cursor::new(b"d4:info")),
+            Err(MetaInfoError::Decode(_))
+        ));
+    }
+
+    #[test]
+    fn from_file_arch_torrent() {
+        let metainfo = MetaInfo::from_file("./archlinux-2022.11.01-x86_64.iso.torrent").unwrap();
+        assert_eq!(
+            metainfo.info_hash_hex(),
+            "9670eb81865d58a1ed397a461f515c5383d883c6"
+        );
+    }
+
+    #[test]
+    fn from_file_missing() {
+        assert!(matches!(
+            MetaInfo::from_file("./does-not-exist.torrent"),
+            Err(MetaInfoError::Io(_))
+        ));
+    }
+
+    #[test]
+    fn from_bencode_arch_torrent() {
+        let bytes = std::fs::read("./archlinux-2022.11.01-x86_64.iso.torrent").unwrap();
+        let metainfo = MetaInfo::from_bytes(&bytes).unwrap();
+        assert_eq!(
+            metainfo.info.name(),
+            Some("archlinux-2022.11.01-x86_64.iso")
+        );
+        assert!(!metainfo.info.piece_hashes().unwrap().is_empty());
+        metainfo.validate().unwrap();
+    }
+
+    fn v2_file(length: i64) -> BencodeType {
+        let file = BencodeType::dict()
+            .insert("length", length)
+            .insert("pieces root", vec![0xcd; 32])
+            .build();
+        BencodeType::dict().insert("", file).build()
+    }
+
+    #[test]
+    fn v2_only_torrent() {
+        let tree = BencodeType::dict()
+            .insert("a.txt", v2_file(5))
+            .insert(
+                "sub",
+                BencodeType::dict().insert("b.txt", v2_file(7)).build(),
+            )
+            .build();
+        let info = BencodeType::dict()
+            .insert("file tree", tree)
+            .insert("meta version", 2)
+            .insert("name", "dir")
+            .insert("piece length", 16384)
+            .build();
+        let info_bytes = info.encode();
+
+        let metainfo =
+            MetaInfo::from_bencode(BencodeType::dict().insert("info", info).build()).unwrap();
+        assert_eq!(metainfo.version(), MetaVersion::V2);
+        let info_hash_v2 = metainfo.info_hash_v2().unwrap();
+        assert_eq!(info_hash_v2.len(), 32);
+        assert_eq!(info_hash_v2, sha256(&info_bytes));
+        assert_eq!(
+            metainfo.info.mode,
+            FileMode::Multi {
+                files: vec![
+                    FileEntry {
+                        length: 5,
+                        path: vec!["a.txt".to_string()],
+                    },
+                    FileEntry {
+                        length: 7,
+                        path: vec!["sub".to_string(), "b.txt".to_string()],
+                    },
+                ]
+            }
+        );
+        assert!(metainfo.info.pieces.is_empty());
+    }
+
+    #[test]
+    fn hybrid_torrent() {
+        let mut info = info_dict().into_dict().unwrap();
+        info.insert(
+            b"file tree".to_vec(),
+            BencodeType::dict().insert("file.txt", v2_file(12)).build(),
+        );
+        info.insert(b"meta version".to_vec(), BencodeType::Integer(2));
+        let info = BencodeType::Dictionary(info);
+        let info_bytes = info.encode();
+
+        let metainfo =
+            MetaInfo::from_bencode(BencodeType::dict().insert("info", info).build()).unwrap();
+        assert_eq!(metainfo.version(), MetaVersion::Hybrid);
+        assert_eq!(metainfo.info_hash_v2(), Some(sha256(&info_bytes)));
+        assert_eq!(metainfo.info_hash(), sha1(&info_bytes));
+        assert_eq!(metainfo.info.mode, FileMode::Single { length: 12 });
+    }
+
+    #[test]
+    fn v1_torrent_has_no_v2_hash() {
+        let metainfo =
+            MetaInfo::from_bencode(BencodeType::dict().insert("info", info_dict()).build())
+                .unwrap();
+        assert_eq!(metainfo.version(), MetaVersion::V1);
+        assert_eq!(metainfo.info_hash_v2(), None);
+    }
+}
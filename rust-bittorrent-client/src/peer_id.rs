@@ -0,0 +1,49 @@
+use crate::rng::{Rng, XorShift64};
+
+const PREFIX_LEN: usize = 8;
+
+/// A fresh peer id, see `generate_with_rng`.
+pub fn generate(client_prefix: &str) -> [u8; 20] {
+    generate_with_rng(client_prefix, &mut XorShift64::from_entropy())
+}
+
+/// An Azureus-style peer id: `client_prefix` cut or padded with `-` to 8 bytes,
+/// e.g. `-RS0001-`, followed by 12 random bytes.
+pub fn generate_with_rng(client_prefix: &str, rng: &mut impl Rng) -> [u8; 20] {
+    let mut id = [b'-'; 20];
+    let prefix = &client_prefix.as_bytes()[..client_prefix.len().min(PREFIX_LEN)];
+    id[..prefix.len()].copy_from_slice(prefix);
+
+    rng.fill_bytes(&mut id[PREFIX_LEN..]);
+    id
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn generate_prefix() {
+        let id = generate("-RS0001-");
+        assert_eq!(id.len(), 20);
+        assert_eq!(&id[..8], b"-RS0001-");
+    }
+
+    #[test]
+    fn generate_pads_and_truncates_prefix() {
+        let mut rng = XorShift64::new(1);
+        assert_eq!(&generate_with_rng("-RS1", &mut rng)[..8], b"-RS1----");
+        assert_eq!(
+            &generate_with_rng("-RS0001-extra", &mut rng)[..8],
+            b"-RS0001-"
+        );
+    }
+
+    #[test]
+    fn generate_with_rng_is_deterministic() {
+        let a = generate_with_rng("-RS0001-", &mut XorShift64::new(7));
+        let b = generate_with_rng("-RS0001-", &mut XorShift64::new(7));
+        assert_eq!(a, b);
+        assert_ne!(a, generate_with_rng("-RS0001-", &mut XorShift64::new(8)));
+    }
+}
@@ -0,0 +1,87 @@
+use crate::message::PeerMessage;
+
+/// The choke and interest flags of one peer connection, from our side.
+/// Starts out choked and not interested in both directions (BEP 3).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PeerState {
+    pub am_choking: bool,
+    pub am_interested: bool,
+    pub peer_choking: bool,
+    pub peer_interested: bool,
+}
+
+impl Default for PeerState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PeerState {
+    pub fn new() -> Self {
+        Self {
+            am_choking: true,
+            am_interested: false,
+            peer_choking: true,
+            peer_interested: false,
+        }
+    }
+
+    /// Updates the peer's flags from a message it sent, other messages are
+    /// ignored.
+    pub fn apply(&mut self, message: &PeerMessage) {
+        match message {
+            PeerMessage::Choke => self.peer_choking = true,
+            PeerMessage::Unchoke => self.peer_choking = false,
+            PeerMessage::Interested => self.peer_interested = true,
+            PeerMessage::NotInterested => self.peer_interested = false,
+            _ => {}
+        }
+    }
+
+    /// Whether we may send requests: the peer unchoked us and we told it
+    /// we're interested.
+    pub fn can_request(&self) -> bool {
+        self.am_interested && !self.peer_choking
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn can_request_transitions() {
+        let mut state = PeerState::new();
+        assert!(!state.can_request());
+
+        state.apply(&PeerMessage::Unchoke);
+        assert!(!state.can_request());
+        state.am_interested = true;
+        assert!(state.can_request());
+
+        state.apply(&PeerMessage::Have(3));
+        state.apply(&PeerMessage::KeepAlive);
+        assert!(state.can_request());
+
+        state.apply(&PeerMessage::Choke);
+        assert!(!state.can_request());
+        state.apply(&PeerMessage::Unchoke);
+        assert!(state.can_request());
+
+        state.am_interested = false;
+        assert!(!state.can_request());
+    }
+
+    #[test]
+    fn apply_tracks_peer_interest() {
+        let mut state = PeerState::default();
+        state.apply(&PeerMessage::Interested);
+        assert!(state.peer_interested);
+        state.apply(&PeerMessage::NotInterested);
+        assert!(!state.peer_interested);
+
+        // Our own flags only change when we send something
+        assert!(state.am_choking);
+        assert!(!state.am_interested);
+    }
+}
@@ -0,0 +1,77 @@
+const DIGITS: &[u8; 16] = b"0123456789ABCDEF";
+
+/// Percent-encodes every byte, as trackers expect for binary values such as
+/// the info hash.
+pub(crate) fn encode_all(bytes: &[u8]) -> String {
+    let mut out = String::with_capacity(bytes.len() * 3);
+    for byte in bytes {
+        push_escaped(&mut out, *byte);
+    }
+    out
+}
+
+/// Percent-encodes everything except RFC 3986 unreserved characters.
+pub(crate) fn encode(bytes: &[u8]) -> String {
+    let mut out = String::with_capacity(bytes.len());
+    for byte in bytes {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                out.push(*byte as char)
+            }
+            _ => push_escaped(&mut out, *byte),
+        }
+    }
+    out
+}
+
+/// Decodes a query string value, where `+` stands for a space. Returns `None`
+/// on a malformed escape.
+pub(crate) fn decode(input: &str) -> Option<Vec<u8>> {
+    let mut out = Vec::with_capacity(input.len());
+    let mut bytes = input.bytes();
+
+    while let Some(byte) = bytes.next() {
+        match byte {
+            b'%' => {
+                let high = (bytes.next()? as char).to_digit(16)?;
+                let low = (bytes.next()? as char).to_digit(16)?;
+                out.push((high << 4 | low) as u8);
+            }
+            b'+' => out.push(b' '),
+            _ => out.push(byte),
+        }
+    }
+
+    Some(out)
+}
+
+fn push_escaped(out: &mut String, byte: u8) {
+    out.push('%');
+    out.push(DIGITS[(byte >> 4) as usize] as char);
+    out.push(DIGITS[(byte & 0xf) as usize] as char);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn percent_encode_all() {
+        assert_eq!(encode_all(b""), "");
+        assert_eq!(encode_all(b"a\x00\xff"), "%61%00%FF");
+    }
+
+    #[test]
+    fn percent_encode_unreserved() {
+        assert_eq!(encode(b"abc-XYZ_0.9~"), "abc-XYZ_0.9~");
+        assert_eq!(encode(b"a b/c:\xff"), "a%20b%2Fc%3A%FF");
+    }
+
+    #[test]
+    fn percent_decode() {
+        assert_eq!(decode("a%20b+c%2f").unwrap(), b"a b c/");
+        assert_eq!(decode("%FF").unwrap(), [0xff]);
+        assert_eq!(decode("%2"), None);
+        assert_eq!(decode("%zz"), None);
+    }
+}
@@ -0,0 +1,118 @@
+use crate::{bitfield::Bitfield, rng::XorShift64};
+
+/// Chooses the next piece to download, rarest first.
+#[derive(Debug, Clone)]
+pub struct PiecePicker {
+    // Number of known peers holding each piece
+    availability: Vec<u32>,
+    have: Bitfield,
+    rng: XorShift64,
+}
+
+impl PiecePicker {
+    pub fn new(num_pieces: usize) -> Self {
+        Self::with_rng(num_pieces, XorShift64::from_entropy())
+    }
+
+    /// Uses `rng` for tie-breaks, for reproducible picks.
+    pub fn with_rng(num_pieces: usize, rng: XorShift64) -> Self {
+        Self {
+            availability: vec![0; num_pieces],
+            have: Bitfield::new(num_pieces),
+            rng,
+        }
+    }
+
+    pub fn mark_have(&mut self, index: usize) {
+        self.have.set(index);
+    }
+
+    pub fn add_peer_bitfield(&mut self, bitfield: &Bitfield) {
+        for (index, count) in self.availability.iter_mut().enumerate() {
+            if bitfield.has(index) {
+                *count += 1;
+            }
+        }
+    }
+
+    /// The rarest piece `from_peer` has that we don't, ties broken at random.
+    pub fn next_piece(&mut self, from_peer: &Bitfield) -> Option<usize> {
+        let mut best = None;
+        let mut ties = 0;
+
+        for (index, &count) in self.availability.iter().enumerate() {
+            if self.have.has(index) || !from_peer.has(index) {
+                continue;
+            }
+
+            match best {
+                Some((_, best_count)) if count > best_count => continue,
+                Some((_, best_count)) if count == best_count => {
+                    // Reservoir sampling keeps each tied piece equally likely
+                    ties += 1;
+                    if self.rng.next_u64().is_multiple_of(ties) {
+                        best = Some((index, count));
+                    }
+                }
+                _ => {
+                    best = Some((index, count));
+                    ties = 1;
+                }
+            }
+        }
+
+        best.map(|(index, _)| index)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bitfield(num_pieces: usize, pieces: &[usize]) -> Bitfield {
+        let mut bitfield = Bitfield::new(num_pieces);
+        for &piece in pieces {
+            bitfield.set(piece);
+        }
+        bitfield
+    }
+
+    #[test]
+    fn picker_prefers_rarest() {
+        let mut picker = PiecePicker::with_rng(4, XorShift64::new(1));
+        picker.add_peer_bitfield(&bitfield(4, &[0, 1, 2]));
+        picker.add_peer_bitfield(&bitfield(4, &[0, 1]));
+        picker.add_peer_bitfield(&bitfield(4, &[0]));
+
+        assert_eq!(picker.next_piece(&bitfield(4, &[0, 1, 2])), Some(2));
+        assert_eq!(picker.next_piece(&bitfield(4, &[0, 1])), Some(1));
+    }
+
+    #[test]
+    fn picker_skips_pieces_we_have() {
+        let mut picker = PiecePicker::with_rng(3, XorShift64::new(1));
+        picker.add_peer_bitfield(&bitfield(3, &[0, 1, 2]));
+        picker.add_peer_bitfield(&bitfield(3, &[0, 1]));
+        picker.mark_have(2);
+
+        let peer = bitfield(3, &[0, 1, 2]);
+        assert!(matches!(picker.next_piece(&peer), Some(0 | 1)));
+
+        picker.mark_have(0);
+        picker.mark_have(1);
+        assert_eq!(picker.next_piece(&peer), None);
+    }
+
+    #[test]
+    fn picker_breaks_ties_randomly() {
+        let mut picker = PiecePicker::with_rng(8, XorShift64::new(3));
+        let peer = bitfield(8, &[0, 1, 2, 3, 4, 5, 6, 7]);
+        picker.add_peer_bitfield(&peer);
+
+        let mut seen = [false; 8];
+        for _ in 0..200 {
+            seen[picker.next_piece(&peer).unwrap()] = true;
+        }
+        assert!(seen.iter().filter(|&&seen| seen).count() > 1);
+    }
+}
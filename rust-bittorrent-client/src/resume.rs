@@ -0,0 +1,175 @@
+use crate::{
+    bitfield::{Bitfield, BitfieldError},
+    metainfo::MetaInfo,
+};
+use bencoding::{BencodeType, DecodeError, Encodable};
+use std::{fs, io, path::Path};
+
+/// Download progress persisted between runs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResumeData {
+    pub info_hash: [u8; 20],
+    /// Pieces already written and verified
+    pub pieces: Bitfield,
+}
+
+#[derive(Debug)]
+pub enum ResumeError {
+    Io(io::Error),
+    Decode(DecodeError),
+    NotADictionary,
+    MissingKey(&'static str),
+    WrongType(&'static str),
+    Bitfield(BitfieldError),
+    /// The resume data belongs to a different torrent
+    InfoHashMismatch,
+    /// The saved piece count isn't the torrent's
+    PieceCountMismatch {
+        saved: usize,
+        expected: usize,
+    },
+}
+
+impl ResumeData {
+    /// Resume data for `metainfo` with no pieces completed.
+    pub fn new(metainfo: &MetaInfo) -> Self {
+        Self {
+            info_hash: metainfo.info_hash(),
+            pieces: Bitfield::new(metainfo.info.pieces.len() / 20),
+        }
+    }
+
+    pub fn encode(&self) -> Vec<u8> {
+        BencodeType::dict()
+            .insert("info hash", self.info_hash.to_vec())
+            .insert("num pieces", self.pieces.len() as i64)
+            .insert("pieces", self.pieces.to_bytes())
+            .build()
+            .encode()
+    }
+
+    /// Decodes resume data, trusting the bitfield only if it was saved for
+    /// `metainfo`.
+    pub fn decode(input: &[u8], metainfo: &MetaInfo) -> Result<Self, ResumeError> {
+        let value = bencoding::decode(input).map_err(ResumeError::Decode)?;
+        if value.as_dict().is_none() {
+            return Err(ResumeError::NotADictionary);
+        }
+
+        let get = |key: &'static str| value.get(key).ok_or(ResumeError::MissingKey(key));
+
+        let info_hash: [u8; 20] = get("info hash")?
+            .as_bytes()
+            .and_then(|bytes| bytes.try_into().ok())
+            .ok_or(ResumeError::WrongType("info hash"))?;
+        if info_hash != metainfo.info_hash() {
+            return Err(ResumeError::InfoHashMismatch);
+        }
+
+        let num_pieces = get("num pieces")?
+            .as_integer()
+            .and_then(|n| usize::try_from(n).ok())
+            .ok_or(ResumeError::WrongType("num pieces"))?;
+        let expected = metainfo.info.pieces.len() / 20;
+        if num_pieces != expected {
+            return Err(ResumeError::PieceCountMismatch {
+                saved: num_pieces,
+                expected,
+            });
+        }
+        let bytes = get("pieces")?
+            .as_bytes()
+            .ok_or(ResumeError::WrongType("pieces"))?;
+
+        Ok(Self {
+            info_hash,
+            pieces: Bitfield::from_bytes(bytes, num_pieces).map_err(ResumeError::Bitfield)?,
+        })
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), ResumeError> {
+        fs::write(path, self.encode()).map_err(ResumeError::Io)
+    }
+
+    pub fn load(path: impl AsRef<Path>, metainfo: &MetaInfo) -> Result<Self, ResumeError> {
+        let bytes = fs::read(path).map_err(ResumeError::Io)?;
+        Self::decode(&bytes, metainfo)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn torrent(name: &str) -> MetaInfo {
+        let info = BencodeType::dict()
+            .insert("length", 40)
+            .insert("name", name)
+            .insert("piece length", 4)
+            .insert("pieces", vec![0xab; 200])
+            .build();
+        MetaInfo::from_bencode(BencodeType::dict().insert("info", info).build()).unwrap()
+    }
+
+    #[test]
+    fn save_and_load_round_trip() {
+        let metainfo = torrent("file.txt");
+        let mut resume = ResumeData::new(&metainfo);
+        resume.pieces.set(0);
+        resume.pieces.set(9);
+
+        let path = std::env::temp_dir().join(format!("{}-resume-round-trip", std::process::id()));
+        resume.save(&path).unwrap();
+        let loaded = ResumeData::load(&path, &metainfo).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(loaded, resume);
+        assert_eq!(loaded.pieces.count_ones(), 2);
+    }
+
+    #[test]
+    fn decode_rejects_other_torrent() {
+        let resume = ResumeData::new(&torrent("file.txt"));
+        assert!(matches!(
+            ResumeData::decode(&resume.encode(), &torrent("other.txt")),
+            Err(ResumeError::InfoHashMismatch)
+        ));
+    }
+
+    #[test]
+    fn decode_errors() {
+        let metainfo = torrent("file.txt");
+        let with_pieces = |num_pieces: i64, pieces: Vec<u8>| {
+            BencodeType::dict()
+                .insert("info hash", metainfo.info_hash().to_vec())
+                .insert("num pieces", num_pieces)
+                .insert("pieces", pieces)
+                .build()
+                .encode()
+        };
+
+        assert!(matches!(
+            ResumeData::decode(&with_pieces(10, vec![0, 0, 0]), &metainfo),
+            Err(ResumeError::Bitfield(BitfieldError::WrongLength { .. }))
+        ));
+        assert!(matches!(
+            ResumeData::decode(&with_pieces(16, vec![0, 0]), &metainfo),
+            Err(ResumeError::PieceCountMismatch {
+                saved: 16,
+                expected: 10
+            })
+        ));
+        assert!(matches!(
+            ResumeData::decode(&with_pieces(-1, vec![]), &metainfo),
+            Err(ResumeError::WrongType("num pieces"))
+        ));
+        assert!(matches!(
+            ResumeData::decode(b"d6:pieceslee", &metainfo),
+            Err(ResumeError::MissingKey("info hash"))
+        ));
+        assert!(matches!(
+            ResumeData::load("./does-not-exist.resume", &metainfo),
+            Err(ResumeError::Io(_))
+        ));
+    }
+}
@@ -0,0 +1,353 @@
+use crate::metainfo::{FileMode, MetaInfo, MetaInfoError};
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{self, Read, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+};
+
+/// Reads and writes whole pieces of a torrent's files on disk.
+///
+/// A single-file torrent is stored at `root/name`, a multi-file one under the
+/// directory `root/name`, where `name` is `Info::safe_name`.
+#[derive(Debug, Clone)]
+pub struct Storage {
+    files: Vec<StoredFile>,
+    piece_length: u64,
+    total_length: u64,
+}
+
+#[derive(Debug, Clone)]
+struct StoredFile {
+    path: PathBuf,
+    /// Offset of the file's first byte within the torrent's content
+    offset: u64,
+    length: u64,
+}
+
+#[derive(Debug)]
+pub enum StorageError {
+    Io(io::Error),
+    MetaInfo(MetaInfoError),
+    /// A name or path segment that would escape the download directory
+    UnsafePath(String),
+    PieceIndexOutOfRange {
+        index: usize,
+        count: usize,
+    },
+    WrongPieceLength {
+        index: usize,
+        expected: u64,
+        actual: usize,
+    },
+}
+
+impl From<io::Error> for StorageError {
+    fn from(value: io::Error) -> Self {
+        Self::Io(value)
+    }
+}
+
+impl Storage {
+    /// Lays out the files of `metainfo` below `root`. Nothing is touched on
+    /// disk until the first write.
+    pub fn new(metainfo: &MetaInfo, root: impl AsRef<Path>) -> Result<Self, StorageError> {
+        metainfo.validate().map_err(StorageError::MetaInfo)?;
+        let info = &metainfo.info;
+        let base = root.as_ref().join(info.safe_name());
+
+        let mut files = Vec::new();
+        let mut offset: u64 = 0;
+        match &info.mode {
+            FileMode::Single { length } => {
+                files.push(StoredFile {
+                    path: base,
+                    offset,
+                    length: *length as u64,
+                });
+                offset = *length as u64;
+            }
+            FileMode::Multi { files: entries } => {
+                for entry in entries {
+                    if entry.path.is_empty() {
+                        return Err(StorageError::UnsafePath(String::new()));
+                    }
+
+                    let mut path = base.clone();
+                    for segment in &entry.path {
+                        path.push(safe_segment(segment)?);
+                    }
+                    files.push(StoredFile {
+                        path,
+                        offset,
+                        length: entry.length as u64,
+                    });
+                    offset = offset
+                        .checked_add(entry.length as u64)
+                        .ok_or(StorageError::MetaInfo(MetaInfoError::LengthOverflow))?;
+                }
+            }
+        }
+
+        Ok(Self {
+            files,
+            piece_length: info.piece_length as u64,
+            total_length: offset,
+        })
+    }
+
+    pub fn piece_count(&self) -> usize {
+        self.total_length.div_ceil(self.piece_length) as usize
+    }
+
+    /// Length of piece `index`, only the last piece may be shorter than the
+    /// piece length.
+    pub fn piece_len(&self, index: usize) -> Result<u64, StorageError> {
+        let count = self.piece_count();
+        if index >= count {
+            return Err(StorageError::PieceIndexOutOfRange { index, count });
+        }
+
+        let start = index as u64 * self.piece_length;
+        Ok(self.piece_length.min(self.total_length - start))
+    }
+
+    /// Writes a verified piece to every file it overlaps, creating files and
+    /// directories as needed.
+    pub fn write_piece(&self, index: usize, data: &[u8]) -> Result<(), StorageError> {
+        let expected = self.piece_len(index)?;
+        if data.len() as u64 != expected {
+            return Err(StorageError::WrongPieceLength {
+                index,
+                expected,
+                actual: data.len(),
+            });
+        }
+
+        for (file, position, range) in self.segments(index, expected) {
+            if let Some(parent) = file.path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            let mut handle = OpenOptions::new()
+                .write(true)
+                .create(true)
+                .truncate(false)
+                .open(&file.path)?;
+            handle.seek(SeekFrom::Start(position))?;
+            handle.write_all(&data[range])?;
+        }
+
+        Ok(())
+    }
+
+    /// Reads piece `index` back, e.g. to re-verify it or serve it to a peer.
+    pub fn read_piece(&self, index: usize) -> Result<Vec<u8>, StorageError> {
+        let len = self.piece_len(index)?;
+        let mut data = vec![0; len as usize];
+
+        for (file, position, range) in self.segments(index, len) {
+            let mut handle = File::open(&file.path)?;
+            handle.seek(SeekFrom::Start(position))?;
+            handle.read_exact(&mut data[range])?;
+        }
+
+        Ok(data)
+    }
+
+    // The files piece `index` overlaps, each with the position within the file
+    // and the matching range of the piece's bytes
+    fn segments(
+        &self,
+        index: usize,
+        len: u64,
+    ) -> impl Iterator<Item = (&StoredFile, u64, std::ops::Range<usize>)> {
+        let start = index as u64 * self.piece_length;
+        let end = start + len;
+
+        self.files
+            .iter()
+            .filter(move |file| file.offset < end && file.offset + file.length > start)
+            .map(move |file| {
+                let from = start.max(file.offset);
+                let to = end.min(file.offset + file.length);
+                (
+                    file,
+                    from - file.offset,
+                    (from - start) as usize..(to - start) as usize,
+                )
+            })
+    }
+}
+
+fn safe_segment(segment: &str) -> Result<&str, StorageError> {
+    let unsafe_segment = segment.is_empty()
+        || segment == "."
+        || segment == ".."
+        || segment.contains(['/', '\\'])
+        || Path::new(segment).is_absolute();
+    if unsafe_segment {
+        return Err(StorageError::UnsafePath(segment.to_string()));
+    }
+    Ok(segment)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sha1::sha1;
+    use bencoding::BencodeType;
+
+    fn temp_root(name: &str) -> PathBuf {
+        let root = std::env::temp_dir().join(format!("{}-storage-{name}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        root
+    }
+
+    fn two_file_torrent(content: &[u8]) -> MetaInfo {
+        let pieces: Vec<u8> = content.chunks(8).flat_map(sha1).collect();
+        let file = |length: i64, path: &[&str]| {
+            BencodeType::dict()
+                .insert("length", length)
+                .insert(
+                    "path",
+                    path.iter()
+                        .map(|s| BencodeType::from(*s))
+                        .collect::<BencodeType>(),
+                )
+                .build()
+        };
+        let info = BencodeType::dict()
+            .insert(
+                "files",
+                vec![file(5, &["a.txt"]), file(15, &["sub", "b.txt"])],
+            )
+            .insert("name", "dir")
+            .insert("piece length", 8)
+            .insert("pieces", pieces)
+            .build();
+        MetaInfo::from_bencode(BencodeType::dict().insert("info", info).build()).unwrap()
+    }
+
+    #[test]
+    fn piece_straddles_file_boundary() {
+        let content = b"hello world, storage";
+        let metainfo = two_file_torrent(content);
+        let root = temp_root("straddle");
+        let storage = Storage::new(&metainfo, &root).unwrap();
+        assert_eq!(storage.piece_count(), 3);
+
+        for (index, piece) in content.chunks(8).enumerate() {
+            storage.write_piece(index, piece).unwrap();
+        }
+
+        assert_eq!(fs::read(root.join("dir/a.txt")).unwrap(), b"hello");
+        assert_eq!(
+            fs::read(root.join("dir/sub/b.txt")).unwrap(),
+            b" world, storage"
+        );
+        assert_eq!(storage.read_piece(0).unwrap(), b"hello wo");
+        assert_eq!(storage.read_piece(2).unwrap(), b"rage");
+        assert!(metainfo
+            .verify_piece(0, &storage.read_piece(0).unwrap())
+            .unwrap());
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn write_piece_out_of_order() {
+        let content = b"hello world, storage";
+        let root = temp_root("out-of-order");
+        let storage = Storage::new(&two_file_torrent(content), &root).unwrap();
+
+        storage.write_piece(2, b"rage").unwrap();
+        storage.write_piece(0, b"hello wo").unwrap();
+        storage.write_piece(1, b"rld, sto").unwrap();
+        assert_eq!(
+            fs::read(root.join("dir/sub/b.txt")).unwrap(),
+            b" world, storage"
+        );
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn write_piece_errors() {
+        let root = temp_root("errors");
+        let storage = Storage::new(&two_file_torrent(b"hello world, storage"), &root).unwrap();
+
+        assert!(matches!(
+            storage.write_piece(3, b""),
+            Err(StorageError::PieceIndexOutOfRange { index: 3, count: 3 })
+        ));
+        assert!(matches!(
+            storage.write_piece(2, b"toolong"),
+            Err(StorageError::WrongPieceLength {
+                index: 2,
+                expected: 4,
+                actual: 7
+            })
+        ));
+        assert!(matches!(storage.read_piece(0), Err(StorageError::Io(_))));
+    }
+
+    #[test]
+    fn rejects_path_traversal() {
+        let file = BencodeType::dict()
+            .insert("length", 0)
+            .insert("path", vec![BencodeType::from(".."), "passwd".into()])
+            .build();
+        let info = BencodeType::dict()
+            .insert("files", vec![file])
+            .insert("name", "dir")
+            .insert("piece length", 8)
+            .insert("pieces", Vec::<u8>::new())
+            .build();
+        let metainfo =
+            MetaInfo::from_bencode(BencodeType::dict().insert("info", info).build()).unwrap();
+        assert!(matches!(
+            Storage::new(&metainfo, temp_root("traversal")),
+            Err(StorageError::UnsafePath(segment)) if segment == ".."
+        ));
+    }
+
+    #[test]
+    fn rejects_negative_file_length() {
+        let file = |length: i64, name: &str| {
+            BencodeType::dict()
+                .insert("length", length)
+                .insert("path", vec![BencodeType::from(name)])
+                .build()
+        };
+        let info = BencodeType::dict()
+            .insert("files", vec![file(-5, "a"), file(10, "b")])
+            .insert("name", "dir")
+            .insert("piece length", 8)
+            .insert("pieces", vec![0; 20])
+            .build();
+        let metainfo =
+            MetaInfo::from_bencode(BencodeType::dict().insert("info", info).build()).unwrap();
+        assert!(matches!(
+            Storage::new(&metainfo, temp_root("negative")),
+            Err(StorageError::MetaInfo(MetaInfoError::NegativeLength(-5)))
+        ));
+    }
+
+    #[test]
+    fn unsafe_name_is_sanitized() {
+        let info = BencodeType::dict()
+            .insert("length", 4)
+            .insert("name", "../escape")
+            .insert("piece length", 8)
+            .insert("pieces", sha1(b"data").to_vec())
+            .build();
+        let metainfo =
+            MetaInfo::from_bencode(BencodeType::dict().insert("info", info).build()).unwrap();
+        let root = temp_root("sanitized");
+        let storage = Storage::new(&metainfo, &root).unwrap();
+
+        storage.write_piece(0, b"data").unwrap();
+        assert_eq!(fs::read(root.join(".._escape")).unwrap(), b"data");
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,866 @@
+use crate::{
+    percent,
+    rng::XorShift64,
+    udp_tracker::{self, UdpTrackerError},
+};
+use bencoding::{BencodeType, DecodeError};
+use std::{
+    collections::BTreeMap,
+    io::{self, Read, Write},
+    net::{
+        IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, SocketAddrV4, SocketAddrV6, TcpStream,
+        ToSocketAddrs, UdpSocket,
+    },
+    sync::mpsc,
+    thread,
+    time::Duration,
+};
+
+// Larger HTTP tracker replies are cut off and fail to decode
+const MAX_RESPONSE_LEN: u64 = 1024 * 1024;
+
+/// Parameters of an HTTP tracker announce.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AnnounceRequest {
+    pub info_hash: [u8; 20],
+    pub peer_id: [u8; 20],
+    pub port: u16,
+    pub uploaded: u64,
+    pub downloaded: u64,
+    pub left: u64,
+    pub event: Option<Event>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Event {
+    Started,
+    Completed,
+    Stopped,
+}
+
+impl Event {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Event::Started => "started",
+            Event::Completed => "completed",
+            Event::Stopped => "stopped",
+        }
+    }
+}
+
+/// A tracker's reply to an announce. When `failure_reason` is set the tracker
+/// sent nothing else, so `interval` is zero and the peer lists are empty.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AnnounceResponse {
+    pub interval: i64,
+    pub peers: Vec<SocketAddrV4>,
+    pub peers6: Vec<SocketAddrV6>,
+    pub failure_reason: Option<String>,
+    pub warning_message: Option<String>,
+}
+
+/// Swarm counts from a scrape, keyed by info hash.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ScrapeResponse {
+    pub files: BTreeMap<[u8; 20], ScrapeStats>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ScrapeStats {
+    /// Seeders
+    pub complete: i64,
+    pub downloaded: i64,
+    /// Leechers
+    pub incomplete: i64,
+}
+
+#[derive(Debug)]
+pub enum TrackerError {
+    Decode(DecodeError),
+    NotADictionary,
+    MissingKey(&'static str),
+    WrongType(&'static str),
+    /// Compact peers not a multiple of 6 (18 for `peers6`) bytes, or a
+    /// malformed peer entry
+    InvalidPeers,
+    Io(io::Error),
+    Udp(UdpTrackerError),
+    /// Only `http://` and `udp://` trackers are supported
+    UnsupportedScheme(String),
+    InvalidUrl(String),
+    HttpStatus(u16),
+    InvalidHttpResponse,
+}
+
+impl From<io::Error> for TrackerError {
+    fn from(e: io::Error) -> Self {
+        TrackerError::Io(e)
+    }
+}
+
+impl AnnounceResponse {
+    pub fn from_bytes(input: &[u8]) -> Result<Self, TrackerError> {
+        Self::from_bencode(bencoding::decode(input).map_err(TrackerError::Decode)?)
+    }
+
+    pub fn from_bencode(value: BencodeType) -> Result<Self, TrackerError> {
+        if value.as_dict().is_none() {
+            return Err(TrackerError::NotADictionary);
+        }
+
+        let warning_message = optional_string(&value, "warning message")?;
+        if let Some(reason) = optional_string(&value, "failure reason")? {
+            return Ok(Self {
+                interval: 0,
+                peers: Vec::new(),
+                peers6: Vec::new(),
+                failure_reason: Some(reason),
+                warning_message,
+            });
+        }
+
+        let interval = value
+            .get("interval")
+            .ok_or(TrackerError::MissingKey("interval"))?
+            .as_integer()
+            .ok_or(TrackerError::WrongType("interval"))?;
+
+        let mut peers6 = Vec::new();
+        let peers = match value.get("peers") {
+            None => Vec::new(),
+            Some(BencodeType::ByteString(compact)) => {
+                parse_compact_peers(compact).ok_or(TrackerError::InvalidPeers)?
+            }
+            Some(BencodeType::List(entries)) => {
+                let mut peers = Vec::new();
+                for entry in entries {
+                    match parse_peer_dict(entry)? {
+                        Some(SocketAddr::V4(peer)) => peers.push(peer),
+                        Some(SocketAddr::V6(peer)) => peers6.push(peer),
+                        None => {}
+                    }
+                }
+                peers
+            }
+            Some(_) => return Err(TrackerError::WrongType("peers")),
+        };
+
+        match value.get("peers6") {
+            None => {}
+            Some(BencodeType::ByteString(compact)) => {
+                peers6.extend(parse_compact_peers6(compact).ok_or(TrackerError::InvalidPeers)?)
+            }
+            Some(_) => return Err(TrackerError::WrongType("peers6")),
+        }
+
+        Ok(Self {
+            interval,
+            peers,
+            peers6,
+            failure_reason: None,
+            warning_message,
+        })
+    }
+
+    /// IPv4 peers followed by IPv6 peers.
+    pub fn peers(&self) -> Vec<SocketAddr> {
+        let v4 = self.peers.iter().copied().map(SocketAddr::V4);
+        let v6 = self.peers6.iter().copied().map(SocketAddr::V6);
+        v4.chain(v6).collect()
+    }
+}
+
+impl ScrapeResponse {
+    pub fn from_bytes(input: &[u8]) -> Result<Self, TrackerError> {
+        Self::from_bencode(bencoding::decode(input).map_err(TrackerError::Decode)?)
+    }
+
+    pub fn from_bencode(value: BencodeType) -> Result<Self, TrackerError> {
+        if value.as_dict().is_none() {
+            return Err(TrackerError::NotADictionary);
+        }
+
+        let files = value
+            .get("files")
+            .ok_or(TrackerError::MissingKey("files"))?
+            .iter_dict()
+            .ok_or(TrackerError::WrongType("files"))?
+            .map(|(hash, stats)| {
+                let hash = hash
+                    .try_into()
+                    .map_err(|_| TrackerError::WrongType("files"))?;
+                Ok((hash, ScrapeStats::from_bencode(stats)?))
+            })
+            .collect::<Result<_, TrackerError>>()?;
+
+        Ok(Self { files })
+    }
+}
+
+impl ScrapeStats {
+    fn from_bencode(value: &BencodeType) -> Result<Self, TrackerError> {
+        let count = |key: &'static str| match value.get(key) {
+            None => Ok(0),
+            Some(count) => count.as_integer().ok_or(TrackerError::WrongType(key)),
+        };
+
+        Ok(Self {
+            complete: count("complete")?,
+            downloaded: count("downloaded")?,
+            incomplete: count("incomplete")?,
+        })
+    }
+}
+
+/// Splits 6-byte entries of a big-endian IPv4 address followed by the port.
+pub(crate) fn parse_compact_peers(bytes: &[u8]) -> Option<Vec<SocketAddrV4>> {
+    if !bytes.len().is_multiple_of(6) {
+        return None;
+    }
+
+    Some(
+        bytes
+            .chunks_exact(6)
+            .map(|entry| {
+                let ip = Ipv4Addr::new(entry[0], entry[1], entry[2], entry[3]);
+                SocketAddrV4::new(ip, u16::from_be_bytes([entry[4], entry[5]]))
+            })
+            .collect(),
+    )
+}
+
+/// Splits 18-byte entries of a big-endian IPv6 address followed by the port.
+pub(crate) fn parse_compact_peers6(bytes: &[u8]) -> Option<Vec<SocketAddrV6>> {
+    if !bytes.len().is_multiple_of(18) {
+        return None;
+    }
+
+    Some(
+        bytes
+            .chunks_exact(18)
+            .map(|entry| {
+                let ip: [u8; 16] = entry[..16].try_into().unwrap();
+                let port = u16::from_be_bytes([entry[16], entry[17]]);
+                SocketAddrV6::new(Ipv6Addr::from(ip), port, 0, 0)
+            })
+            .collect(),
+    )
+}
+
+// `Ok(None)` for an `ip` that is a hostname rather than an address literal,
+// resolving it is left to the caller
+fn parse_peer_dict(entry: &BencodeType) -> Result<Option<SocketAddr>, TrackerError> {
+    let ip = entry
+        .get("ip")
+        .and_then(BencodeType::as_str)
+        .ok_or(TrackerError::InvalidPeers)?;
+    let port = entry
+        .get("port")
+        .and_then(BencodeType::as_integer)
+        .and_then(|port| u16::try_from(port).ok())
+        .ok_or(TrackerError::InvalidPeers)?;
+
+    let literal = ip
+        .strip_prefix('[')
+        .and_then(|ip| ip.strip_suffix(']'))
+        .unwrap_or(ip);
+    Ok(literal
+        .parse::<IpAddr>()
+        .ok()
+        .map(|ip| SocketAddr::new(ip, port)))
+}
+
+fn optional_string(value: &BencodeType, key: &'static str) -> Result<Option<String>, TrackerError> {
+    value
+        .get(key)
+        .map(|v| {
+            v.as_bytes()
+                .map(|bytes| String::from_utf8_lossy(bytes).into_owned())
+                .ok_or(TrackerError::WrongType(key))
+        })
+        .transpose()
+}
+
+/// Appends the announce query to the tracker's `announce` URL, asking for a
+/// compact peer list.
+pub fn build_announce_url(base: &str, req: &AnnounceRequest) -> String {
+    // Some trackers carry their own query parameters, e.g. a passkey
+    let separator = if base.contains('?') { '&' } else { '?' };
+
+    let mut url = format!(
+        "{base}{separator}info_hash={}&peer_id={}&port={}&uploaded={}&downloaded={}&left={}&compact=1",
+        percent::encode(&req.info_hash),
+        percent::encode(&req.peer_id),
+        req.port,
+        req.uploaded,
+        req.downloaded,
+        req.left,
+    );
+    if let Some(event) = req.event {
+        url.push_str("&event=");
+        url.push_str(event.as_str());
+    }
+
+    url
+}
+
+/// Derives the scrape URL by the convention of replacing `announce` at the start
+/// of the last path segment with `scrape`, then adds the info hashes. `None`
+/// if the tracker doesn't follow the convention.
+pub fn build_scrape_url(base: &str, info_hashes: &[[u8; 20]]) -> Option<String> {
+    let (path, query) = match base.split_once('?') {
+        Some((path, query)) => (path, Some(query)),
+        None => (base, None),
+    };
+    let slash = path.rfind('/')?;
+    let rest = path[slash + 1..].strip_prefix("announce")?;
+
+    let mut url = format!("{}scrape{rest}", &path[..=slash]);
+    let mut separator = '?';
+    if let Some(query) = query {
+        url.push('?');
+        url.push_str(query);
+        separator = '&';
+    }
+    for info_hash in info_hashes {
+        url.push(separator);
+        url.push_str("info_hash=");
+        url.push_str(&percent::encode(info_hash));
+        separator = '&';
+    }
+
+    Some(url)
+}
+
+/// Announces to an `http://` or `udp://` tracker and waits for its reply.
+/// `timeout` applies separately to resolving the tracker's host name,
+/// connecting, and every read and write. UDP requests are retried with
+/// doubling timeouts, see `udp_tracker::announce`.
+pub fn announce(
+    url: &str,
+    req: &AnnounceRequest,
+    timeout: Duration,
+) -> Result<AnnounceResponse, TrackerError> {
+    if let Some(rest) = url.strip_prefix("http://") {
+        http_announce(&build_announce_url(url, req), rest, timeout)
+    } else if let Some(rest) = url.strip_prefix("udp://") {
+        udp_announce(rest, req, timeout)
+    } else {
+        Err(TrackerError::UnsupportedScheme(url.to_string()))
+    }
+}
+
+fn http_announce(
+    url: &str,
+    rest: &str,
+    timeout: Duration,
+) -> Result<AnnounceResponse, TrackerError> {
+    let authority = rest.split(['/', '?']).next().unwrap_or_default();
+    let target = &url["http://".len() + authority.len()..];
+    let target = if target.starts_with('/') {
+        target.to_string()
+    } else {
+        format!("/{target}")
+    };
+
+    let mut stream = connect_tcp(authority, 80, timeout)?;
+    stream.set_read_timeout(Some(timeout))?;
+    stream.set_write_timeout(Some(timeout))?;
+    // HTTP/1.0 keeps trackers from replying with a chunked body
+    write!(
+        stream,
+        "GET {target} HTTP/1.0\r\nHost: {authority}\r\nConnection: close\r\n\r\n"
+    )?;
+
+    let mut reply = Vec::new();
+    stream.take(MAX_RESPONSE_LEN).read_to_end(&mut reply)?;
+
+    let header_end = reply
+        .windows(4)
+        .position(|window| window == b"\r\n\r\n")
+        .ok_or(TrackerError::InvalidHttpResponse)?;
+    let status = std::str::from_utf8(&reply[..header_end])
+        .ok()
+        .and_then(|head| head.split_whitespace().nth(1))
+        .and_then(|code| code.parse().ok())
+        .ok_or(TrackerError::InvalidHttpResponse)?;
+    if status != 200 {
+        return Err(TrackerError::HttpStatus(status));
+    }
+
+    AnnounceResponse::from_bytes(&reply[header_end + 4..])
+}
+
+fn udp_announce(
+    rest: &str,
+    req: &AnnounceRequest,
+    timeout: Duration,
+) -> Result<AnnounceResponse, TrackerError> {
+    let authority = rest.split('/').next().unwrap_or_default();
+    let addr = resolve(authority, None, timeout)?;
+
+    let local = if addr.is_ipv4() {
+        "0.0.0.0:0"
+    } else {
+        "[::]:0"
+    };
+    let socket = UdpSocket::bind(local)?;
+    socket.connect(addr)?;
+    socket.set_read_timeout(Some(timeout))?;
+    socket.set_write_timeout(Some(timeout))?;
+
+    let mut rng = XorShift64::from_entropy();
+    let random = rng.next_u64();
+    match udp_tracker::announce(&socket, req, random as u32, (random >> 32) as u32) {
+        Ok(response) => Ok(AnnounceResponse {
+            interval: response.interval as i64,
+            peers: response.peers,
+            peers6: response.peers6,
+            failure_reason: None,
+            warning_message: None,
+        }),
+        Err(UdpTrackerError::Tracker(reason)) => Ok(AnnounceResponse {
+            interval: 0,
+            peers: Vec::new(),
+            peers6: Vec::new(),
+            failure_reason: Some(reason),
+            warning_message: None,
+        }),
+        Err(e) => Err(TrackerError::Udp(e)),
+    }
+}
+
+fn connect_tcp(
+    authority: &str,
+    default_port: u16,
+    timeout: Duration,
+) -> Result<TcpStream, TrackerError> {
+    let addr = resolve(authority, Some(default_port), timeout)?;
+    Ok(TcpStream::connect_timeout(&addr, timeout)?)
+}
+
+// Resolves `host[:port]`, bracketed IPv6 literals included. The lookup runs on
+// a helper thread since `to_socket_addrs` has no timeout of its own; a thread
+// outliving `timeout` is left to finish in the background
+fn resolve(
+    authority: &str,
+    default_port: Option<u16>,
+    timeout: Duration,
+) -> Result<SocketAddr, TrackerError> {
+    let invalid = || TrackerError::InvalidUrl(authority.to_string());
+
+    let (host, port) = match authority.rsplit_once(':') {
+        Some((host, port)) if !port.contains(']') => {
+            (host, port.parse::<u16>().map_err(|_| invalid())?)
+        }
+        _ => (authority, default_port.ok_or_else(invalid)?),
+    };
+    let host = host
+        .strip_prefix('[')
+        .and_then(|host| host.strip_suffix(']'))
+        .unwrap_or(host);
+    if host.is_empty() {
+        return Err(invalid());
+    }
+
+    if let Ok(ip) = host.parse::<IpAddr>() {
+        return Ok(SocketAddr::new(ip, port));
+    }
+
+    let (sender, receiver) = mpsc::channel();
+    let name = host.to_string();
+    thread::spawn(move || {
+        let addr = (name.as_str(), port)
+            .to_socket_addrs()
+            .map(|mut addrs| addrs.next());
+        let _ = sender.send(addr);
+    });
+
+    match receiver.recv_timeout(timeout) {
+        Ok(addr) => addr?.ok_or_else(invalid),
+        Err(_) => Err(TrackerError::Io(io::Error::new(
+            io::ErrorKind::TimedOut,
+            "name resolution timed out",
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request() -> AnnounceRequest {
+        let mut info_hash = [0; 20];
+        info_hash[..4].copy_from_slice(&[0x12, 0x34, b'a', 0xff]);
+
+        AnnounceRequest {
+            info_hash,
+            peer_id: *b"-RS0001-abcdefghijkl",
+            port: 6881,
+            uploaded: 0,
+            downloaded: 1024,
+            left: 4096,
+            event: Some(Event::Started),
+        }
+    }
+
+    #[test]
+    fn announce_response_compact_peers() {
+        let response = AnnounceResponse::from_bytes(
+            b"d8:intervali1800e5:peers12:\x7f\x00\x00\x01\x1a\xe1\x0a\x00\x00\x02\x00\x50e",
+        )
+        .unwrap();
+
+        assert_eq!(
+            response,
+            AnnounceResponse {
+                interval: 1800,
+                peers: vec![
+                    SocketAddrV4::new(Ipv4Addr::new(127, 0, 0, 1), 6881),
+                    SocketAddrV4::new(Ipv4Addr::new(10, 0, 0, 2), 80),
+                ],
+                peers6: Vec::new(),
+                failure_reason: None,
+                warning_message: None,
+            }
+        );
+    }
+
+    #[test]
+    fn announce_response_dict_peers() {
+        let response = AnnounceResponse::from_bytes(
+            b"d8:intervali900e5:peersld2:ip9:127.0.0.17:peer id20:-RS0001-abcdefghijkl4:porti6881eeee",
+        )
+        .unwrap();
+
+        assert_eq!(response.interval, 900);
+        assert_eq!(
+            response.peers,
+            [SocketAddrV4::new(Ipv4Addr::new(127, 0, 0, 1), 6881)]
+        );
+    }
+
+    #[test]
+    fn announce_response_dict_peers_mixed() {
+        let peer = |ip: &str, port: i64| {
+            BencodeType::dict()
+                .insert("ip", ip)
+                .insert("peer id", vec![0x2d; 20])
+                .insert("port", port)
+                .build()
+        };
+        let response = BencodeType::dict()
+            .insert("interval", 900)
+            .insert(
+                "peers",
+                vec![
+                    peer("10.0.0.2", 6881),
+                    peer("2001:db8::1", 51413),
+                    peer("tracker.example.com", 6881),
+                    peer("[::1]", 80),
+                ],
+            )
+            .build();
+        let response = AnnounceResponse::from_bencode(response).unwrap();
+
+        assert_eq!(response.peers, ["10.0.0.2:6881".parse().unwrap()]);
+        assert_eq!(
+            response.peers6,
+            [
+                "[2001:db8::1]:51413".parse().unwrap(),
+                "[::1]:80".parse().unwrap()
+            ]
+        );
+    }
+
+    #[test]
+    fn announce_response_dict_peers_malformed() {
+        let err =
+            AnnounceResponse::from_bytes(b"d8:intervali1e5:peersld2:ip8:10.0.0.14:porti70000eeee")
+                .unwrap_err();
+        assert!(matches!(err, TrackerError::InvalidPeers));
+    }
+
+    #[test]
+    fn announce_response_compact_peers6() {
+        let mut peers6 = Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 1)
+            .octets()
+            .to_vec();
+        peers6.extend_from_slice(&6881u16.to_be_bytes());
+        let response = BencodeType::dict()
+            .insert("interval", 1800)
+            .insert("peers", b"\x7f\x00\x00\x01\x1a\xe1".to_vec())
+            .insert("peers6", peers6)
+            .build();
+        let response = AnnounceResponse::from_bencode(response).unwrap();
+
+        assert_eq!(
+            response.peers6,
+            ["[2001:db8::1]:6881".parse::<SocketAddrV6>().unwrap()]
+        );
+        assert_eq!(
+            response.peers(),
+            [
+                "127.0.0.1:6881".parse::<SocketAddr>().unwrap(),
+                "[2001:db8::1]:6881".parse().unwrap(),
+            ]
+        );
+    }
+
+    #[test]
+    fn announce_response_invalid_peers6_length() {
+        let err = AnnounceResponse::from_bytes(b"d8:intervali1e6:peers617:aaaaaaaaaaaaaaaaae")
+            .unwrap_err();
+        assert!(matches!(err, TrackerError::InvalidPeers));
+    }
+
+    #[test]
+    fn announce_response_failure() {
+        let response =
+            AnnounceResponse::from_bytes(b"d14:failure reason17:torrent not founde").unwrap();
+        assert_eq!(
+            response.failure_reason.as_deref(),
+            Some("torrent not found")
+        );
+        assert!(response.peers.is_empty());
+    }
+
+    #[test]
+    fn announce_response_invalid_compact_length() {
+        let err = AnnounceResponse::from_bytes(b"d8:intervali1e5:peers5:abcdee").unwrap_err();
+        assert!(matches!(err, TrackerError::InvalidPeers));
+    }
+
+    #[test]
+    fn build_scrape_url_derivation() {
+        assert_eq!(
+            build_scrape_url("http://example.com/announce", &[]).as_deref(),
+            Some("http://example.com/scrape")
+        );
+        assert_eq!(
+            build_scrape_url("http://example.com/x/announce.php?passkey=1", &[[0x41; 20]])
+                .as_deref(),
+            Some("http://example.com/x/scrape.php?passkey=1&info_hash=AAAAAAAAAAAAAAAAAAAA")
+        );
+        assert_eq!(
+            build_scrape_url("http://example.com/announce", &[[0; 20], [0xff; 20]])
+                .unwrap()
+                .matches("info_hash=")
+                .count(),
+            2
+        );
+        assert_eq!(build_scrape_url("http://example.com/a", &[]), None);
+        assert_eq!(
+            build_scrape_url("http://example.com/x/announce/y", &[]),
+            None
+        );
+    }
+
+    #[test]
+    fn scrape_response_files() {
+        let mut input = b"d5:filesd20:".to_vec();
+        input.extend_from_slice(&[0xaa; 20]);
+        input.extend_from_slice(b"d8:completei5e10:downloadedi50e10:incompletei10eeee");
+
+        let response = ScrapeResponse::from_bytes(&input).unwrap();
+        assert_eq!(
+            response.files.get(&[0xaa; 20]),
+            Some(&ScrapeStats {
+                complete: 5,
+                downloaded: 50,
+                incomplete: 10,
+            })
+        );
+        assert_eq!(response.files.len(), 1);
+    }
+
+    #[test]
+    fn scrape_response_missing_files() {
+        let err = ScrapeResponse::from_bytes(b"de").unwrap_err();
+        assert!(matches!(err, TrackerError::MissingKey("files")));
+    }
+
+    #[test]
+    fn build_announce_url_query() {
+        assert_eq!(
+            build_announce_url("http://tracker/announce", &request()),
+            "http://tracker/announce?info_hash=%124a%FF%00%00%00%00%00%00%00%00%00%00%00%00%00%00%00%00\
+             &peer_id=-RS0001-abcdefghijkl&port=6881&uploaded=0&downloaded=1024&left=4096\
+             &compact=1&event=started"
+        );
+    }
+
+    #[test]
+    fn build_announce_url_existing_query() {
+        let req = AnnounceRequest {
+            event: None,
+            ..request()
+        };
+        let url = build_announce_url("http://tracker/announce?passkey=xyz", &req);
+        assert!(url.starts_with("http://tracker/announce?passkey=xyz&info_hash=%124a%FF"));
+        assert!(url.ends_with("&compact=1"));
+    }
+
+    fn mock_http_tracker(reply: &'static [u8]) -> (u16, std::thread::JoinHandle<String>) {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+
+        let server = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = Vec::new();
+            let mut buf = [0; 1024];
+            while !request.ends_with(b"\r\n\r\n") {
+                let len = stream.read(&mut buf).unwrap();
+                request.extend_from_slice(&buf[..len]);
+            }
+            stream.write_all(reply).unwrap();
+            String::from_utf8(request).unwrap()
+        });
+
+        (port, server)
+    }
+
+    #[test]
+    fn announce_http_mock_tracker() {
+        let (port, server) = mock_http_tracker(
+            b"HTTP/1.0 200 OK\r\nContent-Type: text/plain\r\n\r\nd8:intervali1800e5:peers12:\x7f\x00\x00\x01\x1a\xe1\x0a\x00\x00\x02\x00\x50e",
+        );
+
+        let url = format!("http://127.0.0.1:{port}/announce?passkey=abc");
+        let response = announce(&url, &request(), Duration::from_secs(5)).unwrap();
+        let http_request = server.join().unwrap();
+
+        assert!(http_request.starts_with("GET /announce?passkey=abc&info_hash=%124a%FF"));
+        assert!(http_request.contains(&format!("\r\nHost: 127.0.0.1:{port}\r\n")));
+        assert_eq!(response.interval, 1800);
+        assert_eq!(
+            response.peers,
+            [
+                SocketAddrV4::new(Ipv4Addr::new(127, 0, 0, 1), 6881),
+                SocketAddrV4::new(Ipv4Addr::new(10, 0, 0, 2), 80),
+            ]
+        );
+    }
+
+    #[test]
+    fn announce_http_error_status() {
+        let (port, server) = mock_http_tracker(b"HTTP/1.1 404 Not Found\r\n\r\n");
+        let url = format!("http://127.0.0.1:{port}/announce");
+        let err = announce(&url, &request(), Duration::from_secs(5)).unwrap_err();
+        server.join().unwrap();
+        assert!(matches!(err, TrackerError::HttpStatus(404)));
+    }
+
+    #[test]
+    fn announce_http_timeout() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!(
+            "http://127.0.0.1:{}/announce",
+            listener.local_addr().unwrap().port()
+        );
+
+        let err = announce(&url, &request(), Duration::from_millis(50)).unwrap_err();
+        assert!(matches!(err, TrackerError::Io(_)));
+    }
+
+    #[test]
+    fn announce_udp_mock_tracker() {
+        let tracker = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let url = format!("udp://{}/announce", tracker.local_addr().unwrap());
+
+        let server = std::thread::spawn(move || {
+            let mut buf = [0; 128];
+            let (_, client) = tracker.recv_from(&mut buf).unwrap();
+            let mut reply = vec![0, 0, 0, 0];
+            reply.extend_from_slice(&buf[12..16]);
+            reply.extend_from_slice(&42u64.to_be_bytes());
+            tracker.send_to(&reply, client).unwrap();
+
+            let (_, client) = tracker.recv_from(&mut buf).unwrap();
+            let mut reply = vec![0, 0, 0, 1];
+            reply.extend_from_slice(&buf[12..16]);
+            reply.extend_from_slice(&[0, 0, 0, 60, 0, 0, 0, 0, 0, 0, 0, 1]);
+            reply.extend_from_slice(&[10, 0, 0, 1, 0, 80]);
+            tracker.send_to(&reply, client).unwrap();
+        });
+
+        let response = announce(&url, &request(), Duration::from_secs(5)).unwrap();
+        server.join().unwrap();
+
+        assert_eq!(response.interval, 60);
+        assert_eq!(
+            response.peers,
+            [SocketAddrV4::new(Ipv4Addr::new(10, 0, 0, 1), 80)]
+        );
+    }
+
+    #[test]
+    fn announce_udp_mock_tracker_v6() {
+        let tracker = UdpSocket::bind("[::1]:0").unwrap();
+        let url = format!("udp://{}/announce", tracker.local_addr().unwrap());
+
+        let server = std::thread::spawn(move || {
+            let mut buf = [0; 128];
+            let (_, client) = tracker.recv_from(&mut buf).unwrap();
+            let mut reply = vec![0, 0, 0, 0];
+            reply.extend_from_slice(&buf[12..16]);
+            reply.extend_from_slice(&42u64.to_be_bytes());
+            tracker.send_to(&reply, client).unwrap();
+
+            let (_, client) = tracker.recv_from(&mut buf).unwrap();
+            let mut reply = vec![0, 0, 0, 1];
+            reply.extend_from_slice(&buf[12..16]);
+            reply.extend_from_slice(&[0, 0, 0, 60, 0, 0, 0, 0, 0, 0, 0, 1]);
+            reply.extend_from_slice(&Ipv6Addr::LOCALHOST.octets());
+            reply.extend_from_slice(&80u16.to_be_bytes());
+            tracker.send_to(&reply, client).unwrap();
+        });
+
+        let response = announce(&url, &request(), Duration::from_secs(5)).unwrap();
+        server.join().unwrap();
+
+        assert!(response.peers.is_empty());
+        assert_eq!(
+            response.peers6,
+            [SocketAddrV6::new(Ipv6Addr::LOCALHOST, 80, 0, 0)]
+        );
+    }
+
+    #[test]
+    fn announce_unsupported_scheme() {
+        let err = announce(
+            "https://tracker.example/announce",
+            &request(),
+            Duration::from_secs(1),
+        )
+        .unwrap_err();
+        assert!(matches!(err, TrackerError::UnsupportedScheme(_)));
+    }
+
+    #[test]
+    fn resolve_authority() {
+        assert_eq!(
+            resolve("127.0.0.1:6969", None, Duration::from_secs(1)).unwrap(),
+            "127.0.0.1:6969".parse().unwrap()
+        );
+        assert_eq!(
+            resolve("[::1]", Some(80), Duration::from_secs(1)).unwrap(),
+            "[::1]:80".parse().unwrap()
+        );
+        assert!(matches!(
+            resolve("127.0.0.1", None, Duration::from_secs(1)),
+            Err(TrackerError::InvalidUrl(_))
+        ));
+        assert!(matches!(
+            resolve(":80", None, Duration::from_secs(1)),
+            Err(TrackerError::InvalidUrl(_))
+        ));
+    }
+
+    #[test]
+    fn resolve_host_name() {
+        let addr = resolve("localhost:6969", None, Duration::from_secs(5)).unwrap();
+        assert!(addr.ip().is_loopback());
+        assert_eq!(addr.port(), 6969);
+    }
+}
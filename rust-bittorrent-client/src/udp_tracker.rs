@@ -0,0 +1,559 @@
+use crate::tracker::{parse_compact_peers, parse_compact_peers6, AnnounceRequest, Event};
+use std::{
+    io::{self, ErrorKind},
+    net::{SocketAddrV4, SocketAddrV6, UdpSocket},
+    time::Instant,
+};
+
+const PROTOCOL_ID: u64 = 0x41727101980;
+
+const ACTION_CONNECT: u32 = 0;
+const ACTION_ANNOUNCE: u32 = 1;
+const ACTION_ERROR: u32 = 3;
+
+// Larger than any UDP payload, so replies are never truncated
+const MAX_PACKET_LEN: usize = 65536;
+
+// Sends of a request before giving up, each waiting twice as long as the
+// last for the reply (BEP 15)
+const MAX_ATTEMPTS: u32 = 4;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ConnectRequest {
+    pub transaction_id: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ConnectResponse {
+    pub transaction_id: u32,
+    pub connection_id: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UdpAnnounceRequest {
+    pub connection_id: u64,
+    pub transaction_id: u32,
+    pub key: u32,
+    /// Number of peers wanted, -1 lets the tracker decide
+    pub num_want: i32,
+    pub announce: AnnounceRequest,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UdpAnnounceResponse {
+    pub transaction_id: u32,
+    pub interval: u32,
+    pub leechers: u32,
+    pub seeders: u32,
+    pub peers: Vec<SocketAddrV4>,
+    /// Filled instead of `peers` when the tracker was reached over IPv6
+    pub peers6: Vec<SocketAddrV6>,
+}
+
+#[derive(Debug)]
+pub enum UdpTrackerError {
+    Io(io::Error),
+    TooShort,
+    UnexpectedAction(u32),
+    /// The tracker replied with an error action and this message
+    Tracker(String),
+    InvalidPeers,
+}
+
+impl From<io::Error> for UdpTrackerError {
+    fn from(e: io::Error) -> Self {
+        UdpTrackerError::Io(e)
+    }
+}
+
+impl ConnectRequest {
+    pub fn to_bytes(&self) -> [u8; 16] {
+        let mut out = [0; 16];
+        out[0..8].copy_from_slice(&PROTOCOL_ID.to_be_bytes());
+        out[8..12].copy_from_slice(&ACTION_CONNECT.to_be_bytes());
+        out[12..16].copy_from_slice(&self.transaction_id.to_be_bytes());
+        out
+    }
+}
+
+impl ConnectResponse {
+    pub fn from_bytes(packet: &[u8]) -> Result<Self, UdpTrackerError> {
+        let transaction_id = check_header(packet, ACTION_CONNECT)?;
+        if packet.len() < 16 {
+            return Err(UdpTrackerError::TooShort);
+        }
+
+        Ok(Self {
+            transaction_id,
+            connection_id: read_u64(packet, 8),
+        })
+    }
+}
+
+impl UdpAnnounceRequest {
+    pub fn to_bytes(&self) -> [u8; 98] {
+        let req = &self.announce;
+        let event: u32 = match req.event {
+            None => 0,
+            Some(Event::Completed) => 1,
+            Some(Event::Started) => 2,
+            Some(Event::Stopped) => 3,
+        };
+
+        let mut out = [0; 98];
+        out[0..8].copy_from_slice(&self.connection_id.to_be_bytes());
+        out[8..12].copy_from_slice(&ACTION_ANNOUNCE.to_be_bytes());
+        out[12..16].copy_from_slice(&self.transaction_id.to_be_bytes());
+        out[16..36].copy_from_slice(&req.info_hash);
+        out[36..56].copy_from_slice(&req.peer_id);
+        out[56..64].copy_from_slice(&req.downloaded.to_be_bytes());
+        out[64..72].copy_from_slice(&req.left.to_be_bytes());
+        out[72..80].copy_from_slice(&req.uploaded.to_be_bytes());
+        out[80..84].copy_from_slice(&event.to_be_bytes());
+        // 84..88 is the IP address, zero means the sender's
+        out[88..92].copy_from_slice(&self.key.to_be_bytes());
+        out[92..96].copy_from_slice(&self.num_want.to_be_bytes());
+        out[96..98].copy_from_slice(&req.port.to_be_bytes());
+        out
+    }
+}
+
+impl UdpAnnounceResponse {
+    /// Decodes a reply received over IPv4, with 6-byte peer entries.
+    pub fn from_bytes(packet: &[u8]) -> Result<Self, UdpTrackerError> {
+        Self::parse(packet, false)
+    }
+
+    /// Decodes a reply received over IPv6, with 18-byte peer entries.
+    pub fn from_bytes_v6(packet: &[u8]) -> Result<Self, UdpTrackerError> {
+        Self::parse(packet, true)
+    }
+
+    fn parse(packet: &[u8], ipv6: bool) -> Result<Self, UdpTrackerError> {
+        let transaction_id = check_header(packet, ACTION_ANNOUNCE)?;
+        if packet.len() < 20 {
+            return Err(UdpTrackerError::TooShort);
+        }
+
+        let (peers, peers6) = if ipv6 {
+            let peers6 =
+                parse_compact_peers6(&packet[20..]).ok_or(UdpTrackerError::InvalidPeers)?;
+            (Vec::new(), peers6)
+        } else {
+            let peers = parse_compact_peers(&packet[20..]).ok_or(UdpTrackerError::InvalidPeers)?;
+            (peers, Vec::new())
+        };
+
+        Ok(Self {
+            transaction_id,
+            interval: read_u32(packet, 8),
+            leechers: read_u32(packet, 12),
+            seeders: read_u32(packet, 16),
+            peers,
+            peers6,
+        })
+    }
+}
+
+/// Runs the connect and announce exchange over a socket already connected to
+/// the tracker. Set a read timeout on the socket, UDP gives no other signal
+/// that the tracker is gone. A request that times out is resent up to 3
+/// times, doubling the timeout each time. Replies to earlier copies of a
+/// request, or with another transaction id, are skipped. Peers come back in
+/// `peers6` when the socket is connected over IPv6.
+pub fn announce(
+    socket: &UdpSocket,
+    request: &AnnounceRequest,
+    transaction_id: u32,
+    key: u32,
+) -> Result<UdpAnnounceResponse, UdpTrackerError> {
+    let mut buf = vec![0; MAX_PACKET_LEN];
+
+    let len = send_and_receive(
+        socket,
+        &ConnectRequest { transaction_id }.to_bytes(),
+        &mut buf,
+        ACTION_CONNECT,
+        transaction_id,
+    )?;
+    let connect = ConnectResponse::from_bytes(&buf[..len])?;
+
+    let transaction_id = transaction_id.wrapping_add(1);
+    let announce = UdpAnnounceRequest {
+        connection_id: connect.connection_id,
+        transaction_id,
+        key,
+        num_want: -1,
+        announce: request.clone(),
+    };
+    let len = send_and_receive(
+        socket,
+        &announce.to_bytes(),
+        &mut buf,
+        ACTION_ANNOUNCE,
+        transaction_id,
+    )?;
+    if socket.peer_addr()?.is_ipv6() {
+        UdpAnnounceResponse::from_bytes_v6(&buf[..len])
+    } else {
+        UdpAnnounceResponse::from_bytes(&buf[..len])
+    }
+}
+
+// Sends `packet` until a reply with `action` (or an error) and
+// `transaction_id` arrives or the attempts run out, leaving the socket's read
+// timeout as it was
+fn send_and_receive(
+    socket: &UdpSocket,
+    packet: &[u8],
+    buf: &mut [u8],
+    action: u32,
+    transaction_id: u32,
+) -> Result<usize, UdpTrackerError> {
+    let timeout = socket.read_timeout()?;
+    let mut attempt = 0;
+    let result = loop {
+        let deadline = timeout.map(|timeout| Instant::now() + timeout * 2u32.pow(attempt));
+        match socket
+            .send(packet)
+            .and_then(|_| receive_reply(socket, buf, deadline, action, transaction_id))
+        {
+            Err(e)
+                if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut)
+                    && attempt + 1 < MAX_ATTEMPTS =>
+            {
+                attempt += 1;
+            }
+            result => break result,
+        }
+    };
+    socket.set_read_timeout(timeout)?;
+
+    Ok(result?)
+}
+
+// Reads until a packet matching `action` and `transaction_id`, dropping stale
+// or stray ones, or until `deadline` passes
+fn receive_reply(
+    socket: &UdpSocket,
+    buf: &mut [u8],
+    deadline: Option<Instant>,
+    action: u32,
+    transaction_id: u32,
+) -> io::Result<usize> {
+    loop {
+        if let Some(deadline) = deadline {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                return Err(ErrorKind::TimedOut.into());
+            }
+            socket.set_read_timeout(Some(remaining))?;
+        }
+
+        let len = socket.recv(buf)?;
+        let packet = &buf[..len];
+        if packet.len() >= 8
+            && matches!(read_u32(packet, 0), a if a == action || a == ACTION_ERROR)
+            && read_u32(packet, 4) == transaction_id
+        {
+            return Ok(len);
+        }
+    }
+}
+
+// Returns the transaction id after checking the action
+fn check_header(packet: &[u8], expected: u32) -> Result<u32, UdpTrackerError> {
+    if packet.len() < 8 {
+        return Err(UdpTrackerError::TooShort);
+    }
+
+    match read_u32(packet, 0) {
+        ACTION_ERROR => Err(UdpTrackerError::Tracker(
+            String::from_utf8_lossy(&packet[8..]).into_owned(),
+        )),
+        action if action != expected => Err(UdpTrackerError::UnexpectedAction(action)),
+        _ => Ok(read_u32(packet, 4)),
+    }
+}
+
+fn read_u32(packet: &[u8], at: usize) -> u32 {
+    u32::from_be_bytes(packet[at..at + 4].try_into().unwrap())
+}
+
+fn read_u64(packet: &[u8], at: usize) -> u64 {
+    u64::from_be_bytes(packet[at..at + 8].try_into().unwrap())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::{Ipv4Addr, Ipv6Addr};
+
+    fn request() -> AnnounceRequest {
+        AnnounceRequest {
+            info_hash: [0xaa; 20],
+            peer_id: [0xbb; 20],
+            port: 6881,
+            uploaded: 3,
+            downloaded: 1,
+            left: 2,
+            event: Some(Event::Started),
+        }
+    }
+
+    #[test]
+    fn connect_request_layout() {
+        let bytes = ConnectRequest {
+            transaction_id: 0x01020304,
+        }
+        .to_bytes();
+        assert_eq!(
+            bytes,
+            [0, 0, 0x04, 0x17, 0x27, 0x10, 0x19, 0x80, 0, 0, 0, 0, 1, 2, 3, 4]
+        );
+    }
+
+    #[test]
+    fn connect_response_decode() {
+        let packet = [0, 0, 0, 0, 1, 2, 3, 4, 0, 0, 0, 0, 0, 0, 0x12, 0x34];
+        assert_eq!(
+            ConnectResponse::from_bytes(&packet).unwrap(),
+            ConnectResponse {
+                transaction_id: 0x01020304,
+                connection_id: 0x1234,
+            }
+        );
+    }
+
+    #[test]
+    fn connect_response_error_action() {
+        let mut packet = vec![0, 0, 0, 3, 1, 2, 3, 4];
+        packet.extend_from_slice(b"banned");
+        let err = ConnectResponse::from_bytes(&packet).unwrap_err();
+        assert!(matches!(err, UdpTrackerError::Tracker(message) if message == "banned"));
+    }
+
+    #[test]
+    fn connect_response_too_short() {
+        let err = ConnectResponse::from_bytes(&[0, 0, 0, 0, 1, 2, 3, 4]).unwrap_err();
+        assert!(matches!(err, UdpTrackerError::TooShort));
+    }
+
+    #[test]
+    fn announce_request_layout() {
+        let bytes = UdpAnnounceRequest {
+            connection_id: 0x1122334455667788,
+            transaction_id: 7,
+            key: 9,
+            num_want: -1,
+            announce: request(),
+        }
+        .to_bytes();
+
+        assert_eq!(bytes[0..8], 0x1122334455667788u64.to_be_bytes());
+        assert_eq!(bytes[8..16], [0, 0, 0, 1, 0, 0, 0, 7]);
+        assert_eq!(bytes[16..36], [0xaa; 20]);
+        assert_eq!(bytes[36..56], [0xbb; 20]);
+        assert_eq!(bytes[56..64], 1u64.to_be_bytes());
+        assert_eq!(bytes[64..72], 2u64.to_be_bytes());
+        assert_eq!(bytes[72..80], 3u64.to_be_bytes());
+        assert_eq!(bytes[80..84], [0, 0, 0, 2]);
+        assert_eq!(bytes[84..88], [0; 4]);
+        assert_eq!(bytes[88..92], [0, 0, 0, 9]);
+        assert_eq!(bytes[92..96], [0xff; 4]);
+        assert_eq!(bytes[96..98], [0x1a, 0xe1]);
+    }
+
+    #[test]
+    fn announce_response_decode() {
+        let packet = [
+            0, 0, 0, 1, 0, 0, 0, 7, 0, 0, 0x07, 0x08, 0, 0, 0, 2, 0, 0, 0, 5, 127, 0, 0, 1, 0x1a,
+            0xe1,
+        ];
+        assert_eq!(
+            UdpAnnounceResponse::from_bytes(&packet).unwrap(),
+            UdpAnnounceResponse {
+                transaction_id: 7,
+                interval: 1800,
+                leechers: 2,
+                seeders: 5,
+                peers: vec![SocketAddrV4::new(Ipv4Addr::LOCALHOST, 6881)],
+                peers6: Vec::new(),
+            }
+        );
+    }
+
+    #[test]
+    fn announce_response_decode_v6() {
+        let mut packet = vec![
+            0, 0, 0, 1, 0, 0, 0, 7, 0, 0, 0x07, 0x08, 0, 0, 0, 2, 0, 0, 0, 5,
+        ];
+        packet.extend_from_slice(&Ipv6Addr::LOCALHOST.octets());
+        packet.extend_from_slice(&6881u16.to_be_bytes());
+
+        let response = UdpAnnounceResponse::from_bytes_v6(&packet).unwrap();
+        assert!(response.peers.is_empty());
+        assert_eq!(
+            response.peers6,
+            [SocketAddrV6::new(Ipv6Addr::LOCALHOST, 6881, 0, 0)]
+        );
+
+        packet.pop();
+        let err = UdpAnnounceResponse::from_bytes_v6(&packet).unwrap_err();
+        assert!(matches!(err, UdpTrackerError::InvalidPeers));
+    }
+
+    #[test]
+    fn announce_over_local_socket() {
+        let tracker = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let tracker_addr = tracker.local_addr().unwrap();
+
+        let server = std::thread::spawn(move || {
+            let mut buf = vec![0; MAX_PACKET_LEN];
+
+            let (len, client) = tracker.recv_from(&mut buf).unwrap();
+            assert_eq!(len, 16);
+            let mut reply = vec![0, 0, 0, 0];
+            reply.extend_from_slice(&buf[12..16]);
+            reply.extend_from_slice(&42u64.to_be_bytes());
+            tracker.send_to(&reply, client).unwrap();
+
+            let (len, client) = tracker.recv_from(&mut buf).unwrap();
+            assert_eq!(len, 98);
+            assert_eq!(read_u64(&buf, 0), 42);
+            let mut reply = vec![0, 0, 0, 1];
+            reply.extend_from_slice(&buf[12..16]);
+            reply.extend_from_slice(&[0, 0, 0, 60, 0, 0, 0, 0, 0, 0, 0, 1]);
+            reply.extend_from_slice(&[10, 0, 0, 1, 0, 80]);
+            tracker.send_to(&reply, client).unwrap();
+        });
+
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        socket.connect(tracker_addr).unwrap();
+        socket
+            .set_read_timeout(Some(std::time::Duration::from_secs(5)))
+            .unwrap();
+
+        let response = announce(&socket, &request(), 100, 9).unwrap();
+        server.join().unwrap();
+
+        assert_eq!(response.transaction_id, 101);
+        assert_eq!(response.interval, 60);
+        assert_eq!(
+            response.peers,
+            [SocketAddrV4::new(Ipv4Addr::new(10, 0, 0, 1), 80)]
+        );
+    }
+
+    // Answers a connect with connection id 42, then an announce with `peers`
+    fn reply_to(tracker: &UdpSocket, buf: &mut [u8], peers: usize) {
+        let (_, client) = tracker.recv_from(buf).unwrap();
+        let mut reply = vec![0, 0, 0, 0];
+        reply.extend_from_slice(&buf[12..16]);
+        reply.extend_from_slice(&42u64.to_be_bytes());
+        tracker.send_to(&reply, client).unwrap();
+
+        let (_, client) = tracker.recv_from(buf).unwrap();
+        let mut reply = vec![0, 0, 0, 1];
+        reply.extend_from_slice(&buf[12..16]);
+        reply.extend_from_slice(&[0, 0, 0, 60, 0, 0, 0, 0, 0, 0, 0, 1]);
+        for _ in 0..peers {
+            reply.extend_from_slice(&[10, 0, 0, 1, 0, 80]);
+        }
+        tracker.send_to(&reply, client).unwrap();
+    }
+
+    fn client_for(tracker: &UdpSocket, timeout_ms: u64) -> UdpSocket {
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        socket.connect(tracker.local_addr().unwrap()).unwrap();
+        socket
+            .set_read_timeout(Some(std::time::Duration::from_millis(timeout_ms)))
+            .unwrap();
+        socket
+    }
+
+    #[test]
+    fn announce_retransmits_dropped_request() {
+        let tracker = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let socket = client_for(&tracker, 200);
+
+        let server = std::thread::spawn(move || {
+            let mut buf = vec![0; MAX_PACKET_LEN];
+            // Drop the first connect request
+            tracker.recv_from(&mut buf).unwrap();
+            reply_to(&tracker, &mut buf, 1);
+        });
+
+        let response = announce(&socket, &request(), 100, 9).unwrap();
+        server.join().unwrap();
+
+        assert_eq!(response.peers.len(), 1);
+        assert_eq!(
+            socket.read_timeout().unwrap(),
+            Some(std::time::Duration::from_millis(200))
+        );
+    }
+
+    #[test]
+    fn announce_skips_stale_replies() {
+        let tracker = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let socket = client_for(&tracker, 5000);
+
+        let server = std::thread::spawn(move || {
+            let mut buf = vec![0; MAX_PACKET_LEN];
+            let (_, client) = tracker.recv_from(&mut buf).unwrap();
+            let mut reply = vec![0, 0, 0, 0];
+            reply.extend_from_slice(&buf[12..16]);
+            reply.extend_from_slice(&42u64.to_be_bytes());
+            // A late reply to a retransmitted connect
+            tracker.send_to(&reply, client).unwrap();
+            tracker.send_to(&reply, client).unwrap();
+
+            let (_, client) = tracker.recv_from(&mut buf).unwrap();
+            let mut reply = vec![0, 0, 0, 1];
+            reply.extend_from_slice(&buf[12..16]);
+            reply.extend_from_slice(&[0, 0, 0, 60, 0, 0, 0, 0, 0, 0, 0, 1]);
+            reply.extend_from_slice(&[10, 0, 0, 1, 0, 80]);
+            // A reply to some other announce
+            let mut stray = reply.clone();
+            stray[4..8].copy_from_slice(&7u32.to_be_bytes());
+            tracker.send_to(&stray, client).unwrap();
+            tracker.send_to(&reply, client).unwrap();
+        });
+
+        let response = announce(&socket, &request(), 100, 9).unwrap();
+        server.join().unwrap();
+
+        assert_eq!(response.transaction_id, 101);
+        assert_eq!(response.peers.len(), 1);
+    }
+
+    #[test]
+    fn announce_gives_up_after_retries() {
+        let tracker = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let socket = client_for(&tracker, 10);
+
+        let err = announce(&socket, &request(), 100, 9).unwrap_err();
+        assert!(matches!(err, UdpTrackerError::Io(_)));
+
+        let mut buf = [0; 16];
+        for _ in 0..MAX_ATTEMPTS {
+            assert_eq!(tracker.recv(&mut buf).unwrap(), 16);
+        }
+    }
+
+    #[test]
+    fn announce_large_peer_list() {
+        let tracker = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let socket = client_for(&tracker, 5000);
+
+        let server = std::thread::spawn(move || {
+            let mut buf = vec![0; MAX_PACKET_LEN];
+            reply_to(&tracker, &mut buf, 2000);
+        });
+
+        let response = announce(&socket, &request(), 100, 9).unwrap();
+        server.join().unwrap();
+        assert_eq!(response.peers.len(), 2000);
+    }
+}
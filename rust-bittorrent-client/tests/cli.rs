@@ -0,0 +1,100 @@
+use std::{path::PathBuf, process::Command};
+
+fn run(args: &[&str]) -> (bool, String, String) {
+    let output = Command::new(env!("CARGO_BIN_EXE_rust-bittorrent-client"))
+        .args(args)
+        .output()
+        .unwrap();
+    (
+        output.status.success(),
+        String::from_utf8(output.stdout).unwrap(),
+        String::from_utf8(output.stderr).unwrap(),
+    )
+}
+
+fn fixture(name: &str, contents: &[u8]) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("{}-{name}", std::process::id()));
+    std::fs::write(&path, contents).unwrap();
+    path
+}
+
+#[test]
+fn info_single_file() {
+    let (ok, stdout, _) = run(&["info", "archlinux-2022.11.01-x86_64.iso.torrent"]);
+    assert!(ok);
+
+    let lines: Vec<&str> = stdout.lines().collect();
+    assert!(lines.contains(&"name:         archlinux-2022.11.01-x86_64.iso"));
+    assert!(lines.contains(&"size:         782.3 MiB (820326400 bytes)"));
+    assert!(lines.contains(&"piece length: 512.0 KiB"));
+    assert!(lines.contains(&"pieces:       1565"));
+    assert!(lines.contains(&"info hash:    9670eb81865d58a1ed397a461f515c5383d883c6"));
+    assert!(lines.contains(&"trackers:     none"));
+}
+
+#[test]
+fn info_multi_file_with_trackers() {
+    let mut torrent = b"d8:announce17:http://a/announce13:announce-listll17:http://a/announce\
+        el10:udp://b:80ee4:infod5:filesld6:lengthi1024e4:pathl5:a.binee\
+        d6:lengthi5e4:pathl3:sub5:b.txteee4:name3:dir12:piece lengthi1024e6:pieces40:"
+        .to_vec();
+    torrent.extend_from_slice(&[0; 40]);
+    torrent.extend_from_slice(b"ee");
+    let path = fixture("multi.torrent", &torrent);
+
+    let (ok, stdout, stderr) = run(&["info", path.to_str().unwrap()]);
+    std::fs::remove_file(&path).unwrap();
+    assert!(ok, "{stderr}");
+
+    assert!(stdout.contains("size:         1.0 KiB (1029 bytes)\n"));
+    assert!(stdout.contains("trackers:\n  http://a/announce\n  udp://b:80\n"));
+    assert!(stdout.contains("files:\n     1.0 KiB  a.bin\n         5 B  sub/b.txt\n"));
+}
+
+#[test]
+fn info_lists_announce_missing_from_tiers() {
+    let mut torrent = b"d8:announce17:http://a/announce13:announce-listll10:udp://b:80ee\
+        4:infod6:lengthi5e4:name1:a12:piece lengthi1024e6:pieces20:"
+        .to_vec();
+    torrent.extend_from_slice(&[0; 20]);
+    torrent.extend_from_slice(b"ee");
+    let path = fixture("announce.torrent", &torrent);
+
+    let (ok, stdout, stderr) = run(&["info", path.to_str().unwrap()]);
+    std::fs::remove_file(&path).unwrap();
+    assert!(ok, "{stderr}");
+    assert!(stdout.contains("trackers:\n  http://a/announce\n  udp://b:80\n"));
+}
+
+#[test]
+fn info_missing_file() {
+    let (ok, stdout, stderr) = run(&["info", "does-not-exist.torrent"]);
+    assert!(!ok);
+    assert!(stdout.is_empty());
+    assert!(stderr.starts_with("cannot read does-not-exist.torrent"));
+}
+
+#[test]
+fn info_length_overflow() {
+    let path = fixture(
+        "overflow.torrent",
+        b"d4:infod5:filesld6:lengthi9223372036854775807e4:pathl1:aee\
+        d6:lengthi9223372036854775807e4:pathl1:beee4:name3:dir12:piece lengthi1024e6:pieces0:ee",
+    );
+    let (ok, _, stderr) = run(&["info", path.to_str().unwrap()]);
+    std::fs::remove_file(&path).unwrap();
+    assert!(!ok);
+    assert!(stderr.contains("total length overflows"), "{stderr}");
+}
+
+#[test]
+fn info_malformed_file() {
+    let path = fixture("malformed.torrent", b"d4:info");
+    let (ok, _, stderr) = run(&["info", path.to_str().unwrap()]);
+    std::fs::remove_file(&path).unwrap();
+    assert!(!ok);
+    assert!(
+        stderr.contains("invalid bencode at offset 7: unexpected end of input"),
+        "{stderr}"
+    );
+}